@@ -1,38 +1,467 @@
 use std::env::{self, consts};
+use std::fs;
 use std::path::{Path, PathBuf};
 
-fn main() {
+/// The default install location of the DOCA SDK
+const DOCA_DEFAULT_HOME: &str = "/opt/mellanox/doca";
+
+/// The DOCA libraries enabled by the cargo features, as
+/// (feature, library, pkg-config package, define used in `wrapper.h`)
+type DocaLib = (bool, &'static str, &'static str, Option<&'static str>);
+
+const DOCA_LIBS: [DocaLib; 12] = [
+    (true, "doca_common", "doca-common", None),
+    (
+        cfg!(feature = "dma"),
+        "doca_dma",
+        "doca-dma",
+        Some("DOCA_SYS_DMA"),
+    ),
+    (
+        cfg!(feature = "comm-channel"),
+        "doca_comm_channel",
+        "doca-comm-channel",
+        Some("DOCA_SYS_COMM_CHANNEL"),
+    ),
+    (
+        cfg!(feature = "compress"),
+        "doca_compress",
+        "doca-compress",
+        Some("DOCA_SYS_COMPRESS"),
+    ),
+    (
+        cfg!(feature = "sha"),
+        "doca_sha",
+        "doca-sha",
+        Some("DOCA_SYS_SHA"),
+    ),
+    (
+        cfg!(feature = "regex"),
+        "doca_regex",
+        "doca-regex",
+        Some("DOCA_SYS_REGEX"),
+    ),
+    (
+        cfg!(feature = "erasure-coding"),
+        "doca_erasure_coding",
+        "doca-erasure-coding",
+        Some("DOCA_SYS_ERASURE_CODING"),
+    ),
+    (
+        cfg!(feature = "aes-gcm"),
+        "doca_aes_gcm",
+        "doca-aes-gcm",
+        Some("DOCA_SYS_AES_GCM"),
+    ),
+    (
+        cfg!(feature = "rdma"),
+        "doca_rdma",
+        "doca-rdma",
+        Some("DOCA_SYS_RDMA"),
+    ),
+    (
+        cfg!(feature = "eth"),
+        "doca_eth",
+        "doca-eth",
+        Some("DOCA_SYS_ETH"),
+    ),
+    (
+        cfg!(feature = "flow"),
+        "doca_flow",
+        "doca-flow",
+        Some("DOCA_SYS_FLOW"),
+    ),
+    (
+        cfg!(feature = "telemetry-diag"),
+        "doca_telemetry_diag",
+        "doca-telemetry-diag",
+        Some("DOCA_SYS_TELEMETRY_DIAG"),
+    ),
+];
+
+/// Iterate over the DOCA libraries enabled by the cargo features
+fn enabled_libs() -> impl Iterator<Item = (&'static str, &'static str, Option<&'static str>)> {
+    DOCA_LIBS
+        .into_iter()
+        .filter(|(enabled, ..)| *enabled)
+        .map(|(_, lib, pkg, define)| (lib, pkg, define))
+}
+
+/// Where the DOCA headers and libraries are installed
+struct DocaInstall {
+    include_dirs: Vec<PathBuf>,
+    lib_dirs: Vec<PathBuf>,
+}
+
+/// The version of the DOCA SDK, read from `doca_version.h`
+#[cfg(feature = "regenerate-bindings")]
+struct DocaVersion {
+    major: u32,
+    minor: u32,
+    patch: u32,
+}
+
+/// Find the DOCA SDK in the following order:
+/// 1. `DOCA_INCLUDE_DIR` / `DOCA_LIB_DIR`, each one overrides a single directory;
+/// 2. `DOCA_HOME`, with the usual `include` and `lib/{arch}-linux-gnu` layout;
+/// 3. `pkg-config` with the packages of the enabled libraries;
+/// 4. the default location `/opt/mellanox/doca`.
+fn find_doca() -> DocaInstall {
+    for var in ["DOCA_HOME", "DOCA_INCLUDE_DIR", "DOCA_LIB_DIR"] {
+        println!("cargo:rerun-if-env-changed={}", var);
+    }
+
     let arch = consts::ARCH;
+    let home = env::var_os("DOCA_HOME").map(PathBuf::from);
+    let include_dir = env::var_os("DOCA_INCLUDE_DIR").map(PathBuf::from);
+    let lib_dir = env::var_os("DOCA_LIB_DIR").map(PathBuf::from);
+
+    let mut install = if home.is_none() && (include_dir.is_none() || lib_dir.is_none()) {
+        find_doca_pkg_config().unwrap_or_else(|| {
+            let home = Path::new(DOCA_DEFAULT_HOME);
+            DocaInstall {
+                include_dirs: vec![home.join("include")],
+                lib_dirs: vec![home.join(format!("lib/{}-linux-gnu", arch))],
+            }
+        })
+    } else {
+        let home = home.unwrap_or_else(|| PathBuf::from(DOCA_DEFAULT_HOME));
+        DocaInstall {
+            include_dirs: vec![home.join("include")],
+            lib_dirs: vec![home.join(format!("lib/{}-linux-gnu", arch))],
+        }
+    };
+
+    if let Some(include_dir) = include_dir {
+        install.include_dirs = vec![include_dir];
+    }
+    if let Some(lib_dir) = lib_dir {
+        install.lib_dirs = vec![lib_dir];
+    }
+
+    install
+}
+
+/// Ask `pkg-config` for the DOCA packages
+fn find_doca_pkg_config() -> Option<DocaInstall> {
+    let mut install = DocaInstall {
+        include_dirs: Vec::new(),
+        lib_dirs: Vec::new(),
+    };
+
+    for (_, name, _) in enabled_libs() {
+        let lib = pkg_config::Config::new()
+            .cargo_metadata(false)
+            .probe(name)
+            .ok()?;
+
+        for dir in lib.include_paths {
+            if !install.include_dirs.contains(&dir) {
+                install.include_dirs.push(dir);
+            }
+        }
+        for dir in lib.link_paths {
+            if !install.lib_dirs.contains(&dir) {
+                install.lib_dirs.push(dir);
+            }
+        }
+    }
+
+    Some(install)
+}
+
+/// Read the version of the SDK from `doca_version.h`
+#[cfg(feature = "regenerate-bindings")]
+fn detect_version(install: &DocaInstall) -> DocaVersion {
+    let header = install
+        .include_dirs
+        .iter()
+        .map(|dir| dir.join("doca_version.h"))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| {
+            panic!(
+                "doca is not available in this machine, `doca_version.h` is not found in {:?}. \
+                 Set `DOCA_HOME` or `DOCA_INCLUDE_DIR` to the DOCA install location",
+                install.include_dirs
+            )
+        });
+    println!("cargo:rerun-if-changed={}", header.display());
+
+    let content = fs::read_to_string(&header).expect("Could not read doca_version.h");
+    let define = |name: &str| -> Option<u32> {
+        content.lines().find_map(|line| {
+            let mut tokens = line.split_whitespace();
+            match (tokens.next(), tokens.next(), tokens.next()) {
+                (Some("#define"), Some(n), Some(v)) if n == name => {
+                    v.trim_matches(|c| c == '(' || c == ')').parse().ok()
+                }
+                _ => None,
+            }
+        })
+    };
+
+    DocaVersion {
+        major: define("DOCA_VER_MAJOR").expect("DOCA_VER_MAJOR is not found in doca_version.h"),
+        minor: define("DOCA_VER_MINOR").expect("DOCA_VER_MINOR is not found in doca_version.h"),
+        patch: define("DOCA_VER_PATCH").unwrap_or(0),
+    }
+}
+
+/// The DOCA releases the crate has bindings for
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DocaRelease {
+    V1_5,
+    V2X,
+}
+
+impl DocaRelease {
+    /// The name used by the cfg flag and the pre-generated bindings file
+    fn name(self) -> &'static str {
+        match self {
+            DocaRelease::V1_5 => "doca_1_5",
+            DocaRelease::V2X => "doca_2_x",
+        }
+    }
+
+    /// The release selected by the `doca-1-5` / `doca-2-x` features.
+    /// `doca-2-x` takes precedence, so it can be enabled on top of the default `doca-1-5`.
+    fn from_features() -> Self {
+        let v1_5 = env::var_os("CARGO_FEATURE_DOCA_1_5").is_some();
+        let v2_x = env::var_os("CARGO_FEATURE_DOCA_2_X").is_some();
+        match (v1_5, v2_x) {
+            (_, true) => DocaRelease::V2X,
+            (true, false) => DocaRelease::V1_5,
+            (false, false) => {
+                panic!("one of the features `doca-1-5` and `doca-2-x` should be enabled")
+            }
+        }
+    }
+
+    /// The release matching the version read from the headers
+    #[cfg(feature = "regenerate-bindings")]
+    fn from_version(version: &DocaVersion) -> Self {
+        match (version.major, version.minor) {
+            (1, 5) => DocaRelease::V1_5,
+            (2, _) => DocaRelease::V2X,
+            (major, minor) => panic!(
+                "DOCA {}.{} is not supported, rust-doca supports DOCA 1.5 and 2.x",
+                major, minor
+            ),
+        }
+    }
+
+    /// The committed bindings of the release
+    fn pregenerated_path(self) -> PathBuf {
+        PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap())
+            .join("bindings")
+            .join(format!("{}.rs", self.name()))
+    }
+}
+
+fn main() {
+    // In `no-link` mode (and on docs.rs) only the bindings are needed,
+    // so `cargo check` and `cargo doc` work without the SDK.
+    println!("cargo:rerun-if-env-changed=DOCS_RS");
+    let no_link =
+        env::var_os("CARGO_FEATURE_NO_LINK").is_some() || env::var_os("DOCS_RS").is_some();
+
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap()).join("bindings.rs");
+
+    let release = DocaRelease::from_features();
+    let pregenerated = release.pregenerated_path();
+    println!("cargo:rerun-if-changed={}", pregenerated.display());
+
+    #[cfg(feature = "regenerate-bindings")]
+    {
+        let install = find_doca();
+        let version = detect_version(&install);
+        let installed = DocaRelease::from_version(&version);
+
+        if installed != release {
+            panic!(
+                "the installed DOCA {}.{} does not match the selected release `{}`, \
+                 enable the feature of the installed release instead",
+                version.major,
+                version.minor,
+                release.name()
+            );
+        }
+
+        generate_bindings(&install, &version, &out_path);
+
+        // Refresh the committed bindings if asked to
+        println!("cargo:rerun-if-env-changed=DOCA_SYS_UPDATE_BINDINGS");
+        if env::var_os("DOCA_SYS_UPDATE_BINDINGS").is_some() {
+            fs::copy(&out_path, &pregenerated)
+                .expect("Could not update the pre-generated bindings");
+        }
+
+        println!("cargo:version_major={}", version.major);
+        println!("cargo:version_minor={}", version.minor);
+        println!("cargo:version_patch={}", version.patch);
+
+        if !no_link {
+            link_doca(&install);
+        }
+    }
+
+    #[cfg(not(feature = "regenerate-bindings"))]
+    {
+        if !pregenerated.is_file() {
+            panic!(
+                "the pre-generated bindings of `{}` are missing at {}. Generate them on a \
+                 machine with the DOCA SDK and libclang, see doca-sys/bindings/README.md, \
+                 or enable the `regenerate-bindings` feature to run bindgen at build time",
+                release.name(),
+                pregenerated.display()
+            );
+        }
+        fs::copy(&pregenerated, &out_path).expect("Could not copy the pre-generated bindings");
+
+        if !no_link {
+            link_doca(&find_doca());
+        }
+    }
+
+    // Replace the extern functions by shims resolving the symbols at runtime
+    #[cfg(feature = "dlopen")]
+    dlopen_shims(&out_path);
+
+    // Tell the crates which SDK release the bindings are generated for,
+    // exported to the dependent crates as `DEP_DOCA_RELEASE`
+    println!("cargo:rustc-check-cfg=cfg(doca_1_5)");
+    println!("cargo:rustc-check-cfg=cfg(doca_2_x)");
+    println!("cargo:rustc-cfg={}", release.name());
+    println!("cargo:release={}", release.name());
+}
+
+/// Emit the link directives of the DOCA libraries
+fn link_doca(install: &DocaInstall) {
     println!(
-        "cargo:rustc-link-search=native=/opt/mellanox/doca/lib/{}-linux-gnu",
-        arch
+        "cargo:include={}",
+        env::join_paths(&install.include_dirs)
+            .unwrap()
+            .to_string_lossy()
     );
-    println!("cargo:rustc-link-lib=doca_dma");
-    println!("cargo:rustc-link-lib=doca_comm_channel");
-    println!("cargo:rustc-link-lib=doca_common");
 
+    for dir in &install.lib_dirs {
+        println!("cargo:rustc-link-search=native={}", dir.display());
+    }
+    for (lib, ..) in enabled_libs() {
+        println!("cargo:rustc-link-lib={}", lib);
+    }
+}
+
+/// Generate the bindings from the installed headers with bindgen
+#[cfg(feature = "regenerate-bindings")]
+fn generate_bindings(install: &DocaInstall, version: &DocaVersion, out_path: &Path) {
     // Tell cargo to invalidate the built crate whenever the wrapper changes
     println!("cargo:rerun-if-changed=wrapper.h");
 
-    // Check whether doca is available in this machine or not
-    assert!(
-        Path::new("/opt/mellanox/doca").is_dir(),
-        "doca is not available in this machine"
-    );
+    let mut builder = bindgen::Builder::default().header("wrapper.h");
+    for dir in &install.include_dirs {
+        builder = builder.clang_arg(format!("-I{}", dir.display()));
+    }
+    if version.major >= 2 {
+        // Part of the DOCA 2.x API is still marked as experimental
+        builder = builder.clang_arg("-DDOCA_ALLOW_EXPERIMENTAL_API");
+    }
+    // Only include the headers of the enabled libraries
+    for define in enabled_libs().filter_map(|(_, _, define)| define) {
+        builder = builder.clang_arg(format!("-D{}", define));
+    }
+
+    #[cfg(feature = "dma")]
+    {
+        builder = builder
+            .allowlist_type("doca_dma_.*")
+            .allowlist_function("doca_dma_.*");
+    }
+
+    #[cfg(feature = "comm-channel")]
+    {
+        builder = builder
+            .allowlist_type("doca_comm_channel_.*")
+            .allowlist_function("doca_comm_channel_.*");
+    }
 
-    // First we build a `util.a` for function `parse_pci_addr` to use
-    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+    #[cfg(feature = "compress")]
+    {
+        builder = builder
+            .allowlist_type("doca_compress_.*")
+            .allowlist_function("doca_compress_.*");
+    }
+
+    #[cfg(feature = "sha")]
+    {
+        builder = builder
+            .allowlist_type("doca_sha_.*")
+            .allowlist_function("doca_sha_.*");
+    }
+
+    #[cfg(feature = "regex")]
+    {
+        builder = builder
+            .allowlist_type("doca_regex_.*")
+            .allowlist_function("doca_regex_.*");
+    }
+
+    #[cfg(feature = "erasure-coding")]
+    {
+        builder = builder
+            .allowlist_type("doca_ec_.*")
+            .allowlist_function("doca_ec_.*");
+    }
+
+    #[cfg(feature = "aes-gcm")]
+    {
+        builder = builder
+            .allowlist_type("doca_aes_gcm_.*")
+            .allowlist_function("doca_aes_gcm_.*");
+    }
+
+    #[cfg(feature = "rdma")]
+    {
+        builder = builder
+            .allowlist_type("doca_rdma_.*")
+            .allowlist_function("doca_rdma_.*")
+            // the RDMA permissions of DOCA 2.x
+            .allowlist_type("doca_access_flag");
+    }
+
+    #[cfg(feature = "eth")]
+    {
+        builder = builder
+            .allowlist_type("doca_eth_rxq_.*")
+            .allowlist_function("doca_eth_rxq_.*")
+            .allowlist_type("doca_eth_txq_.*")
+            .allowlist_function("doca_eth_txq_.*");
+    }
+
+    #[cfg(feature = "flow")]
+    {
+        builder = builder
+            .allowlist_type("doca_flow_.*")
+            .allowlist_function("doca_flow_.*");
+    }
+
+    #[cfg(feature = "telemetry-diag")]
+    {
+        builder = builder
+            .allowlist_type("doca_telemetry_diag_.*")
+            .allowlist_function("doca_telemetry_diag_.*");
+    }
 
     // generate bindings based on the wrapper header
-    let bindings = bindgen::Builder::default()
-        .header("wrapper.h")
-        .clang_arg("-I/opt/mellanox/doca/include")
+    let bindings = builder
         .generate_comments(false)
         .allowlist_function("doca_dev_.*")
         .allowlist_function("doca_devinfo_.*")
         // DOCA_DEV part
         .allowlist_type("doca_dev")
         .allowlist_type("doca_devinfo")
+        .allowlist_var("DOCA_DEVINFO_.*")
+        .allowlist_type("doca_dev_rep_filter")
         // DOCA_MMAP part
         .allowlist_function("doca_mmap_.*")
         .allowlist_type("doca_mmap")
@@ -47,16 +476,17 @@ fn main() {
         .allowlist_type("doca_job_.*")
         .allowlist_function("doca_workq_.*")
         .allowlist_function("doca_ctx_.*")
+        // DOCA_PE part, since DOCA 2.x
+        .allowlist_type("doca_data")
+        .allowlist_type("doca_pe")
+        .allowlist_function("doca_pe_.*")
+        .allowlist_type("doca_task")
+        .allowlist_function("doca_task_.*")
+        .allowlist_type("doca_ctx_states")
         // DOCA_BUF part
         .allowlist_type("doca_buf")
         .allowlist_function("doca_buf_.*")
-        // DOCA_DMA part
-        .allowlist_type("doca_dma_.*")
-        .allowlist_function("doca_dma_.*")
         .allowlist_type("doca_pci_bdf")
-        // DOCA_COMM_CHAN part
-        .allowlist_type("doca_comm_channel_.*")
-        .allowlist_function("doca_comm_channel_.*")
         .derive_default(true)
         .derive_debug(true)
         .prepend_enum_name(false)
@@ -68,6 +498,69 @@ fn main() {
         .expect("Unable to generate bindings");
 
     bindings
-        .write_to_file(out_path.join("bindings.rs"))
+        .write_to_file(out_path)
         .expect("Could not write bindings");
 }
+
+/// Rewrite every `extern "C"` function of the bindings into a shim with the same
+/// signature, which calls the function resolved by `doca_sys::dlopen`.
+/// Variadic functions can not be forwarded and are dropped.
+#[cfg(feature = "dlopen")]
+fn dlopen_shims(bindings_path: &Path) {
+    use quote::{format_ident, quote};
+
+    let content = fs::read_to_string(bindings_path).expect("Could not read bindings");
+    let mut file = syn::parse_file(&content).expect("Could not parse bindings");
+
+    let mut items = Vec::with_capacity(file.items.len());
+    for item in file.items.drain(..) {
+        let foreign = match item {
+            syn::Item::ForeignMod(foreign) => foreign,
+            item => {
+                items.push(item);
+                continue;
+            }
+        };
+
+        for foreign_item in foreign.items {
+            let func = match foreign_item {
+                syn::ForeignItem::Fn(func) if func.sig.variadic.is_none() => func,
+                syn::ForeignItem::Fn(_) => continue,
+                other => panic!(
+                    "unexpected foreign item in the bindings: {}",
+                    quote!(#other)
+                ),
+            };
+
+            let attrs = &func.attrs;
+            let vis = &func.vis;
+            let sig = &func.sig;
+            let name = &sig.ident;
+            let output = &sig.output;
+            let symbol = proc_macro2::Literal::byte_string(format!("{}\0", name).as_bytes());
+
+            let mut arg_names = Vec::new();
+            let mut arg_types = Vec::new();
+            for (i, arg) in sig.inputs.iter().enumerate() {
+                if let syn::FnArg::Typed(arg) = arg {
+                    arg_names.push(format_ident!("arg{}", i));
+                    arg_types.push(arg.ty.clone());
+                }
+            }
+
+            let shim = quote! {
+                #(#attrs)*
+                #vis unsafe fn #name(#(#arg_names: #arg_types),*) #output {
+                    static SYMBOL: crate::dlopen::Symbol = crate::dlopen::Symbol::new(#symbol);
+                    let f: unsafe extern "C" fn(#(#arg_types),*) #output =
+                        ::core::mem::transmute(SYMBOL.get());
+                    f(#(#arg_names),*)
+                }
+            };
+            items.push(syn::parse2(shim).expect("Could not generate the dlopen shim"));
+        }
+    }
+    file.items = items;
+
+    fs::write(bindings_path, prettyplease::unparse(&file)).expect("Could not write bindings");
+}
@@ -0,0 +1,171 @@
+//! Resolve the DOCA symbols at runtime instead of linking against the libraries.
+//!
+//! With the `dlopen` feature every `extern "C"` function of the bindings is
+//! replaced by a shim with the same signature, which looks the symbol up in
+//! the DOCA libraries on its first call. The libraries are opened once,
+//! the first time [`load`] (or any shim) is called.
+//!
+//! Calling a shim while the libraries are not available panics,
+//! so callers should check [`is_available`] before using any DOCA API.
+
+use std::ffi::c_void;
+use std::fmt;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::OnceLock;
+
+use libloading::Library;
+
+/// The versioned soname of a DOCA library of the selected release
+macro_rules! soname {
+    ($name:literal) => {
+        if cfg!(feature = "doca-2-x") {
+            concat!($name, ".so.2")
+        } else {
+            concat!($name, ".so.1")
+        }
+    };
+}
+
+/// The DOCA libraries to open, `libdoca_common` first as the others depend on it.
+/// Each one is tried with the development name, then with the versioned soname.
+const LIBRARIES: &[&[&str]] = &[
+    &["libdoca_common.so", soname!("libdoca_common")],
+    #[cfg(feature = "dma")]
+    &["libdoca_dma.so", soname!("libdoca_dma")],
+    #[cfg(feature = "comm-channel")]
+    &["libdoca_comm_channel.so", soname!("libdoca_comm_channel")],
+    #[cfg(feature = "compress")]
+    &["libdoca_compress.so", soname!("libdoca_compress")],
+    #[cfg(feature = "sha")]
+    &["libdoca_sha.so", soname!("libdoca_sha")],
+    #[cfg(feature = "regex")]
+    &["libdoca_regex.so", soname!("libdoca_regex")],
+    #[cfg(feature = "erasure-coding")]
+    &["libdoca_erasure_coding.so", soname!("libdoca_erasure_coding")],
+    #[cfg(feature = "aes-gcm")]
+    &["libdoca_aes_gcm.so", soname!("libdoca_aes_gcm")],
+    #[cfg(feature = "rdma")]
+    &["libdoca_rdma.so", soname!("libdoca_rdma")],
+    #[cfg(feature = "eth")]
+    &["libdoca_eth.so", soname!("libdoca_eth")],
+    #[cfg(feature = "flow")]
+    &["libdoca_flow.so", soname!("libdoca_flow")],
+    #[cfg(feature = "telemetry-diag")]
+    &["libdoca_telemetry_diag.so", soname!("libdoca_telemetry_diag")],
+];
+
+/// The install location of the DOCA SDK, its libraries are tried
+/// when the dynamic loader can not find them by itself
+const DOCA_DEFAULT_HOME: &str = "/opt/mellanox/doca";
+
+static LOADED: OnceLock<Result<Vec<Library>, LoadError>> = OnceLock::new();
+
+/// The DOCA libraries could not be opened
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadError {
+    library: &'static str,
+    reason: String,
+}
+
+impl LoadError {
+    /// The library that could not be opened, e.g. `libdoca_common.so`
+    pub fn library(&self) -> &str {
+        self.library
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "could not load {}: {}", self.library, self.reason)
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Open one DOCA library, trying every name in the default search path
+/// and then in the DOCA install location.
+fn open(names: &[&'static str]) -> Result<Library, LoadError> {
+    let mut reason = String::new();
+    for name in names {
+        let installed = format!(
+            "{}/lib/{}-linux-gnu/{}",
+            DOCA_DEFAULT_HOME,
+            std::env::consts::ARCH,
+            name
+        );
+        for path in [name.to_string(), installed] {
+            match unsafe { Library::new(&path) } {
+                Ok(lib) => return Ok(lib),
+                Err(e) => reason = e.to_string(),
+            }
+        }
+    }
+
+    Err(LoadError {
+        library: names[0],
+        reason,
+    })
+}
+
+/// Return the opened DOCA libraries, open them on the first call
+fn libraries() -> &'static Result<Vec<Library>, LoadError> {
+    LOADED.get_or_init(|| LIBRARIES.iter().map(|names| open(names)).collect())
+}
+
+/// Open the DOCA libraries if they are not opened yet.
+///
+/// The result is cached, a failed load is not retried.
+pub fn load() -> Result<(), LoadError> {
+    libraries().as_ref().map(|_| ()).map_err(Clone::clone)
+}
+
+/// Check whether the DOCA libraries can be loaded on this machine.
+pub fn is_available() -> bool {
+    load().is_ok()
+}
+
+/// A DOCA function resolved on its first use, used by the generated shims
+#[doc(hidden)]
+pub struct Symbol {
+    /// The nul-terminated name of the symbol
+    name: &'static [u8],
+    addr: AtomicPtr<c_void>,
+}
+
+impl Symbol {
+    /// Create an unresolved symbol
+    pub const fn new(name: &'static [u8]) -> Self {
+        Self {
+            name,
+            addr: AtomicPtr::new(std::ptr::null_mut()),
+        }
+    }
+
+    /// Return the address of the symbol, resolve it if needed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the libraries can not be loaded or none of them exports the symbol.
+    pub fn get(&self) -> *mut c_void {
+        let addr = self.addr.load(Ordering::Acquire);
+        if !addr.is_null() {
+            return addr;
+        }
+
+        let libs = match libraries() {
+            Ok(libs) => libs,
+            Err(e) => panic!("DOCA is not available ({}), check `is_available` first", e),
+        };
+
+        let addr = libs
+            .iter()
+            .find_map(|lib| unsafe { lib.get::<*mut c_void>(self.name).ok().map(|sym| *sym) })
+            .unwrap_or_else(|| {
+                let name = String::from_utf8_lossy(&self.name[..self.name.len() - 1]);
+                panic!("DOCA symbol `{}` is not found", name)
+            });
+
+        self.addr.store(addr, Ordering::Release);
+        addr
+    }
+}
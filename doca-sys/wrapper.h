@@ -1,8 +1,62 @@
+#include <doca_version.h>
 #include <doca_types.h>
 #include <doca_dev.h>
 #include <doca_mmap.h>
 #include <doca_ctx.h>
 #include <doca_buf_inventory.h>
 #include <doca_buf.h>
+#if DOCA_VER_MAJOR >= 2
+#include <doca_pe.h>
+#endif
+
+/* The optional libraries, defined by build.rs according to the cargo features */
+#ifdef DOCA_SYS_DMA
 #include <doca_dma.h>
-#include <doca_comm_channel.h>
\ No newline at end of file
+#endif
+
+#ifdef DOCA_SYS_COMM_CHANNEL
+#include <doca_comm_channel.h>
+#endif
+
+#ifdef DOCA_SYS_COMPRESS
+#include <doca_compress.h>
+#endif
+
+#ifdef DOCA_SYS_SHA
+#include <doca_sha.h>
+#endif
+
+#ifdef DOCA_SYS_REGEX
+#include <doca_regex.h>
+#endif
+
+/* Erasure Coding is only available since DOCA 2.0 */
+#if defined(DOCA_SYS_ERASURE_CODING) && DOCA_VER_MAJOR >= 2
+#include <doca_erasure_coding.h>
+#endif
+
+/* AES-GCM is only available since DOCA 2.5 */
+#if defined(DOCA_SYS_AES_GCM) && (DOCA_VER_MAJOR > 2 || (DOCA_VER_MAJOR == 2 && DOCA_VER_MINOR >= 5))
+#include <doca_aes_gcm.h>
+#endif
+
+/* RDMA is only available since DOCA 2.0 */
+#if defined(DOCA_SYS_RDMA) && DOCA_VER_MAJOR >= 2
+#include <doca_rdma.h>
+#endif
+
+/* The Ethernet queues are only available since DOCA 2.5 */
+#if defined(DOCA_SYS_ETH) && (DOCA_VER_MAJOR > 2 || (DOCA_VER_MAJOR == 2 && DOCA_VER_MINOR >= 5))
+#include <doca_eth_rxq.h>
+#include <doca_eth_txq.h>
+#endif
+
+/* The configuration objects of DOCA Flow are only available since DOCA 2.7 */
+#if defined(DOCA_SYS_FLOW) && (DOCA_VER_MAJOR > 2 || (DOCA_VER_MAJOR == 2 && DOCA_VER_MINOR >= 7))
+#include <doca_flow.h>
+#endif
+
+/* DOCA Telemetry Diagnostics is only available since DOCA 2.9 */
+#if defined(DOCA_SYS_TELEMETRY_DIAG) && (DOCA_VER_MAJOR > 2 || (DOCA_VER_MAJOR == 2 && DOCA_VER_MINOR >= 9))
+#include <doca_telemetry_diag.h>
+#endif
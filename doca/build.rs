@@ -0,0 +1,18 @@
+use std::env;
+
+fn main() {
+    // `doca-sys` exports the DOCA release its bindings are generated for,
+    // turn it into the same cfg flags for this crate.
+    println!("cargo:rustc-check-cfg=cfg(doca_1_5)");
+    println!("cargo:rustc-check-cfg=cfg(doca_2_x)");
+
+    let release = env::var("DEP_DOCA_RELEASE").unwrap_or_default();
+
+    match release.as_str() {
+        "doca_1_5" | "doca_2_x" => println!("cargo:rustc-cfg={}", release),
+        _ => println!(
+            "cargo:warning=unknown DOCA release `{}` reported by doca-sys",
+            release
+        ),
+    }
+}
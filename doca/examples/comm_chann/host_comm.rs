@@ -4,7 +4,7 @@ use doca::comm_chan::CommChannel;
 use doca::*;
 fn main() {
     let device = doca::device::open_device_with_pci("af:00.0").unwrap();
-    let conn = CommChannel::create_client("cc_conn\0", &device);
+    let conn = CommChannel::create_client("cc_conn", &device);
 
     let send_txt = "hello dpu";
     let mut send_buffer = vec![0u8; 9].into_boxed_slice();
@@ -16,18 +16,13 @@ fn main() {
         payload: send_txt.len(),
     };
 
-    let mut recv_raw = RawPointer {
-        inner: NonNull::new(recv_buffer.as_mut_ptr() as *mut _).unwrap(),
-        payload: 100,
-    };
-
-    conn.block_recv_req(&mut recv_raw);
+    let (len, _peer) = conn.recv_into(&mut recv_buffer).unwrap();
 
     conn.block_send_req(&send_raw);
 
     println!(
         "[After] recv_buffer check: {}",
-        String::from_utf8(recv_buffer.to_vec()).unwrap()
+        String::from_utf8_lossy(&recv_buffer[..len])
     );
 
 }
\ No newline at end of file
@@ -0,0 +1,93 @@
+use clap::{arg, App, AppSettings};
+use doca::pe::{DmaMemcpyTask, PeContext, ProgressEngine};
+use doca::*;
+
+use std::sync::Arc;
+
+fn main() {
+    let matches = App::new("doca dma local copy with progress engine")
+        .version("0.1")
+        .about("The doca dma local copy samples on DPU, for DOCA 2.x")
+        .setting(AppSettings::AllArgsOverrideSelf)
+        .args(&[
+            arg!(--pci <DEV_PCI> "DOCA DMA Device PCI address"),
+            arg!(--txt [COPY_TEXT] "The text to be delivered"),
+        ])
+        .get_matches();
+
+    let pci_addr = matches.value_of("pci").unwrap_or("03:00.0");
+    let cpy_txt = matches
+        .value_of("txt")
+        .unwrap_or("This is a sample copy text");
+
+    let length = cpy_txt.as_bytes().len();
+
+    println!(
+        "[Init] params check, pci: {}, cpy_txt {}, length {}",
+        pci_addr, cpy_txt, length
+    );
+
+    // first malloc the destination buffer
+    let dst_buffer = vec![0u8; length].into_boxed_slice();
+    let mut src_buffer = vec![0u8; length].into_boxed_slice();
+
+    // copy the text into src_buffer
+    src_buffer.copy_from_slice(cpy_txt.as_bytes());
+    println!(
+        "[Before] src_buffer and dst_buffer check: {} || {}",
+        String::from_utf8(src_buffer.to_vec()).unwrap(),
+        String::from_utf8(dst_buffer.to_vec()).unwrap()
+    );
+
+    /* ********** The main test body ********** */
+
+    // Create the progress engine and a DMA context connected to it
+    let device = doca::open_device_with_pci(pci_addr).unwrap();
+    let pe = ProgressEngine::new().unwrap();
+    let dma = DMAEngine::new(&device).unwrap();
+
+    let ctx = PeContext::builder(&dma, &pe)
+        .on_state_changed(|prev, next| println!("DMA context {:?} -> {:?}", prev, next))
+        .dma_memcpy(1, |task, result| match result {
+            Ok(()) => println!("Task {} finished!", task.user_data()),
+            Err(e) => panic!("Task failed! {:?}", e),
+        })
+        .start()
+        .unwrap();
+
+    let mut src_mmap = DOCAMmap::new().unwrap();
+    let mut dst_mmap = DOCAMmap::new().unwrap();
+    src_mmap.add_device(&device).unwrap();
+    dst_mmap.add_device(&device).unwrap();
+    let src_mmap = Arc::new(src_mmap);
+    let dst_mmap = Arc::new(dst_mmap);
+
+    let inv = BufferInventory::new(1024).unwrap();
+    let src_memory =
+        DOCARegisteredMemory::new(&src_mmap, unsafe { RawPointer::from_box(&src_buffer) }).unwrap();
+    let dst_memory =
+        DOCARegisteredMemory::new(&dst_mmap, unsafe { RawPointer::from_box(&dst_buffer) }).unwrap();
+
+    src_mmap.start().unwrap();
+    dst_mmap.start().unwrap();
+
+    let mut dma_src_buf = src_memory.to_buffer(&inv).unwrap();
+    unsafe { dma_src_buf.set_data(0, length).unwrap() };
+    let dma_dst_buf = dst_memory.to_buffer(&inv).unwrap();
+
+    /* Start to submit the DMA task!  */
+    let task = DmaMemcpyTask::new(&ctx, dma_src_buf, dma_dst_buf, 0).unwrap();
+    task.submit()
+        .map_err(|(e, _task)| e)
+        .expect("failed to submit the task");
+
+    // The completion callback is run by the progress engine
+    pe.progress_until_idle().unwrap();
+
+    /* ------- Finalize check ---------- */
+    println!(
+        "[After] src_buffer and dst_buffer check: {} || {}",
+        String::from_utf8(src_buffer.to_vec()).unwrap(),
+        String::from_utf8(dst_buffer.to_vec()).unwrap()
+    );
+}
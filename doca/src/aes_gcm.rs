@@ -0,0 +1,734 @@
+//! Wrapper for DOCA AES-GCM. It provides the ability of
+//! encrypting and decrypting data with AES-GCM using hardware acceleration.
+//!
+//! It basically contains the following core structs:
+//! - [`AesGcmEngine`]: The AES-GCM Engine of DOCA, created on a device.
+//!
+//! - [`AesGcmKey`]: A key loaded into the engine from a [`KeyMaterial`],
+//! the raw key bytes which are zeroized when dropped.
+//!
+//! - [`AesGcmEncryptTask`] and [`AesGcmDecryptTask`]: Encrypt or decrypt the data of the
+//! source buffer into the destination buffer, with the IV, the tag size and the length
+//! of the AAD given by [`AesGcmParams`].
+//!
+//! The AAD is the first `aad_len` bytes of the source data, it is authenticated only and
+//! copied in front of the output. The encrypt task appends the AAD, the ciphertext then
+//! the tag to the destination, and the decrypt task expects the tag after the ciphertext.
+//! A decrypt task whose tag does not verify completes with [`AesGcmError::TagMismatch`].
+//!
+//! The AES-GCM library is only available since DOCA 2.5, which has no work queue, so the
+//! engine is built on the progress engine (see [`crate::pe`]) rather than on `DOCAContext`
+//! and `DOCAWorkQueue`, and requires the `aes-gcm` and `doca-2-x` features.
+//! The tasks are enabled on the context with [`PeContextBuilder::aes_gcm_encrypt`] and
+//! [`PeContextBuilder::aes_gcm_decrypt`].
+//!
+//! # Examples
+//!
+//! ``` rust, no_run
+//! use doca::aes_gcm::{AesGcmEncryptTask, AesGcmEngine, AesGcmKey, AesGcmParams, KeyMaterial};
+//! use doca::pe::{PeContext, ProgressEngine};
+//!
+//! let device = doca::device::open_device_with_pci("03:00.0").unwrap();
+//! let pe = ProgressEngine::new().unwrap();
+//! let aes_gcm = AesGcmEngine::new(&device).unwrap();
+//!
+//! let ctx = PeContext::builder(&aes_gcm, &pe)
+//!     .aes_gcm_encrypt(16, |task, result| {
+//!         println!("task {} finished: {:?}", task.user_data(), result);
+//!     })
+//!     .start()
+//!     .unwrap();
+//!
+//! let key = AesGcmKey::new(&aes_gcm, KeyMaterial::from([7u8; 32])).unwrap();
+//! let params = AesGcmParams::new(&[0u8; 12]).unwrap();
+//!
+//! # fn buffers() -> (doca::DOCABuffer, doca::DOCABuffer) { unimplemented!() }
+//! # let (src_buf, dst_buf) = buffers();
+//! let task = AesGcmEncryptTask::new(&ctx, &key, src_buf, dst_buf, &params, 1).unwrap();
+//! task.submit().map_err(|(e, _task)| e).unwrap();
+//! pe.progress_until_idle().unwrap();
+//! ```
+//!
+
+use std::fmt;
+use std::ptr::NonNull;
+use std::sync::atomic::{compiler_fence, Ordering};
+use std::sync::Arc;
+
+use crate::context::EngineToContext;
+use crate::pe::task::{self, RawTask, TaskCompletion};
+use crate::pe::{CtxCallbacks, OnCompletion, PeContext, PeContextBuilder, Task};
+use crate::{DOCABuffer, DOCAError, DOCAResult, DevContext};
+
+/// The maximum length of an IV
+pub const MAX_IV_LEN: usize = 12;
+
+/// Errors of the AES-GCM tasks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AesGcmError {
+    /// The authentication tag of a decrypt task does not verify,
+    /// the data or the AAD has been tampered with or the key is wrong
+    TagMismatch,
+    /// Any other failure of the task
+    Doca(DOCAError),
+}
+
+impl fmt::Display for AesGcmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AesGcmError::TagMismatch => write!(f, "AES-GCM authentication tag mismatch"),
+            AesGcmError::Doca(e) => write!(f, "DOCA error: {:?}", e),
+        }
+    }
+}
+
+impl std::error::Error for AesGcmError {}
+
+impl From<DOCAError> for AesGcmError {
+    fn from(e: DOCAError) -> Self {
+        AesGcmError::Doca(e)
+    }
+}
+
+/// DOCA AES-GCM engine instance
+pub struct AesGcmEngine {
+    inner: NonNull<ffi::doca_aes_gcm>,
+
+    // The engine is created on a device,
+    // which should be closed after the engine is destroyed
+    #[allow(dead_code)]
+    dev: Arc<DevContext>,
+}
+
+unsafe impl Sync for AesGcmEngine {}
+unsafe impl Send for AesGcmEngine {}
+
+impl Drop for AesGcmEngine {
+    fn drop(&mut self) {
+        let ret = unsafe { ffi::doca_aes_gcm_destroy(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            panic!("Failed to destory aes-gcm engine!");
+        }
+
+        trace!(aes_gcm = ?self.inner, "AES-GCM Engine is dropped");
+    }
+}
+
+/// Implementation `EngineToContext` Trait for AES-GCM Engine
+impl EngineToContext for AesGcmEngine {
+    unsafe fn to_ctx(&self) -> *mut ffi::doca_ctx {
+        ffi::doca_aes_gcm_as_ctx(self.inner_ptr())
+    }
+}
+
+impl AesGcmEngine {
+    /// Create a DOCA AES-GCM instance on the device.
+    pub fn new(dev: &Arc<DevContext>) -> DOCAResult<Arc<Self>> {
+        let mut aes_gcm: *mut ffi::doca_aes_gcm = std::ptr::null_mut();
+        let ret = unsafe { ffi::doca_aes_gcm_create(dev.inner_ptr(), &mut aes_gcm as *mut _) };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(Arc::new(Self {
+            inner: unsafe { NonNull::new_unchecked(aes_gcm) },
+            dev: dev.clone(),
+        }))
+    }
+
+    /// Get the inner pointer of the DOCA AES-GCM instance.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_aes_gcm {
+        self.inner.as_ptr()
+    }
+}
+
+/// The raw bytes of an AES-128 or AES-256 key, zeroized when dropped
+pub struct KeyMaterial {
+    bytes: Box<[u8]>,
+}
+
+impl KeyMaterial {
+    /// Copy the key from a slice, the caller should zeroize its own copy
+    pub fn from_slice(key: &[u8]) -> Self {
+        Self {
+            bytes: key.to_vec().into_boxed_slice(),
+        }
+    }
+
+    /// Get the length of the key in bytes
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Return `true` if the key is empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Get the DOCA key type from the length of the key
+    fn key_type(&self) -> DOCAResult<ffi::doca_aes_gcm_key_type> {
+        match self.bytes.len() {
+            16 => Ok(ffi::DOCA_AES_GCM_KEY_128),
+            32 => Ok(ffi::DOCA_AES_GCM_KEY_256),
+            _ => Err(DOCAError::DOCA_ERROR_INVALID_VALUE),
+        }
+    }
+}
+
+impl From<Vec<u8>> for KeyMaterial {
+    fn from(mut key: Vec<u8>) -> Self {
+        // `into_boxed_slice` may reallocate and free the key without zeroizing it,
+        // so copy it into an exact-size box and zeroize the whole allocation of the vector.
+        let material = Self::from_slice(&key);
+        key.resize(key.capacity(), 0);
+        zeroize(&mut key);
+        material
+    }
+}
+
+impl From<[u8; 16]> for KeyMaterial {
+    fn from(mut key: [u8; 16]) -> Self {
+        let material = Self::from_slice(&key);
+        zeroize(&mut key);
+        material
+    }
+}
+
+impl From<[u8; 32]> for KeyMaterial {
+    fn from(mut key: [u8; 32]) -> Self {
+        let material = Self::from_slice(&key);
+        zeroize(&mut key);
+        material
+    }
+}
+
+impl fmt::Debug for KeyMaterial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "KeyMaterial({} bytes)", self.bytes.len())
+    }
+}
+
+impl Drop for KeyMaterial {
+    fn drop(&mut self) {
+        zeroize(&mut self.bytes);
+    }
+}
+
+/// Overwrite the key bytes with zeros before their memory is released
+#[inline]
+fn zeroize(bytes: &mut [u8]) {
+    // volatile writes are not optimized out even though the memory is freed next
+    for b in bytes.iter_mut() {
+        unsafe { std::ptr::write_volatile(b, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// A key loaded into the AES-GCM engine
+pub struct AesGcmKey {
+    inner: NonNull<ffi::doca_aes_gcm_key>,
+
+    // Ensure that the engine is destroyed after the key
+    #[allow(dead_code)]
+    engine: Arc<AesGcmEngine>,
+}
+
+unsafe impl Sync for AesGcmKey {}
+unsafe impl Send for AesGcmKey {}
+
+impl Drop for AesGcmKey {
+    fn drop(&mut self) {
+        let ret = unsafe { ffi::doca_aes_gcm_key_destroy(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            panic!("Failed to destroy the aes-gcm key: {:?}", ret);
+        }
+
+        trace!(key = ?self.inner, "AES-GCM Key is dropped");
+    }
+}
+
+impl AesGcmKey {
+    /// Load the key into the engine, the key material is zeroized once loaded.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the key is neither 16 nor 32 bytes long.
+    ///  - Any error returned by `doca_aes_gcm_key_create`.
+    ///
+    pub fn new(engine: &Arc<AesGcmEngine>, key: KeyMaterial) -> DOCAResult<Arc<Self>> {
+        let key_type = key.key_type()?;
+
+        let mut inner: *mut ffi::doca_aes_gcm_key = std::ptr::null_mut();
+        let ret = unsafe {
+            ffi::doca_aes_gcm_key_create(
+                engine.inner_ptr(),
+                key.bytes.as_ptr() as *const c_void,
+                key_type,
+                &mut inner as *mut _,
+            )
+        };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(Arc::new(Self {
+            inner: unsafe { NonNull::new_unchecked(inner) },
+            engine: engine.clone(),
+        }))
+    }
+
+    /// Get the inner pointer of the DOCA AES-GCM key.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_aes_gcm_key {
+        self.inner.as_ptr()
+    }
+}
+
+/// The size of the authentication tag
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TagSize {
+    /// 96 bits tag
+    Tag96,
+    /// 128 bits tag
+    Tag128,
+}
+
+impl TagSize {
+    /// Get the size of the tag in bytes
+    #[inline]
+    pub fn bytes(self) -> u32 {
+        match self {
+            TagSize::Tag96 => 12,
+            TagSize::Tag128 => 16,
+        }
+    }
+}
+
+/// The parameters of an AES-GCM task
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AesGcmParams {
+    iv: [u8; MAX_IV_LEN],
+    iv_len: u32,
+    tag_size: TagSize,
+    aad_len: u32,
+}
+
+impl AesGcmParams {
+    /// Create the parameters with the IV, a 128 bits tag and no AAD.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the IV is empty or longer than [`MAX_IV_LEN`].
+    ///
+    pub fn new(iv: &[u8]) -> DOCAResult<Self> {
+        if iv.is_empty() || iv.len() > MAX_IV_LEN {
+            return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+        }
+
+        let mut res = Self {
+            iv: [0; MAX_IV_LEN],
+            iv_len: iv.len() as u32,
+            tag_size: TagSize::Tag128,
+            aad_len: 0,
+        };
+        res.iv[..iv.len()].copy_from_slice(iv);
+        Ok(res)
+    }
+
+    /// Set the size of the tag
+    #[inline]
+    pub fn set_tag_size(&mut self, tag_size: TagSize) -> &mut Self {
+        self.tag_size = tag_size;
+        self
+    }
+
+    /// Set the length of the AAD at the start of the source data
+    #[inline]
+    pub fn set_aad_len(&mut self, aad_len: u32) -> &mut Self {
+        self.aad_len = aad_len;
+        self
+    }
+
+    /// Get the IV
+    #[inline]
+    pub fn iv(&self) -> &[u8] {
+        &self.iv[..self.iv_len as usize]
+    }
+
+    /// Get the size of the tag
+    #[inline]
+    pub fn tag_size(&self) -> TagSize {
+        self.tag_size
+    }
+
+    /// Get the length of the AAD
+    #[inline]
+    pub fn aad_len(&self) -> u32 {
+        self.aad_len
+    }
+}
+
+/// What an AES-GCM task owns
+pub struct AesGcmTaskState {
+    // Ensure that the context is stopped after all its tasks are freed
+    #[allow(dead_code)]
+    ctx: Arc<PeContext<AesGcmEngine>>,
+    key: Arc<AesGcmKey>,
+    src_buf: DOCABuffer,
+    dst_buf: DOCABuffer,
+}
+
+impl AesGcmTaskState {
+    fn new(
+        ctx: &Arc<PeContext<AesGcmEngine>>,
+        key: &Arc<AesGcmKey>,
+        src_buf: DOCABuffer,
+        dst_buf: DOCABuffer,
+    ) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            key: key.clone(),
+            src_buf,
+            dst_buf,
+        }
+    }
+}
+
+impl RawTask for ffi::doca_aes_gcm_task_encrypt {
+    type State = AesGcmTaskState;
+
+    unsafe fn as_task(task: *mut Self) -> *mut ffi::doca_task {
+        ffi::doca_aes_gcm_task_encrypt_as_task(task)
+    }
+}
+
+impl TaskCompletion for ffi::doca_aes_gcm_task_encrypt {
+    type Error = DOCAError;
+
+    fn callback(callbacks: &mut CtxCallbacks) -> &mut OnCompletion<AesGcmEncryptTask> {
+        &mut callbacks.aes_gcm_encrypt
+    }
+
+    fn error(status: DOCAError) -> DOCAError {
+        status
+    }
+}
+
+impl RawTask for ffi::doca_aes_gcm_task_decrypt {
+    type State = AesGcmTaskState;
+
+    unsafe fn as_task(task: *mut Self) -> *mut ffi::doca_task {
+        ffi::doca_aes_gcm_task_decrypt_as_task(task)
+    }
+}
+
+impl TaskCompletion for ffi::doca_aes_gcm_task_decrypt {
+    type Error = AesGcmError;
+
+    fn callback(
+        callbacks: &mut CtxCallbacks,
+    ) -> &mut OnCompletion<AesGcmDecryptTask, Result<(), AesGcmError>> {
+        &mut callbacks.aes_gcm_decrypt
+    }
+
+    /// Tell a tag mismatch apart from the other failures of a decrypt task.
+    /// The hardware reports a tag that does not verify as a failed data check,
+    /// which DOCA returns as `DOCA_ERROR_IO_FAILED`.
+    fn error(status: DOCAError) -> AesGcmError {
+        match status {
+            DOCAError::DOCA_ERROR_IO_FAILED => AesGcmError::TagMismatch,
+            e => AesGcmError::Doca(e),
+        }
+    }
+}
+
+/// A DOCA AES-GCM encrypt task, appending the ciphertext and the tag of the
+/// source data to the destination buffer.
+pub type AesGcmEncryptTask = Task<ffi::doca_aes_gcm_task_encrypt>;
+
+impl AesGcmEncryptTask {
+    /// Allocate an encrypt task on the started context.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NO_MEMORY`: the number of tasks set by
+    ///  [`PeContextBuilder::aes_gcm_encrypt`] are all allocated.
+    ///  - `DOCA_ERROR_BAD_STATE`: the context is not running.
+    ///
+    pub fn new(
+        ctx: &Arc<PeContext<AesGcmEngine>>,
+        key: &Arc<AesGcmKey>,
+        src_buf: DOCABuffer,
+        dst_buf: DOCABuffer,
+        params: &AesGcmParams,
+        user_data: u64,
+    ) -> DOCAResult<Self> {
+        let state = AesGcmTaskState::new(ctx, key, src_buf, dst_buf);
+
+        unsafe {
+            Self::alloc(state, user_data, |state, task_user_data, task| {
+                ffi::doca_aes_gcm_task_encrypt_alloc_init(
+                    ctx.engine().inner_ptr(),
+                    state.src_buf.inner_ptr(),
+                    state.dst_buf.inner_ptr(),
+                    state.key.inner_ptr(),
+                    params.iv.as_ptr(),
+                    params.iv_len,
+                    params.tag_size.bytes(),
+                    params.aad_len,
+                    task_user_data,
+                    task,
+                )
+            })
+        }
+        .map_err(|(e, _state)| e)
+    }
+
+    /// Free the task and return its source and destination buffers.
+    pub fn into_buffers(self) -> (DOCABuffer, DOCABuffer) {
+        let state = self.into_state();
+        (state.src_buf, state.dst_buf)
+    }
+
+    /// Get the source buffer
+    #[inline]
+    pub fn src_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().src_buf
+    }
+
+    /// Get the destination buffer
+    #[inline]
+    pub fn dst_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().dst_buf
+    }
+}
+
+/// A DOCA AES-GCM decrypt task, verifying the tag after the ciphertext of the
+/// source data and appending the plaintext to the destination buffer.
+pub type AesGcmDecryptTask = Task<ffi::doca_aes_gcm_task_decrypt>;
+
+impl AesGcmDecryptTask {
+    /// Allocate a decrypt task on the started context.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NO_MEMORY`: the number of tasks set by
+    ///  [`PeContextBuilder::aes_gcm_decrypt`] are all allocated.
+    ///  - `DOCA_ERROR_BAD_STATE`: the context is not running.
+    ///
+    pub fn new(
+        ctx: &Arc<PeContext<AesGcmEngine>>,
+        key: &Arc<AesGcmKey>,
+        src_buf: DOCABuffer,
+        dst_buf: DOCABuffer,
+        params: &AesGcmParams,
+        user_data: u64,
+    ) -> DOCAResult<Self> {
+        let state = AesGcmTaskState::new(ctx, key, src_buf, dst_buf);
+
+        unsafe {
+            Self::alloc(state, user_data, |state, task_user_data, task| {
+                ffi::doca_aes_gcm_task_decrypt_alloc_init(
+                    ctx.engine().inner_ptr(),
+                    state.src_buf.inner_ptr(),
+                    state.dst_buf.inner_ptr(),
+                    state.key.inner_ptr(),
+                    params.iv.as_ptr(),
+                    params.iv_len,
+                    params.tag_size.bytes(),
+                    params.aad_len,
+                    task_user_data,
+                    task,
+                )
+            })
+        }
+        .map_err(|(e, _state)| e)
+    }
+
+    /// Free the task and return its source and destination buffers.
+    pub fn into_buffers(self) -> (DOCABuffer, DOCABuffer) {
+        let state = self.into_state();
+        (state.src_buf, state.dst_buf)
+    }
+
+    /// Get the source buffer
+    #[inline]
+    pub fn src_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().src_buf
+    }
+
+    /// Get the destination buffer
+    #[inline]
+    pub fn dst_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().dst_buf
+    }
+}
+
+impl PeContextBuilder<AesGcmEngine> {
+    /// Enable the encrypt tasks on the context.
+    ///
+    /// At most `num_tasks` tasks can be allocated at the same time. `on_completion` is
+    /// called with every completed task and its result.
+    pub fn aes_gcm_encrypt<F>(&mut self, num_tasks: u32, on_completion: F) -> &mut Self
+    where
+        F: FnMut(AesGcmEncryptTask, DOCAResult<()>) + 'static,
+    {
+        self.callbacks.aes_gcm_encrypt = Some(Box::new(on_completion));
+        self.confs.push(Box::new(move |aes_gcm: &AesGcmEngine| {
+            let ret = unsafe {
+                ffi::doca_aes_gcm_task_encrypt_set_conf(
+                    aes_gcm.inner_ptr(),
+                    Some(task::success_cb::<ffi::doca_aes_gcm_task_encrypt>),
+                    Some(task::error_cb::<ffi::doca_aes_gcm_task_encrypt>),
+                    num_tasks,
+                )
+            };
+
+            if ret != DOCAError::DOCA_SUCCESS {
+                return Err(ret);
+            }
+            Ok(())
+        }));
+        self
+    }
+
+    /// Enable the decrypt tasks on the context.
+    ///
+    /// At most `num_tasks` tasks can be allocated at the same time. `on_completion` is
+    /// called with every completed task and its result, a tag that does not verify
+    /// is reported as [`AesGcmError::TagMismatch`].
+    pub fn aes_gcm_decrypt<F>(&mut self, num_tasks: u32, on_completion: F) -> &mut Self
+    where
+        F: FnMut(AesGcmDecryptTask, Result<(), AesGcmError>) + 'static,
+    {
+        self.callbacks.aes_gcm_decrypt = Some(Box::new(on_completion));
+        self.confs.push(Box::new(move |aes_gcm: &AesGcmEngine| {
+            let ret = unsafe {
+                ffi::doca_aes_gcm_task_decrypt_set_conf(
+                    aes_gcm.inner_ptr(),
+                    Some(task::success_cb::<ffi::doca_aes_gcm_task_decrypt>),
+                    Some(task::error_cb::<ffi::doca_aes_gcm_task_decrypt>),
+                    num_tasks,
+                )
+            };
+
+            if ret != DOCAError::DOCA_SUCCESS {
+                return Err(ret);
+            }
+            Ok(())
+        }));
+        self
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_aes_gcm_params() {
+        use crate::aes_gcm::*;
+
+        let key = KeyMaterial::from([1u8; 16]);
+        assert_eq!(format!("{:?}", key), "KeyMaterial(16 bytes)");
+        assert!(KeyMaterial::from_slice(&[1u8; 24]).key_type().is_err());
+
+        assert!(AesGcmParams::new(&[]).is_err());
+        assert!(AesGcmParams::new(&[0u8; MAX_IV_LEN + 1]).is_err());
+
+        let mut params = AesGcmParams::new(&[1, 2, 3]).unwrap();
+        params.set_tag_size(TagSize::Tag96).set_aad_len(8);
+        assert_eq!(params.iv(), &[1, 2, 3]);
+        assert_eq!(params.tag_size().bytes(), 12);
+        assert_eq!(params.aad_len(), 8);
+    }
+
+    #[test]
+    #[ignore = "needs a DOCA device supporting AES-GCM"]
+    fn test_aes_gcm_round_trip() {
+        use crate::aes_gcm::*;
+        use crate::device::DeviceCapability;
+        use crate::pe::ProgressEngine;
+        use crate::*;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let dev = devices().unwrap().get(0).unwrap();
+        assert!(dev.supports(DeviceCapability::AesGcmEncrypt).unwrap());
+        let device = dev.open().unwrap();
+
+        let pe = ProgressEngine::new().unwrap();
+        let aes_gcm = AesGcmEngine::new(&device).unwrap();
+
+        let encrypted = Rc::new(RefCell::new(Vec::new()));
+        let decrypted = Rc::new(RefCell::new(Vec::new()));
+        let (encrypted_cb, decrypted_cb) = (encrypted.clone(), decrypted.clone());
+        let ctx = PeContext::builder(&aes_gcm, &pe)
+            .aes_gcm_encrypt(1, move |task, result| {
+                encrypted_cb
+                    .borrow_mut()
+                    .push((task.into_buffers(), result))
+            })
+            .aes_gcm_decrypt(1, move |task, result| {
+                decrypted_cb
+                    .borrow_mut()
+                    .push((task.into_buffers(), result))
+            })
+            .start()
+            .unwrap();
+
+        let key = AesGcmKey::new(&aes_gcm, KeyMaterial::from([7u8; 32])).unwrap();
+        let mut params = AesGcmParams::new(&[9u8; MAX_IV_LEN]).unwrap();
+        params.set_aad_len(16);
+
+        let (aad_len, text_len, tag_len) = (16, 64, 16);
+        let mut plain: Vec<u8> = (0..(aad_len + text_len) as u8).collect();
+        let mut cipher = vec![0u8; aad_len + text_len + tag_len];
+        let mut result = vec![0u8; aad_len + text_len];
+        let inv = BufferInventory::new(8).unwrap();
+
+        let to_buffer =
+            |buffer: &mut [u8], len: usize| crate::memory::test_buffer(&device, &inv, buffer, len);
+        let plain_len = plain.len();
+        let plain_buf = to_buffer(&mut plain, plain_len);
+        let cipher_buf = to_buffer(&mut cipher, 0);
+
+        AesGcmEncryptTask::new(&ctx, &key, plain_buf, cipher_buf, &params, 0)
+            .unwrap()
+            .submit()
+            .map_err(|(e, _task)| e)
+            .unwrap();
+        pe.progress_until_idle().unwrap();
+        let ((_plain_buf, cipher_buf), res) = encrypted.borrow_mut().pop().unwrap();
+        assert_eq!(res, Ok(()));
+        let cipher_len = cipher_buf.data_len().unwrap();
+        assert_ne!(cipher[aad_len..aad_len + text_len], plain[aad_len..]);
+
+        // decrypt it back
+        let result_buf = to_buffer(&mut result, 0);
+        AesGcmDecryptTask::new(&ctx, &key, cipher_buf, result_buf, &params, 1)
+            .unwrap()
+            .submit()
+            .map_err(|(e, _task)| e)
+            .unwrap();
+        pe.progress_until_idle().unwrap();
+        let ((mut cipher_buf, mut result_buf), res) = decrypted.borrow_mut().pop().unwrap();
+        assert_eq!(res, Ok(()));
+        assert_eq!(result[aad_len..], plain[aad_len..]);
+
+        // a flipped bit of the ciphertext fails the tag
+        cipher[aad_len] ^= 1;
+        unsafe {
+            cipher_buf.set_data(0, cipher_len).unwrap();
+            result_buf.set_data(0, 0).unwrap();
+        }
+        AesGcmDecryptTask::new(&ctx, &key, cipher_buf, result_buf, &params, 2)
+            .unwrap()
+            .submit()
+            .map_err(|(e, _task)| e)
+            .unwrap();
+        pe.progress_until_idle().unwrap();
+        let (_, res) = decrypted.borrow_mut().pop().unwrap();
+        assert_eq!(res, Err(AesGcmError::TagMismatch));
+    }
+}
@@ -0,0 +1,1260 @@
+//! Wrapper for DOCA Comm Channel between host and dpu
+//! the ability of send reqs between host and dpu using pcie switch
+//!
+//! When connecting, the client and the server exchange a handshake carrying
+//! a magic number, the protocol version, their maximum message size and an
+//! optional client ID. Either side rejects a peer that speaks another
+//! protocol with a [`CommChanError`].
+//!
+//! Besides the blocking requests, an endpoint exposes the event channel of
+//! DOCA as file descriptors: [`CommChannel`] implements [`AsRawFd`] with its
+//! receive handle, so it can be registered with epoll or mio after
+//! [`CommChannel::arm_recv`]. With the `async` feature, [`AsyncCommChannel`]
+//! provides `async fn send`/`recv` on top of tokio's `AsyncFd`.
+//!
+//! A [`SupervisedClient`] keeps a client connected across server restarts,
+//! with heartbeats and reconnection with an exponential backoff.
+//!
+//! Messages can be received into a caller supplied `&mut [u8]` with
+//! [`CommChannel::recv_into`], or into reusable buffers of a [`RecvPool`].
+//!
+//! Every endpoint counts the messages, bytes, retries and errors, in total and
+//! per peer, see [`CommChannel::stats`]. With the `tracing` feature, connecting,
+//! sending and receiving are also recorded as `tracing` spans carrying the
+//! message sizes.
+
+use std::ffi::{c_void, CString};
+use std::fmt;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::{Duration, Instant};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::sleep;
+use std::ptr::NonNull;
+use ffi::doca_error;
+
+use crate::RawPointer;
+
+pub mod pool;
+pub mod stats;
+
+pub use pool::{RecvBuf, RecvPool};
+pub use stats::{ChannelCounters, CommChanStats, PeerId};
+use stats::Stats;
+
+/// Enter a `tracing` span, a no-op without the `tracing` feature.
+macro_rules! span {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!($($arg)*).entered();
+        #[cfg(not(feature = "tracing"))]
+        let span = NoSpan;
+        span
+    }};
+}
+
+/// Stands for the entered span without the `tracing` feature.
+#[cfg(not(feature = "tracing"))]
+struct NoSpan;
+
+use crate::{device::DevRepContext, DOCAError, DOCAResult, DevContext};
+
+/// The maximum size of a message sent by this endpoint.
+pub const MAX_MSG_SIZE: u32 = 4080;
+
+/// The version of the handshake and heartbeat protocol of this crate.
+pub const PROTOCOL_VERSION: u16 = 2;
+
+const HANDSHAKE_MAGIC: [u8; 4] = *b"DCCH";
+const HANDSHAKE_LEN: usize = 20;
+const HANDSHAKE_HAS_CLIENT_ID: u16 = 1;
+const HANDSHAKE_SENDS_HEARTBEATS: u16 = 1 << 1;
+
+/// Error of establishing a Comm Channel connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommChanError {
+    /// The server name contains a NUL byte.
+    InvalidName,
+    /// The peer sent a handshake without the magic number,
+    /// it is not an endpoint of this crate or predates the handshake.
+    BadMagic,
+    /// The peer sent a truncated handshake of `len` bytes.
+    Malformed {
+        /// Length of the received handshake.
+        len: usize,
+    },
+    /// The peer speaks another protocol version.
+    UnsupportedVersion {
+        /// Our [`PROTOCOL_VERSION`].
+        local: u16,
+        /// The version of the peer.
+        peer: u16,
+    },
+    /// DOCA failed to set up the endpoint or to exchange the handshake.
+    Doca(DOCAError),
+}
+
+impl fmt::Display for CommChanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommChanError::InvalidName => write!(f, "the server name contains a NUL byte"),
+            CommChanError::BadMagic => write!(f, "the peer is not a comm channel endpoint of this protocol"),
+            CommChanError::Malformed { len } => {
+                write!(f, "the peer sent a {} bytes handshake, expected at least {}", len, HANDSHAKE_LEN)
+            }
+            CommChanError::UnsupportedVersion { local, peer } => {
+                write!(f, "the peer speaks protocol version {}, we speak version {}", peer, local)
+            }
+            CommChanError::Doca(e) => write!(f, "DOCA error {:?}", e),
+        }
+    }
+}
+
+impl std::error::Error for CommChanError {}
+
+impl From<DOCAError> for CommChanError {
+    fn from(e: DOCAError) -> Self {
+        CommChanError::Doca(e)
+    }
+}
+
+/// The handshake message exchanged when connecting, little endian:
+/// magic (4), version (2), flags (2), max message size (4), client ID (8).
+///
+/// The flags tell whether the client ID is set, and whether the client sends [`HEARTBEAT`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Handshake {
+    version: u16,
+    max_msg_size: u32,
+    client_id: Option<u64>,
+    sends_heartbeats: bool,
+}
+
+impl Handshake {
+    fn local(client_id: Option<u64>, sends_heartbeats: bool) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            max_msg_size: MAX_MSG_SIZE,
+            client_id,
+            sends_heartbeats,
+        }
+    }
+
+    fn encode(&self) -> [u8; HANDSHAKE_LEN] {
+        let mut flags = if self.client_id.is_some() { HANDSHAKE_HAS_CLIENT_ID } else { 0 };
+        if self.sends_heartbeats {
+            flags |= HANDSHAKE_SENDS_HEARTBEATS;
+        }
+
+        let mut msg = [0u8; HANDSHAKE_LEN];
+        msg[0..4].copy_from_slice(&HANDSHAKE_MAGIC);
+        msg[4..6].copy_from_slice(&self.version.to_le_bytes());
+        msg[6..8].copy_from_slice(&flags.to_le_bytes());
+        msg[8..12].copy_from_slice(&self.max_msg_size.to_le_bytes());
+        msg[12..20].copy_from_slice(&self.client_id.unwrap_or(0).to_le_bytes());
+        msg
+    }
+
+    /// Decode and validate the handshake of the peer.
+    ///
+    /// The version is checked before the length, so that a future version
+    /// with a longer handshake is reported as such.
+    fn decode(msg: &[u8]) -> Result<Self, CommChanError> {
+        if msg.len() < 4 || msg[0..4] != HANDSHAKE_MAGIC {
+            return Err(CommChanError::BadMagic);
+        }
+        if msg.len() < 6 {
+            return Err(CommChanError::Malformed { len: msg.len() });
+        }
+
+        let version = u16::from_le_bytes([msg[4], msg[5]]);
+        if version != PROTOCOL_VERSION {
+            return Err(CommChanError::UnsupportedVersion { local: PROTOCOL_VERSION, peer: version });
+        }
+        if msg.len() < HANDSHAKE_LEN {
+            return Err(CommChanError::Malformed { len: msg.len() });
+        }
+
+        let flags = u16::from_le_bytes([msg[6], msg[7]]);
+        let max_msg_size = u32::from_le_bytes(msg[8..12].try_into().unwrap());
+        let client_id = u64::from_le_bytes(msg[12..20].try_into().unwrap());
+
+        Ok(Self {
+            version,
+            max_msg_size,
+            client_id: (flags & HANDSHAKE_HAS_CLIENT_ID != 0).then_some(client_id),
+            sends_heartbeats: flags & HANDSHAKE_SENDS_HEARTBEATS != 0,
+        })
+    }
+}
+
+/// DOCA Comm Channel
+pub struct CommChannel {
+    inner: NonNull<ffi::doca_comm_channel_ep_t>,
+    peer_addr: NonNull<ffi::doca_comm_channel_addr_t>,
+    send_fd: RawFd,
+    recv_fd: RawFd,
+    max_msg_size: u32,
+    peer_client_id: Option<u64>,
+    // negotiated in the handshake: the client sends heartbeats, and the server drops them
+    sends_heartbeats: bool,
+    drops_heartbeats: bool,
+    stats: Stats,
+    // `doca_comm_channel_ep_t` is not thread-safe, every call on the endpoint
+    // through `&self` holds the lock
+    lock: Mutex<()>,
+    dev: Arc<DevContext>,
+    dev_rep: Option<Arc<DevRepContext>>,
+}
+
+// SAFETY: the calls on the endpoint through `&self`, i.e. sending, receiving,
+// arming the event handles and querying the peer, are serialized by `lock`.
+// The event handles are only read, and the stats have their own lock.
+unsafe impl Sync for CommChannel {}
+// SAFETY: the endpoint is not bound to the thread that created it, it is
+// disconnected and destroyed by whichever thread drops it.
+unsafe impl Send for CommChannel {}
+
+impl Drop for CommChannel {
+    fn drop(&mut self) {
+        unsafe { ffi::doca_comm_channel_ep_disconnect(self.inner_ptr(), self.peer_addr.as_ptr()); }
+        
+        let ret = unsafe { ffi::doca_comm_channel_ep_destroy(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            panic!("Failed to destroy comm channel endpoint!");
+        }
+    }
+}
+
+impl CommChannel {
+
+    /// Create a Comm Channel Server Instance, waiting for a client to connect.
+    ///
+    /// The `server_name` may end with a NUL byte, but must not contain one elsewhere.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the endpoint cannot be set up, or the client is incompatible,
+    /// see [`Self::try_create_server`] to handle the error instead.
+    pub fn create_server(server_name: &str, dev: &Arc<DevContext>, dev_rep: &Arc<DevRepContext>) -> Arc<Self> {
+        match Self::try_create_server(server_name, dev, dev_rep) {
+            Ok(chan) => chan,
+            Err(e) => panic!("Comm Channel server failed to accept a client: {}", e),
+        }
+    }
+
+    /// Create a Comm Channel Server Instance, waiting for a client to connect.
+    ///
+    /// The server answers the handshake of the client even if it rejects it,
+    /// so that the client reports the same error.
+    pub fn try_create_server(
+        server_name: &str,
+        dev: &Arc<DevContext>,
+        dev_rep: &Arc<DevRepContext>,
+    ) -> Result<Arc<Self>, CommChanError> {
+        let _span = span!("comm_chan.connect", server = server_name);
+        let name = server_cstring(server_name)?;
+        let mut ep: *mut ffi::doca_comm_channel_ep_t = std::ptr::null_mut();
+        let mut peer_addr: *mut ffi::doca_comm_channel_addr_t = std::ptr::null_mut();
+
+        let ret = unsafe { ffi::doca_comm_channel_ep_create(&mut ep) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret.into());
+        }
+
+        let res = Self::server_handshake(ep, &mut peer_addr, &name, dev, dev_rep);
+        let (peer, (send_fd, recv_fd)) = match res.and_then(|peer| Ok((peer, event_channel(ep)?))) {
+            Ok(res) => res,
+            Err(e) => {
+                release(ep, peer_addr);
+                return Err(e);
+            }
+        };
+
+        Ok(Arc::new(Self {
+            inner: NonNull::new(ep).unwrap(),
+            peer_addr: NonNull::new(peer_addr).unwrap(),
+            send_fd,
+            recv_fd,
+            max_msg_size: peer.max_msg_size.min(MAX_MSG_SIZE),
+            peer_client_id: peer.client_id,
+            sends_heartbeats: false,
+            drops_heartbeats: peer.sends_heartbeats,
+            stats: Stats::default(),
+            lock: Mutex::new(()),
+            dev: dev.clone(),
+            dev_rep: Some(dev_rep.clone())
+        }))
+    }
+
+    fn server_handshake(
+        ep: *mut ffi::doca_comm_channel_ep_t,
+        peer_addr: &mut *mut ffi::doca_comm_channel_addr_t,
+        server_name: &CString,
+        dev: &Arc<DevContext>,
+        dev_rep: &Arc<DevRepContext>,
+    ) -> Result<Handshake, CommChanError> {
+        set_properties(ep, dev)?;
+        check(unsafe { ffi::doca_comm_channel_ep_set_device_rep(ep, dev_rep.inner_ptr()) })?;
+
+        /* Start listen for new connections */
+        check(unsafe { ffi::doca_comm_channel_ep_listen(ep, server_name.as_ptr()) })?;
+
+        let mut msg = vec![0u8; MAX_MSG_SIZE as usize];
+        let len = loop {
+            let mut len = msg.len();
+            let res = unsafe { ffi::doca_comm_channel_ep_recvfrom(ep, msg.as_mut_ptr().cast(), &mut len, 0, peer_addr) };
+            if res == DOCAError::DOCA_SUCCESS {
+                break len;
+            }
+            if res != DOCAError::DOCA_ERROR_AGAIN {
+                return Err(res.into());
+            }
+
+            sleep(Duration::from_millis(1));
+        };
+
+        // Answer before validating, so that a rejected client learns why.
+        send_handshake(ep, *peer_addr, &Handshake::local(None, false), None)?;
+        Handshake::decode(&msg[..len])
+    }
+
+    /// Create a Comm Channel Client Instance
+    ///
+    /// The `server_name` may end with a NUL byte, but must not contain one elsewhere.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the connection fails, or the server is incompatible,
+    /// see [`Self::try_create_client`] to handle the error instead.
+    pub fn create_client(server_name: &str, dev: &Arc<DevContext>,) -> Arc<Self> {
+        match Self::connect_client(server_name, dev, None, false, None) {
+            Ok(chan) => chan,
+            Err(e) => panic!("Couldn't establish a connection with the server: {}", e),
+        }
+    }
+
+    /// Create a Comm Channel Client Instance, giving up after `timeout`.
+    ///
+    /// The `client_id`, if any, is sent to the server in the handshake.
+    /// Unlike [`Self::create_client`], failures are returned and the endpoint
+    /// is released, so the connection can be retried, e.g. by a [`SupervisedClient`].
+    ///
+    /// # Errors
+    ///
+    ///  - `Doca(DOCA_ERROR_TIME_OUT)`: the server did not answer in time.
+    ///  - `Doca(DOCA_ERROR_CONNECTION_RESET)`: the server went away during the handshake.
+    ///  - `UnsupportedVersion`: the server speaks another protocol version.
+    ///
+    pub fn try_create_client(
+        server_name: &str,
+        dev: &Arc<DevContext>,
+        client_id: Option<u64>,
+        timeout: Duration,
+    ) -> Result<Arc<Self>, CommChanError> {
+        Self::connect_client(server_name, dev, client_id, false, Some(Instant::now() + timeout))
+    }
+
+    /// Connect to the server, announcing in the handshake whether the client
+    /// `sends_heartbeats`, so that the server drops them.
+    fn connect_client(
+        server_name: &str,
+        dev: &Arc<DevContext>,
+        client_id: Option<u64>,
+        sends_heartbeats: bool,
+        deadline: Option<Instant>,
+    ) -> Result<Arc<Self>, CommChanError> {
+        let _span = span!("comm_chan.connect", server = server_name);
+        let name = server_cstring(server_name)?;
+        let mut ep: *mut ffi::doca_comm_channel_ep_t = std::ptr::null_mut();
+        let mut peer_addr: *mut ffi::doca_comm_channel_addr_t = std::ptr::null_mut();
+
+        let ret = unsafe { ffi::doca_comm_channel_ep_create(&mut ep) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret.into());
+        }
+
+        let handshake = Handshake::local(client_id, sends_heartbeats);
+        let res = Self::client_handshake(ep, &mut peer_addr, &name, dev, &handshake, deadline);
+        let (peer, (send_fd, recv_fd)) = match res.and_then(|peer| Ok((peer, event_channel(ep)?))) {
+            Ok(res) => res,
+            Err(e) => {
+                release(ep, peer_addr);
+                return Err(e);
+            }
+        };
+
+        Ok(Arc::new(Self {
+            inner: NonNull::new(ep).unwrap(),
+            peer_addr: NonNull::new(peer_addr).unwrap(),
+            send_fd,
+            recv_fd,
+            max_msg_size: peer.max_msg_size.min(MAX_MSG_SIZE),
+            peer_client_id: None,
+            sends_heartbeats,
+            drops_heartbeats: false,
+            stats: Stats::default(),
+            lock: Mutex::new(()),
+            dev: dev.clone(),
+            dev_rep: None
+        }))
+    }
+
+    fn client_handshake(
+        ep: *mut ffi::doca_comm_channel_ep_t,
+        peer_addr: &mut *mut ffi::doca_comm_channel_addr_t,
+        server_name: &CString,
+        dev: &Arc<DevContext>,
+        handshake: &Handshake,
+        deadline: Option<Instant>,
+    ) -> Result<Handshake, CommChanError> {
+        let expired = || deadline.is_some_and(|d| Instant::now() >= d);
+
+        set_properties(ep, dev)?;
+        check(unsafe{ ffi::doca_comm_channel_ep_connect(ep, server_name.as_ptr(), peer_addr) })?;
+
+        loop {
+            let res = unsafe { ffi::doca_comm_channel_peer_addr_update_info(*peer_addr) };
+            if res != DOCAError::DOCA_ERROR_CONNECTION_INPROGRESS {
+                check(res)?;
+                break;
+            }
+            if expired() {
+                return Err(DOCAError::DOCA_ERROR_TIME_OUT.into());
+            }
+            sleep(Duration::from_millis(1));
+        }
+
+        send_handshake(ep, *peer_addr, handshake, deadline)?;
+
+        let mut msg = vec![0u8; MAX_MSG_SIZE as usize];
+        let mut from: *mut ffi::doca_comm_channel_addr_t = std::ptr::null_mut();
+        loop {
+            let mut len = msg.len();
+            let res = unsafe { ffi::doca_comm_channel_ep_recvfrom(ep, msg.as_mut_ptr().cast(), &mut len, 0, &mut from) };
+            if res == DOCAError::DOCA_SUCCESS {
+                return Handshake::decode(&msg[..len]);
+            }
+            if res != DOCAError::DOCA_ERROR_AGAIN {
+                return Err(res.into());
+            }
+            if expired() {
+                return Err(DOCAError::DOCA_ERROR_TIME_OUT.into());
+            }
+
+            sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// The maximum message size both sides of the connection support.
+    pub fn max_msg_size(&self) -> u32 {
+        self.max_msg_size
+    }
+
+    /// The ID the client sent in its handshake, on the server side.
+    pub fn peer_client_id(&self) -> Option<u64> {
+        self.peer_client_id
+    }
+
+    /// Check that the peer is still connected.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_CONNECTION_RESET`: the peer has disconnected, e.g. the server restarted.
+    ///
+    pub fn peer_status(&self) -> DOCAResult<()> {
+        let _ep = self.lock();
+        let ret = unsafe { ffi::doca_comm_channel_peer_addr_update_info(self.peer_addr.as_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+        Ok(())
+    }
+
+    /// block send req
+    pub fn block_send_req(&self, raw: &RawPointer) {
+        let _span = span!("comm_chan.send", size = raw.payload);
+        let start = Instant::now();
+        loop {
+            let res = self.sendto(raw, start);
+            if res == DOCAError::DOCA_SUCCESS {
+                break;
+            }
+            if res != DOCAError::DOCA_ERROR_AGAIN {
+                panic!("unexpected");
+            }
+
+            sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// block recv req
+    pub fn block_recv_req(&self, raw: &mut RawPointer) {
+        let _span = span!("comm_chan.recv", size = tracing::field::Empty);
+        let capacity = raw.payload;
+        loop {
+            raw.payload = capacity;
+            let (res, _) = self.recvfrom(raw.inner.as_ptr(), &mut raw.payload);
+            if res == DOCAError::DOCA_SUCCESS {
+                break;
+            }
+            if res != DOCAError::DOCA_ERROR_AGAIN {
+                panic!("unexpected");
+            }
+
+            sleep(Duration::from_millis(1));
+        }
+        #[cfg(feature = "tracing")]
+        _span.record("size", raw.payload);
+    }
+
+    /// recv req
+    pub fn recv_req(&self, raw: &mut RawPointer) -> doca_error {
+        let _span = span!("comm_chan.recv", size = tracing::field::Empty);
+        let (res, _) = self.recvfrom(raw.inner.as_ptr(), &mut raw.payload);
+        #[cfg(feature = "tracing")]
+        if res == DOCAError::DOCA_SUCCESS {
+            _span.record("size", raw.payload);
+        }
+        res
+    }
+
+    /// Try to send a request without blocking.
+    ///
+    /// Return `DOCA_ERROR_AGAIN` if the send queue is full.
+    pub fn try_send_req(&self, raw: &RawPointer) -> DOCAResult<()> {
+        let _span = span!("comm_chan.send", size = raw.payload);
+        let res = self.sendto(raw, Instant::now());
+        if res != DOCAError::DOCA_SUCCESS {
+            return Err(res);
+        }
+        Ok(())
+    }
+
+    /// Try to receive a request without blocking,
+    /// `raw.payload` is updated to the length of the received message.
+    ///
+    /// Return `DOCA_ERROR_AGAIN` if no message is pending.
+    pub fn try_recv_req(&self, raw: &mut RawPointer) -> DOCAResult<()> {
+        let res = self.recv_req(raw);
+        if res != DOCAError::DOCA_SUCCESS {
+            return Err(res);
+        }
+        Ok(())
+    }
+
+    /// Try to receive a message into `buf` without blocking.
+    ///
+    /// Return the length of the message and the peer that sent it.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_AGAIN`: no message is pending.
+    ///  - `DOCA_ERROR_NO_MEMORY`: the message was larger than `buf`, and was truncated,
+    ///    buffers of [`MAX_MSG_SIZE`] bytes always fit the messages.
+    ///
+    pub fn try_recv_into(&self, buf: &mut [u8]) -> DOCAResult<(usize, PeerId)> {
+        self.recv_slice(buf).and_then(reject_truncated)
+    }
+
+    /// Receive a message into `buf`, waiting until one arrives.
+    ///
+    /// Return the length of the message and the peer that sent it,
+    /// the errors are the ones of [`Self::try_recv_into`].
+    pub fn recv_into(&self, buf: &mut [u8]) -> DOCAResult<(usize, PeerId)> {
+        loop {
+            match self.try_recv_into(buf) {
+                Err(DOCAError::DOCA_ERROR_AGAIN) => sleep(Duration::from_millis(1)),
+                res => return res,
+            }
+        }
+    }
+
+    /// Return the counters of the endpoint and of each of its peers.
+    pub fn stats(&self) -> CommChanStats {
+        self.stats.snapshot()
+    }
+
+    /// The ID of the connected peer in the [`Self::stats`].
+    pub fn peer_id(&self) -> PeerId {
+        PeerId(self.peer_addr.as_ptr() as usize)
+    }
+
+    /// Send once to the connected peer, counting the attempt,
+    /// `start` is when the first attempt of the message was made.
+    ///
+    /// A client sending heartbeats refuses a message equal to [`HEARTBEAT`]
+    /// with `DOCA_ERROR_INVALID_VALUE`, as the server would drop it.
+    fn sendto(&self, raw: &RawPointer, start: Instant) -> DOCAError {
+        let res = if self.sends_heartbeats && is_heartbeat(unsafe { raw_bytes(raw) }) {
+            DOCAError::DOCA_ERROR_INVALID_VALUE
+        } else {
+            self.send_raw(raw)
+        };
+        self.stats.record_send(self.peer_id(), raw.payload, res, start);
+        res
+    }
+
+    /// Send a [`HEARTBEAT`] to the server, which is not counted in the [`Self::stats`].
+    fn send_heartbeat(&self) -> DOCAResult<()> {
+        let heartbeat = RawPointer {
+            inner: NonNull::from(&HEARTBEAT).cast(),
+            payload: HEARTBEAT.len(),
+        };
+        check(self.send_raw(&heartbeat))
+    }
+
+    /// Send once to the connected peer.
+    fn send_raw(&self, raw: &RawPointer) -> DOCAError {
+        let _ep = self.lock();
+        let (buf, len) = (raw.inner.as_ptr(), raw.payload);
+        unsafe { ffi::doca_comm_channel_ep_sendto(self.inner_ptr(), buf, len, 0, self.peer_addr.as_ptr()) }
+    }
+
+    /// Receive once from any peer into `len` bytes at `buf`, counting the attempt,
+    /// `len` is updated to the length of the message.
+    ///
+    /// On a server whose client announced heartbeats in the handshake, the
+    /// heartbeats are dropped here, so that they never reach the application
+    /// nor its counters.
+    fn recvfrom(&self, buf: *mut c_void, len: &mut usize) -> (DOCAError, Option<PeerId>) {
+        let capacity = *len;
+        loop {
+            *len = capacity;
+            let mut peer_addr: *mut ffi::doca_comm_channel_addr_t = std::ptr::null_mut();
+            let res = {
+                let _ep = self.lock();
+                unsafe { ffi::doca_comm_channel_ep_recvfrom(self.inner_ptr(), buf, len, 0, &mut peer_addr) }
+            };
+            let peer = (!peer_addr.is_null()).then_some(PeerId(peer_addr as usize));
+
+            if res == DOCAError::DOCA_SUCCESS {
+                let received = unsafe { std::slice::from_raw_parts(buf as *const u8, (*len).min(capacity)) };
+                if self.drops_heartbeats && is_heartbeat(received) {
+                    continue;
+                }
+            }
+            self.stats.record_recv(peer, *len, res);
+            return (res, peer);
+        }
+    }
+
+    /// Receive once into `buf`, returning the length of the message clipped to
+    /// the buffer, the peer, and whether the message was truncated.
+    fn recv_slice(&self, buf: &mut [u8]) -> DOCAResult<(usize, PeerId, bool)> {
+        let _span = span!("comm_chan.recv", size = tracing::field::Empty);
+        let mut len = buf.len();
+        let (res, peer) = self.recvfrom(buf.as_mut_ptr().cast(), &mut len);
+        check(res)?;
+        let peer = peer.ok_or(DOCAError::DOCA_ERROR_UNEXPECTED)?;
+
+        #[cfg(feature = "tracing")]
+        _span.record("size", len);
+        let (len, truncated) = clip_len(buf.len(), len);
+        Ok((len, peer, truncated))
+    }
+
+    /// Arm the receive handle, so that [`Self::recv_fd`] becomes readable
+    /// when the next message arrives.
+    ///
+    /// The handle fires once per arm: after a wakeup, drain the endpoint with
+    /// [`Self::try_recv_req`] and arm it again before waiting.
+    pub fn arm_recv(&self) -> DOCAResult<()> {
+        let _ep = self.lock();
+        let ret = unsafe { ffi::doca_comm_channel_ep_event_handle_arm_recv(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+        Ok(())
+    }
+
+    /// Arm the send handle, so that [`Self::send_fd`] becomes readable
+    /// when the send queue has room again.
+    pub fn arm_send(&self) -> DOCAResult<()> {
+        let _ep = self.lock();
+        let ret = unsafe { ffi::doca_comm_channel_ep_event_handle_arm_send(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+        Ok(())
+    }
+
+    /// The event handle signaled on send completions, see [`Self::arm_send`].
+    ///
+    /// The descriptor is owned by the endpoint and must not be closed.
+    pub fn send_fd(&self) -> RawFd {
+        self.send_fd
+    }
+
+    /// The event handle signaled on received messages, see [`Self::arm_recv`].
+    ///
+    /// The descriptor is owned by the endpoint and must not be closed.
+    pub fn recv_fd(&self) -> RawFd {
+        self.recv_fd
+    }
+
+    /// Get the inner pointer of the DOCA COMM CHANNEL
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_comm_channel_ep_t {
+        self.inner.as_ptr()
+    }
+
+    /// Lock the endpoint, held by every call on it through `&self`
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        self.lock.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl AsRawFd for CommChannel {
+    /// The receive event handle, the one to poll for incoming requests.
+    fn as_raw_fd(&self) -> RawFd {
+        self.recv_fd
+    }
+}
+
+/// Convert the server name for C, allowing one trailing NUL byte.
+fn server_cstring(server_name: &str) -> Result<CString, CommChanError> {
+    let name = server_name.strip_suffix('\0').unwrap_or(server_name);
+    CString::new(name).map_err(|_| CommChanError::InvalidName)
+}
+
+fn check(ret: DOCAError) -> DOCAResult<()> {
+    if ret != DOCAError::DOCA_SUCCESS {
+        return Err(ret);
+    }
+    Ok(())
+}
+
+fn set_properties(ep: *mut ffi::doca_comm_channel_ep_t, dev: &Arc<DevContext>) -> DOCAResult<()> {
+    check(unsafe { ffi::doca_comm_channel_ep_set_device(ep, dev.inner_ptr()) })?;
+    check(unsafe { ffi::doca_comm_channel_ep_set_max_msg_size(ep, MAX_MSG_SIZE as _) })?;
+    check(unsafe { ffi::doca_comm_channel_ep_set_send_queue_size(ep, 10) })?;
+    check(unsafe { ffi::doca_comm_channel_ep_set_recv_queue_size(ep, 10) })
+}
+
+fn send_handshake(
+    ep: *mut ffi::doca_comm_channel_ep_t,
+    peer_addr: *mut ffi::doca_comm_channel_addr_t,
+    handshake: &Handshake,
+    deadline: Option<Instant>,
+) -> DOCAResult<()> {
+    let msg = handshake.encode();
+    loop {
+        let res = unsafe { ffi::doca_comm_channel_ep_sendto(ep, msg.as_ptr().cast(), msg.len(), 0, peer_addr) };
+        if res != DOCAError::DOCA_ERROR_AGAIN {
+            return check(res);
+        }
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            return Err(DOCAError::DOCA_ERROR_TIME_OUT);
+        }
+
+        sleep(Duration::from_millis(1));
+    }
+}
+
+/// Release an endpoint that failed to connect.
+fn release(ep: *mut ffi::doca_comm_channel_ep_t, peer_addr: *mut ffi::doca_comm_channel_addr_t) {
+    unsafe {
+        if !peer_addr.is_null() {
+            ffi::doca_comm_channel_ep_disconnect(ep, peer_addr);
+        }
+        ffi::doca_comm_channel_ep_destroy(ep);
+    }
+}
+
+/// Get the send and receive event handles of the endpoint.
+fn event_channel(ep: *mut ffi::doca_comm_channel_ep_t) -> DOCAResult<(RawFd, RawFd)> {
+    let mut send_handle: ffi::doca_event_handle_t = -1;
+    let mut recv_handle: ffi::doca_event_handle_t = -1;
+
+    let ret = unsafe { ffi::doca_comm_channel_ep_get_event_channel(ep, &mut send_handle, &mut recv_handle) };
+    if ret != DOCAError::DOCA_SUCCESS {
+        return Err(ret);
+    }
+
+    Ok((send_handle as RawFd, recv_handle as RawFd))
+}
+
+/// A borrowed event handle of an endpoint, to register it with tokio.
+#[cfg(feature = "async")]
+struct EventHandle(RawFd);
+
+#[cfg(feature = "async")]
+impl AsRawFd for EventHandle {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+/// A Comm Channel endpoint driven by the tokio reactor instead of polling.
+///
+/// Must be created within a tokio runtime. It is `Send` and `Sync`, so it can
+/// be shared by the tasks of a multi-threaded runtime, the sends and receives
+/// on the endpoint are serialized by the lock of the [`CommChannel`].
+///
+/// ```rust, no_run
+/// # async fn example(conn: std::sync::Arc<doca::comm_chan::CommChannel>, mut raw: doca::RawPointer) {
+/// use doca::comm_chan::AsyncCommChannel;
+///
+/// let conn = AsyncCommChannel::new(conn).unwrap();
+/// conn.recv(&mut raw).await.unwrap();
+/// conn.send(&raw).await.unwrap();
+/// # }
+/// ```
+#[cfg(feature = "async")]
+pub struct AsyncCommChannel {
+    send: tokio::io::unix::AsyncFd<EventHandle>,
+    recv: tokio::io::unix::AsyncFd<EventHandle>,
+    chan: Arc<CommChannel>,
+}
+
+#[cfg(feature = "async")]
+impl AsyncCommChannel {
+    /// Register the event handles of the endpoint with the current tokio runtime.
+    pub fn new(chan: Arc<CommChannel>) -> std::io::Result<Self> {
+        use tokio::io::{unix::AsyncFd, Interest};
+
+        let register = |fd: RawFd| {
+            // SAFETY: the event handles are owned by the endpoint, which stays open in `chan`
+            // until the `AsyncFd`s, declared before it, are dropped.
+            unsafe { AsyncFd::register_with_interest(EventHandle(fd), Interest::READABLE) }
+                .map_err(|e| e.into_parts().1)
+        };
+
+        Ok(Self {
+            send: register(chan.send_fd)?,
+            recv: register(chan.recv_fd)?,
+            chan,
+        })
+    }
+
+    /// Send a request, waiting for room in the send queue if it is full.
+    pub async fn send(&self, raw: &RawPointer) -> DOCAResult<()> {
+        loop {
+            match self.chan.try_send_req(raw) {
+                Err(DOCAError::DOCA_ERROR_AGAIN) => {}
+                res => return res,
+            }
+
+            self.chan.arm_send()?;
+            // The queue may have drained between the try and the arm.
+            match self.chan.try_send_req(raw) {
+                Err(DOCAError::DOCA_ERROR_AGAIN) => {}
+                res => return res,
+            }
+
+            let mut guard = self.send.readable().await.map_err(|_| DOCAError::DOCA_ERROR_IO_FAILED)?;
+            guard.clear_ready();
+        }
+    }
+
+    /// Receive a request, waiting until one arrives,
+    /// `raw.payload` is updated to the length of the received message.
+    pub async fn recv(&self, raw: &mut RawPointer) -> DOCAResult<()> {
+        let capacity = raw.payload;
+        let mut woken = false;
+        loop {
+            raw.payload = capacity;
+            match self.chan.try_recv_req(raw) {
+                Err(DOCAError::DOCA_ERROR_AGAIN) if woken => self.chan.stats.record_recv_retry(),
+                Err(DOCAError::DOCA_ERROR_AGAIN) => {}
+                res => return res,
+            }
+
+            self.chan.arm_recv()?;
+            // A message may have arrived between the try and the arm.
+            raw.payload = capacity;
+            match self.chan.try_recv_req(raw) {
+                Err(DOCAError::DOCA_ERROR_AGAIN) => {}
+                res => return res,
+            }
+
+            let mut guard = self.recv.readable().await.map_err(|_| DOCAError::DOCA_ERROR_IO_FAILED)?;
+            guard.clear_ready();
+            woken = true;
+        }
+    }
+
+    /// The underlying endpoint.
+    pub fn channel(&self) -> &Arc<CommChannel> {
+        &self.chan
+    }
+}
+/// Clip the length of a received message to the `capacity` of the buffer,
+/// and tell whether the message was truncated.
+fn clip_len(capacity: usize, len: usize) -> (usize, bool) {
+    (len.min(capacity), len > capacity)
+}
+
+/// The bytes of the message described by `raw`.
+///
+/// # Safety
+///
+/// `raw` must describe `raw.payload` readable bytes, as for sending it.
+unsafe fn raw_bytes(raw: &RawPointer) -> &[u8] {
+    std::slice::from_raw_parts(raw.inner.as_ptr() as *const u8, raw.payload)
+}
+
+/// Report a truncated message as `DOCA_ERROR_NO_MEMORY`, see [`CommChannel::try_recv_into`].
+fn reject_truncated((len, peer, truncated): (usize, PeerId, bool)) -> DOCAResult<(usize, PeerId)> {
+    if truncated {
+        return Err(DOCAError::DOCA_ERROR_NO_MEMORY);
+    }
+    Ok((len, peer))
+}
+
+/// The message a [`SupervisedClient`] sends as heartbeat: the magic and the
+/// version of the handshake, followed by the `HB` message type.
+///
+/// The client announces its heartbeats in the handshake, and the server drops
+/// them, so they are never returned by the receive methods of [`CommChannel`],
+/// [`AsyncCommChannel`] or [`RecvPool`]. Other endpoints do not drop anything.
+/// A client sending heartbeats refuses to send an application message equal
+/// to it, so no application message is ever mistaken for a heartbeat.
+pub const HEARTBEAT: [u8; 8] = [
+    HANDSHAKE_MAGIC[0],
+    HANDSHAKE_MAGIC[1],
+    HANDSHAKE_MAGIC[2],
+    HANDSHAKE_MAGIC[3],
+    PROTOCOL_VERSION.to_le_bytes()[0],
+    PROTOCOL_VERSION.to_le_bytes()[1],
+    b'H',
+    b'B',
+];
+
+/// Whether a received message is a heartbeat of a [`SupervisedClient`].
+pub fn is_heartbeat(msg: &[u8]) -> bool {
+    msg == HEARTBEAT
+}
+
+/// The connection state reported by a [`SupervisedClient`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The client (re)connected to the server.
+    Connected,
+    /// The connection was lost, or the last attempt failed, with the given error.
+    Disconnected(CommChanError),
+    /// The client is about to make its `attempt`-th connection attempt.
+    Connecting {
+        /// The number of the attempt since the connection was lost, from 1.
+        attempt: u32,
+    },
+}
+
+/// Heartbeat and reconnection settings of a [`SupervisedClient`].
+#[derive(Debug, Clone)]
+pub struct SupervisorConfig {
+    heartbeat_interval: Duration,
+    connect_timeout: Duration,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    client_id: Option<u64>,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval: Duration::from_secs(1),
+            connect_timeout: Duration::from_secs(1),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            client_id: None,
+        }
+    }
+}
+
+impl SupervisorConfig {
+    /// Create the default settings: a heartbeat every second, and a backoff
+    /// from 100ms doubling up to 10s between connection attempts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the interval between two heartbeats while the client is idle.
+    pub fn set_heartbeat_interval(&mut self, interval: Duration) -> &mut Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Set how long a connection attempt waits for the server.
+    pub fn set_connect_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Set the delay before the second connection attempt,
+    /// the delay doubles after every failed attempt.
+    pub fn set_initial_backoff(&mut self, backoff: Duration) -> &mut Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Set the maximum delay between two connection attempts.
+    pub fn set_max_backoff(&mut self, backoff: Duration) -> &mut Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Set the ID sent to the server in the handshake of every connection.
+    pub fn set_client_id(&mut self, client_id: u64) -> &mut Self {
+        self.client_id = Some(client_id);
+        self
+    }
+}
+
+/// A Comm Channel client that keeps its connection to the server alive.
+///
+/// The client is driven by the application: [`Self::tick`], which
+/// [`Self::send`] and [`Self::recv`] also call, detects a lost peer,
+/// sends heartbeats when idle, and reconnects with an exponential backoff.
+/// Every state change is reported to the callback given at creation.
+///
+/// ```rust, no_run
+/// use doca::comm_chan::{SupervisedClient, SupervisorConfig};
+///
+/// let device = doca::device::open_device_with_pci("af:00.0").unwrap();
+/// let mut client = SupervisedClient::new("cc_conn", &device, SupervisorConfig::new(), |state| {
+///     println!("comm channel: {:?}", state);
+/// });
+///
+/// loop {
+///     client.tick();
+///     std::thread::sleep(std::time::Duration::from_millis(10));
+/// }
+/// ```
+pub struct SupervisedClient {
+    server_name: String,
+    dev: Arc<DevContext>,
+    config: SupervisorConfig,
+    conn: Option<Arc<CommChannel>>,
+    last_sent: Instant,
+    next_attempt: Instant,
+    backoff: Duration,
+    attempt: u32,
+    on_state: Box<dyn FnMut(ConnectionState)>,
+}
+
+impl SupervisedClient {
+    /// Create a client of the server, and make the first connection attempt.
+    ///
+    /// The client is returned even if the server is not reachable yet.
+    pub fn new(
+        server_name: &str,
+        dev: &Arc<DevContext>,
+        config: SupervisorConfig,
+        on_state: impl FnMut(ConnectionState) + 'static,
+    ) -> Self {
+        let now = Instant::now();
+        let mut client = Self {
+            server_name: server_name.to_string(),
+            dev: dev.clone(),
+            backoff: config.initial_backoff,
+            config,
+            conn: None,
+            last_sent: now,
+            next_attempt: now,
+            attempt: 0,
+            on_state: Box::new(on_state),
+        };
+        client.tick();
+        client
+    }
+
+    /// Check the connection, send a heartbeat if one is due, and reconnect
+    /// if the connection is lost and the backoff has elapsed.
+    /// A connection attempt blocks for up to the connect timeout.
+    ///
+    /// Return the current connection, if any.
+    pub fn tick(&mut self) -> Option<&Arc<CommChannel>> {
+        let now = Instant::now();
+
+        if let Some(conn) = &self.conn {
+            let mut status = conn.peer_status();
+            if status.is_ok() && now.duration_since(self.last_sent) >= self.config.heartbeat_interval {
+                status = match conn.send_heartbeat() {
+                    // A full send queue means the connection is busy anyway.
+                    Ok(()) | Err(DOCAError::DOCA_ERROR_AGAIN) => {
+                        self.last_sent = now;
+                        Ok(())
+                    }
+                    Err(e) => Err(e),
+                };
+            }
+            if let Err(e) = status {
+                self.disconnected(e);
+            }
+        }
+
+        if self.conn.is_none() && now >= self.next_attempt {
+            self.attempt += 1;
+            (self.on_state)(ConnectionState::Connecting { attempt: self.attempt });
+
+            let deadline = Some(Instant::now() + self.config.connect_timeout);
+            match CommChannel::connect_client(&self.server_name, &self.dev, self.config.client_id, true, deadline) {
+                Ok(conn) => {
+                    self.conn = Some(conn);
+                    self.last_sent = Instant::now();
+                    self.attempt = 0;
+                    self.backoff = self.config.initial_backoff;
+                    (self.on_state)(ConnectionState::Connected);
+                }
+                Err(e) => {
+                    self.next_attempt = Instant::now() + self.backoff;
+                    self.backoff = (self.backoff * 2).min(self.config.max_backoff);
+                    (self.on_state)(ConnectionState::Disconnected(e));
+                }
+            }
+        }
+
+        self.conn.as_ref()
+    }
+
+    /// Try to send a request without waiting for room in the send queue.
+    ///
+    /// It calls [`Self::tick`] first, so if a connection attempt is due
+    /// it blocks for up to the connect timeout of the [`SupervisorConfig`].
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NOT_CONNECTED`: the client is waiting to reconnect.
+    ///  - `DOCA_ERROR_AGAIN`: the send queue is full.
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the message is equal to [`HEARTBEAT`].
+    ///
+    pub fn send(&mut self, raw: &RawPointer) -> DOCAResult<()> {
+        let conn = self.tick().ok_or(DOCAError::DOCA_ERROR_NOT_CONNECTED)?;
+        match conn.try_send_req(raw) {
+            Ok(()) => {
+                self.last_sent = Instant::now();
+                Ok(())
+            }
+            Err(e @ (DOCAError::DOCA_ERROR_AGAIN | DOCAError::DOCA_ERROR_INVALID_VALUE)) => Err(e),
+            Err(e) => {
+                self.disconnected(e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Try to receive a request without waiting for one to arrive,
+    /// `raw.payload` is updated to the length of the received message.
+    ///
+    /// It calls [`Self::tick`] first, so if a connection attempt is due
+    /// it blocks for up to the connect timeout of the [`SupervisorConfig`].
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NOT_CONNECTED`: the client is waiting to reconnect.
+    ///  - `DOCA_ERROR_AGAIN`: no message is pending.
+    ///
+    pub fn recv(&mut self, raw: &mut RawPointer) -> DOCAResult<()> {
+        let conn = self.tick().ok_or(DOCAError::DOCA_ERROR_NOT_CONNECTED)?;
+        match conn.try_recv_req(raw) {
+            Ok(()) => Ok(()),
+            Err(DOCAError::DOCA_ERROR_AGAIN) => Err(DOCAError::DOCA_ERROR_AGAIN),
+            Err(e) => {
+                self.disconnected(e);
+                Err(e)
+            }
+        }
+    }
+
+    /// The current connection, if the client is connected.
+    pub fn channel(&self) -> Option<&Arc<CommChannel>> {
+        self.conn.as_ref()
+    }
+
+    /// Whether the client is connected.
+    pub fn is_connected(&self) -> bool {
+        self.conn.is_some()
+    }
+
+    fn disconnected(&mut self, e: DOCAError) {
+        self.conn = None;
+        self.next_attempt = Instant::now();
+        (self.on_state)(ConnectionState::Disconnected(e.into()));
+    }
+}
+
+mod tests {
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_async_chan_can_be_spawned() {
+        use super::*;
+
+        fn assert_send_sync<T: Send + Sync>() {}
+        fn assert_send<T: Send>(_: &T) {}
+
+        assert_send_sync::<CommChannel>();
+        assert_send_sync::<AsyncCommChannel>();
+
+        // Only type-checked: the task can be given to `tokio::spawn` on a multi-threaded runtime.
+        #[allow(dead_code)]
+        fn echo(conn: Arc<AsyncCommChannel>, mut raw: RawPointer) {
+            let task = async move {
+                conn.recv(&mut raw).await?;
+                conn.send(&raw).await
+            };
+            assert_send(&task);
+        }
+    }
+
+    #[test]
+    fn test_handshake_round_trip() {
+        use super::*;
+
+        let local = Handshake::local(Some(42), true);
+        assert_eq!(Handshake::decode(&local.encode()), Ok(local));
+
+        let anonymous = Handshake::local(None, false);
+        let decoded = Handshake::decode(&anonymous.encode()).unwrap();
+        assert_eq!(decoded.client_id, None);
+        assert!(!decoded.sends_heartbeats);
+    }
+
+    #[test]
+    fn test_heartbeat_is_not_a_handshake() {
+        use super::*;
+
+        assert_eq!(HEARTBEAT[0..4], HANDSHAKE_MAGIC);
+        assert_eq!(Handshake::decode(&HEARTBEAT), Err(CommChanError::Malformed { len: HEARTBEAT.len() }));
+        // The heartbeat of the first protocol version is an application message now.
+        assert!(!is_heartbeat(b"\0HB\0"));
+    }
+
+    #[test]
+    fn test_handshake_rejects_incompatible_peers() {
+        use super::*;
+
+        // The 2 bytes message sent before the handshake existed.
+        assert_eq!(Handshake::decode(&[1u8; 2]), Err(CommChanError::BadMagic));
+
+        let mut msg = Handshake::local(None, false).encode();
+        msg[4..6].copy_from_slice(&(PROTOCOL_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            Handshake::decode(&msg),
+            Err(CommChanError::UnsupportedVersion { local: PROTOCOL_VERSION, peer: PROTOCOL_VERSION + 1 })
+        );
+
+        let msg = Handshake::local(None, false).encode();
+        assert_eq!(Handshake::decode(&msg[..12]), Err(CommChanError::Malformed { len: 12 }));
+    }
+
+    #[test]
+    fn test_truncated_message() {
+        use super::*;
+
+        assert_eq!(clip_len(8, 4), (4, false));
+        assert_eq!(clip_len(8, 8), (8, false));
+        assert_eq!(clip_len(8, 12), (8, true));
+
+        assert_eq!(reject_truncated((4, PeerId(1), false)), Ok((4, PeerId(1))));
+        assert_eq!(reject_truncated((8, PeerId(1), true)), Err(DOCAError::DOCA_ERROR_NO_MEMORY));
+    }
+
+    #[test]
+    fn test_server_name_nul_handling() {
+        use super::*;
+
+        assert_eq!(server_cstring("cc_conn").unwrap().as_bytes(), b"cc_conn");
+        assert_eq!(server_cstring("cc_conn\0").unwrap().as_bytes(), b"cc_conn");
+        assert_eq!(server_cstring("cc\0conn"), Err(CommChanError::InvalidName));
+    }
+}
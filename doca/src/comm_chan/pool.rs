@@ -0,0 +1,210 @@
+//! Reusable receive buffers of a Comm Channel endpoint.
+//!
+//! A [`RecvPool`] allocates its buffers once, and every received message
+//! borrows one of them as a [`RecvBuf`] until it is dropped, so receiving
+//! does not allocate. A [`RecvBuf`] can be handed to another thread, and
+//! goes back to the pool from there.
+//!
+//! ```rust, no_run
+//! use doca::comm_chan::{CommChannel, RecvPool};
+//!
+//! let device = doca::device::open_device_with_pci("af:00.0").unwrap();
+//! let conn = CommChannel::create_client("cc_conn", &device);
+//!
+//! let pool = RecvPool::new(&conn, 16);
+//! let msg = pool.recv().unwrap();
+//! println!("{} bytes from {:?}: {:?}", msg.len(), msg.peer(), &msg[..]);
+//! ```
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+
+use super::{CommChannel, PeerId, MAX_MSG_SIZE};
+use crate::{DOCAError, DOCAResult};
+
+/// The buffers of a pool not held by received messages
+#[derive(Clone)]
+struct FreeList(Arc<Mutex<Vec<Box<[u8]>>>>);
+
+impl FreeList {
+    fn new(count: usize, size: usize) -> Self {
+        let free = (0..count).map(|_| vec![0u8; size].into_boxed_slice()).collect();
+        Self(Arc::new(Mutex::new(free)))
+    }
+
+    /// Take a buffer, `DOCA_ERROR_NO_MEMORY` if all of them are held
+    fn take(&self) -> DOCAResult<Box<[u8]>> {
+        self.0.lock().unwrap().pop().ok_or(DOCAError::DOCA_ERROR_NO_MEMORY)
+    }
+
+    fn put(&self, buf: Box<[u8]>) {
+        self.0.lock().unwrap().push(buf);
+    }
+
+    fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+}
+
+/// A fixed set of receive buffers of an endpoint.
+pub struct RecvPool {
+    chan: Arc<CommChannel>,
+    free: FreeList,
+}
+
+impl RecvPool {
+    /// Create a pool of `count` buffers of [`MAX_MSG_SIZE`] bytes,
+    /// which always fit the messages.
+    pub fn new(chan: &Arc<CommChannel>, count: usize) -> Self {
+        Self::with_buffer_size(chan, count, MAX_MSG_SIZE as usize)
+    }
+
+    /// Create a pool of `count` buffers of `size` bytes.
+    ///
+    /// Longer messages are received truncated, see [`RecvBuf::is_truncated`].
+    pub fn with_buffer_size(chan: &Arc<CommChannel>, count: usize, size: usize) -> Self {
+        Self {
+            chan: chan.clone(),
+            free: FreeList::new(count, size),
+        }
+    }
+
+    /// Try to receive a message without blocking.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_AGAIN`: no message is pending.
+    ///  - `DOCA_ERROR_NO_MEMORY`: all the buffers are held by received messages.
+    ///
+    pub fn try_recv(&self) -> DOCAResult<RecvBuf> {
+        let mut buf = self.free.take()?;
+
+        match self.chan.recv_slice(&mut buf) {
+            Ok((len, peer, truncated)) => Ok(RecvBuf {
+                buf: Some(buf),
+                len,
+                peer,
+                truncated,
+                free: self.free.clone(),
+            }),
+            Err(e) => {
+                self.free.put(buf);
+                Err(e)
+            }
+        }
+    }
+
+    /// Receive a message, waiting until one arrives.
+    ///
+    /// The errors are the ones of [`Self::try_recv`], except `DOCA_ERROR_AGAIN`.
+    pub fn recv(&self) -> DOCAResult<RecvBuf> {
+        loop {
+            match self.try_recv() {
+                Err(DOCAError::DOCA_ERROR_AGAIN) => sleep(Duration::from_millis(1)),
+                res => return res,
+            }
+        }
+    }
+
+    /// The number of buffers not held by received messages.
+    pub fn available(&self) -> usize {
+        self.free.len()
+    }
+}
+
+/// A received message, in a buffer that goes back to its [`RecvPool`] on drop.
+///
+/// It dereferences to the bytes of the message.
+pub struct RecvBuf {
+    buf: Option<Box<[u8]>>,
+    len: usize,
+    peer: PeerId,
+    truncated: bool,
+    free: FreeList,
+}
+
+impl RecvBuf {
+    /// The peer that sent the message.
+    pub fn peer(&self) -> PeerId {
+        self.peer
+    }
+
+    /// Whether the message was longer than the buffer, and lost its tail.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+impl Deref for RecvBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf.as_ref().unwrap()[..self.len]
+    }
+}
+
+impl DerefMut for RecvBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buf.as_mut().unwrap()[..self.len]
+    }
+}
+
+impl AsRef<[u8]> for RecvBuf {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Drop for RecvBuf {
+    fn drop(&mut self) {
+        if let Some(buf) = self.buf.take() {
+            self.free.put(buf);
+        }
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_recv_buf_returns_to_the_pool() {
+        use super::*;
+
+        let free = FreeList::new(2, 8);
+        let held: Vec<RecvBuf> = (0..2)
+            .map(|_| RecvBuf {
+                buf: Some(free.take().unwrap()),
+                len: 4,
+                peer: PeerId(1),
+                truncated: false,
+                free: free.clone(),
+            })
+            .collect();
+        assert_eq!(free.len(), 0);
+        assert_eq!(free.take().err(), Some(DOCAError::DOCA_ERROR_NO_MEMORY));
+        assert_eq!(held[0].len(), 4);
+
+        // the buffers go back to the pool from any thread
+        std::thread::spawn(move || drop(held)).join().unwrap();
+        assert_eq!(free.len(), 2);
+    }
+
+    #[test]
+    fn test_truncated_recv_buf() {
+        use super::*;
+        use crate::comm_chan::clip_len;
+
+        let free = FreeList::new(1, 4);
+        let (len, truncated) = clip_len(4, 6);
+        let msg = RecvBuf {
+            buf: Some(free.take().unwrap()),
+            len,
+            peer: PeerId(1),
+            truncated,
+            free: free.clone(),
+        };
+        assert_eq!(msg.len(), 4);
+        assert!(msg.is_truncated());
+        assert_eq!(clip_len(4, 3), (3, false));
+    }
+}
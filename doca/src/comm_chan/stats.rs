@@ -0,0 +1,159 @@
+//! Counters of the messages exchanged over a Comm Channel endpoint.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::DOCAError;
+
+/// Identifies a peer of an endpoint in its [`CommChanStats`].
+///
+/// It is derived from the DOCA peer address, so it stays the same for the
+/// lifetime of a connection, and may be reused after a reconnection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PeerId(pub(crate) usize);
+
+/// Message counters, of an endpoint or of one of its peers.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelCounters {
+    /// Messages sent.
+    pub msgs_sent: u64,
+    /// Messages received.
+    pub msgs_received: u64,
+    /// Payload bytes sent.
+    pub bytes_sent: u64,
+    /// Payload bytes received.
+    pub bytes_received: u64,
+    /// Sends that returned `DOCA_ERROR_AGAIN` because the send queue was full.
+    pub send_retries: u64,
+    /// Receives retried because the receive event handle woke up a pending
+    /// [`AsyncCommChannel::recv`](super::AsyncCommChannel::recv) without a message.
+    /// Receives finding no message otherwise are idle polls, which are not counted.
+    pub recv_retries: u64,
+    /// Sends and receives that failed with another error.
+    pub errors: u64,
+    /// Total time from the first attempt to send a message until it was queued.
+    pub send_latency_total: Duration,
+    /// The longest time a message took to be queued.
+    pub send_latency_max: Duration,
+}
+
+impl ChannelCounters {
+    /// The mean time a message took to be queued, including the retries.
+    pub fn send_latency_mean(&self) -> Duration {
+        if self.msgs_sent == 0 {
+            return Duration::ZERO;
+        }
+        self.send_latency_total / self.msgs_sent as u32
+    }
+
+    fn record_send(&mut self, bytes: usize, res: DOCAError, start: Instant) {
+        match res {
+            DOCAError::DOCA_SUCCESS => {
+                let latency = start.elapsed();
+                self.msgs_sent += 1;
+                self.bytes_sent += bytes as u64;
+                self.send_latency_total += latency;
+                self.send_latency_max = self.send_latency_max.max(latency);
+            }
+            DOCAError::DOCA_ERROR_AGAIN => self.send_retries += 1,
+            _ => self.errors += 1,
+        }
+    }
+
+    fn record_recv(&mut self, bytes: usize, res: DOCAError) {
+        match res {
+            DOCAError::DOCA_SUCCESS => {
+                self.msgs_received += 1;
+                self.bytes_received += bytes as u64;
+            }
+            // no message was pending
+            DOCAError::DOCA_ERROR_AGAIN => {}
+            _ => self.errors += 1,
+        }
+    }
+}
+
+/// A snapshot of the counters of an endpoint, see [`CommChannel::stats`](super::CommChannel::stats).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CommChanStats {
+    /// The counters of the whole endpoint.
+    pub endpoint: ChannelCounters,
+    /// The counters of each peer the endpoint exchanged messages with.
+    pub peers: BTreeMap<PeerId, ChannelCounters>,
+}
+
+/// The counters updated by an endpoint.
+#[derive(Default)]
+pub(crate) struct Stats {
+    inner: Mutex<StatsInner>,
+}
+
+#[derive(Default)]
+struct StatsInner {
+    endpoint: ChannelCounters,
+    peers: HashMap<PeerId, ChannelCounters>,
+}
+
+impl Stats {
+    pub(crate) fn record_send(&self, peer: PeerId, bytes: usize, res: DOCAError, start: Instant) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.endpoint.record_send(bytes, res, start);
+        inner.peers.entry(peer).or_default().record_send(bytes, res, start);
+    }
+
+    /// `peer` is unknown when no message was received.
+    pub(crate) fn record_recv(&self, peer: Option<PeerId>, bytes: usize, res: DOCAError) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.endpoint.record_recv(bytes, res);
+        if let Some(peer) = peer {
+            inner.peers.entry(peer).or_default().record_recv(bytes, res);
+        }
+    }
+
+    /// The peer of a retried receive is unknown, as no message was received.
+    #[cfg_attr(not(feature = "async"), allow(dead_code))]
+    pub(crate) fn record_recv_retry(&self) {
+        self.inner.lock().unwrap().endpoint.recv_retries += 1;
+    }
+
+    pub(crate) fn snapshot(&self) -> CommChanStats {
+        let inner = self.inner.lock().unwrap();
+        CommChanStats {
+            endpoint: inner.endpoint,
+            peers: inner.peers.iter().map(|(peer, counters)| (*peer, *counters)).collect(),
+        }
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_stats_per_peer() {
+        use super::*;
+
+        let stats = Stats::default();
+        let (a, b) = (PeerId(1), PeerId(2));
+
+        stats.record_send(a, 10, DOCAError::DOCA_ERROR_AGAIN, Instant::now());
+        stats.record_send(a, 10, DOCAError::DOCA_SUCCESS, Instant::now());
+        stats.record_recv(Some(b), 20, DOCAError::DOCA_SUCCESS);
+        stats.record_recv(None, 0, DOCAError::DOCA_ERROR_AGAIN);
+        stats.record_recv(Some(b), 0, DOCAError::DOCA_ERROR_CONNECTION_RESET);
+        assert_eq!(stats.snapshot().endpoint.recv_retries, 0);
+        stats.record_recv_retry();
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.endpoint.msgs_sent, 1);
+        assert_eq!(snapshot.endpoint.bytes_sent, 10);
+        assert_eq!(snapshot.endpoint.send_retries, 1);
+        assert_eq!(snapshot.endpoint.msgs_received, 1);
+        assert_eq!(snapshot.endpoint.recv_retries, 1);
+        assert_eq!(snapshot.endpoint.errors, 1);
+
+        assert_eq!(snapshot.peers[&a].msgs_sent, 1);
+        assert_eq!(snapshot.peers[&a].msgs_received, 0);
+        assert_eq!(snapshot.peers[&b].bytes_received, 20);
+        assert_eq!(snapshot.peers[&b].errors, 1);
+        assert_eq!(snapshot.peers[&b].recv_retries, 0);
+    }
+}
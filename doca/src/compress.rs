@@ -0,0 +1,339 @@
+//! Wrapper for DOCA Compress. It provides the ability of
+//! compressing and decompressing data with the deflate algorithm
+//! using hardware acceleration.
+//!
+//! It basically contains two core structs:
+//! - [`CompressJob`]: A deflate compress or decompress request. It implements the trait
+//! [`ToBaseJob`], which makes it capable for being submitted to the work queue.
+//!
+//! - [`CompressEngine`]: The Compress Engine of DOCA. Users should create an instance of
+//! the engine and execute the jobs on a [`DOCAContext`] of the engine.
+//!
+//! Once a job is completed, [`CompressJob::output`] reports the length of the output and
+//! its CRC and Adler checksums.
+//!
+//! The engine is built on the DOCA 1.5 work queue, and requires the `compress` feature.
+//!
+//! # Examples
+//!
+//! Compress the data of a buffer into another one.
+//!
+//! ``` rust, no_run
+//! use doca::compress::{CompressEngine, CompressJobType};
+//! use doca::context::DOCAContext;
+//! use doca::DOCAWorkQueue;
+//!
+//! let device = doca::device::open_device_with_pci("17:00.0").unwrap();
+//! let compress = CompressEngine::new().unwrap();
+//! let ctx = DOCAContext::new(&compress, vec![device]).unwrap();
+//! let mut workq = DOCAWorkQueue::new(1, &ctx).unwrap();
+//!
+//! # let (src_buf, dst_buf): (doca::DOCABuffer, doca::DOCABuffer) = unimplemented!();
+//! let job = workq
+//!     .create_compress_job(CompressJobType::DeflateCompress, src_buf, dst_buf)
+//!     .unwrap();
+//! workq.submit(&job).unwrap();
+//!
+//! let event = loop {
+//!     if let Ok(event) = workq.poll_completion() {
+//!         break event;
+//!     }
+//! };
+//! let output = job.output(&event).unwrap();
+//! println!("compressed into {} bytes, crc {:#x}", output.len, output.crc);
+//! ```
+//!
+
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+use crate::context::work_queue::ToBaseJob;
+use crate::context::EngineToContext;
+use crate::{DOCABuffer, DOCAError, DOCAResult};
+
+pub use crate::context::work_queue::{DOCAEvent, DOCAWorkQueue};
+pub use crate::context::DOCAContext;
+
+/// DOCA Compress engine instance
+pub struct CompressEngine {
+    inner: NonNull<ffi::doca_compress>,
+}
+
+unsafe impl Sync for CompressEngine {}
+unsafe impl Send for CompressEngine {}
+
+impl Drop for CompressEngine {
+    fn drop(&mut self) {
+        let ret = unsafe { ffi::doca_compress_destroy(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            panic!("Failed to destory compress engine!");
+        }
+
+        trace!(compress = ?self.inner, "Compress Engine is dropped");
+    }
+}
+
+/// Implementation `EngineToContext` Trait for Compress Engine
+impl EngineToContext for CompressEngine {
+    unsafe fn to_ctx(&self) -> *mut ffi::doca_ctx {
+        ffi::doca_compress_as_ctx(self.inner_ptr())
+    }
+}
+
+impl CompressEngine {
+    /// Create a DOCA Compress instance.
+    pub fn new() -> DOCAResult<Arc<Self>> {
+        let mut compress: *mut ffi::doca_compress = std::ptr::null_mut();
+        let ret = unsafe { ffi::doca_compress_create(&mut compress as *mut _) };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(Arc::new(Self {
+            inner: unsafe { NonNull::new_unchecked(compress) },
+        }))
+    }
+
+    /// Get the inner pointer of the DOCA Compress instance.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_compress {
+        self.inner.as_ptr()
+    }
+}
+
+/// The types of the compress jobs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CompressJobType {
+    /// Compress the source with deflate
+    DeflateCompress,
+    /// Decompress the deflate compressed source
+    DeflateDecompress,
+}
+
+impl CompressJobType {
+    /// Get the DOCA job type
+    #[inline]
+    pub(crate) fn to_raw(self) -> ffi::doca_compress_job_types {
+        match self {
+            CompressJobType::DeflateCompress => ffi::DOCA_COMPRESS_DEFLATE_JOB,
+            CompressJobType::DeflateDecompress => ffi::DOCA_DECOMPRESS_DEFLATE_JOB,
+        }
+    }
+}
+
+/// The result of a completed compress job
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompressOutput {
+    /// The number of bytes written into the destination buffer
+    pub len: usize,
+    /// The CRC checksum of the uncompressed data
+    pub crc: u32,
+    /// The Adler checksum of the uncompressed data
+    pub adler: u32,
+}
+
+/// A DOCA deflate compress or decompress request
+///
+/// The output is appended to the data of the destination buffer.
+pub struct CompressJob {
+    pub(crate) inner: ffi::doca_compress_deflate_job,
+
+    #[allow(dead_code)]
+    ctx: Arc<DOCAContext<CompressEngine>>,
+
+    src_buff: DOCABuffer,
+    dst_buff: DOCABuffer,
+    // The length of the destination data before the job
+    dst_len: usize,
+
+    // DOCA writes the checksum here once the job is done,
+    // so it must not move while the job is in flight
+    checksum: Box<u64>,
+}
+
+// SAFETY: the raw pointers in the DOCA job only point to the `doca_buf`s of
+// `src_buff` and `dst_buff`, to the boxed `checksum` and to the context, which
+// the job owns or shares. The heap data does not move with the job, and no other
+// thread can reach it.
+unsafe impl Send for CompressJob {}
+
+/// Implementation of `ToBaseJob` Trait
+impl ToBaseJob for CompressJob {
+    fn to_base(&self) -> &ffi::doca_job {
+        &self.inner.base
+    }
+}
+
+impl CompressJob {
+    /// Create a compress job on the given context
+    pub(crate) fn new(
+        ctx: &Arc<DOCAContext<CompressEngine>>,
+        job_type: CompressJobType,
+        src_buf: DOCABuffer,
+        dst_buf: DOCABuffer,
+    ) -> DOCAResult<Self> {
+        let mut res = Self {
+            inner: Default::default(),
+            ctx: ctx.clone(),
+            dst_len: dst_buf.data_len()?,
+            src_buff: src_buf,
+            dst_buff: dst_buf,
+            checksum: Box::new(0),
+        };
+
+        res.inner.base.ctx = unsafe { ctx.inner_ptr() };
+        res.inner.base.flags = ffi::DOCA_JOB_FLAGS_NONE as i32;
+        res.inner.base.type_ = job_type.to_raw() as i32;
+        unsafe {
+            res.inner.src_buff = res.src_buff.inner_ptr();
+            res.inner.dst_buff = res.dst_buff.inner_ptr();
+        }
+        res.inner.output_chksum = &mut *res.checksum as *mut u64;
+        Ok(res)
+    }
+
+    /// Get the type of the job
+    pub fn job_type(&self) -> CompressJobType {
+        if self.inner.base.type_ == ffi::DOCA_DECOMPRESS_DEFLATE_JOB as i32 {
+            CompressJobType::DeflateDecompress
+        } else {
+            CompressJobType::DeflateCompress
+        }
+    }
+
+    /// Set the data pointer of the src buffer
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the data is out of the buffer.
+    ///
+    #[inline]
+    pub fn set_src_data(&mut self, offset: usize, payload: usize) -> DOCAResult<()> {
+        unsafe { self.src_buff.set_data(offset, payload) }
+    }
+
+    /// Set the data pointer of the dst buffer, the output is appended after it
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the data is out of the buffer.
+    ///
+    #[inline]
+    pub fn set_dst_data(&mut self, offset: usize, payload: usize) -> DOCAResult<()> {
+        unsafe { self.dst_buff.set_data(offset, payload)? };
+        self.dst_len = payload;
+        Ok(())
+    }
+
+    /// Set mark for user data
+    #[inline]
+    pub fn set_user_data(&mut self, user_data: u64) -> &mut Self {
+        self.inner.base.user_data.u64_ = user_data;
+        self
+    }
+
+    /// Get the mark for user data
+    #[inline]
+    pub fn user_data(&self) -> u64 {
+        unsafe { self.inner.base.user_data.u64_ }
+    }
+
+    /// Get the output of the job from its completion event.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the event does not belong to this job.
+    ///  - The error of the job reported by the event.
+    ///
+    pub fn output(&self, event: &DOCAEvent) -> DOCAResult<CompressOutput> {
+        if event.user_mark() != self.user_data() {
+            return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+        }
+
+        let ret = event.result();
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        let checksum = *self.checksum;
+        Ok(CompressOutput {
+            len: self.dst_buff.data_len()? - self.dst_len,
+            // the lower 32 bits are the CRC, and the upper 32 bits the Adler checksum
+            crc: checksum as u32,
+            adler: (checksum >> 32) as u32,
+        })
+    }
+
+    /// Return the source and destination buffers of the job
+    pub fn into_buffers(self) -> (DOCABuffer, DOCABuffer) {
+        (self.src_buff, self.dst_buff)
+    }
+}
+
+impl DOCAWorkQueue<CompressEngine> {
+    /// Create a compress job
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the data length of the destination buffer can not be read.
+    ///
+    pub fn create_compress_job(
+        &self,
+        job_type: CompressJobType,
+        src_buf: DOCABuffer,
+        dst_buf: DOCABuffer,
+    ) -> DOCAResult<CompressJob> {
+        CompressJob::new(&self.ctx, job_type, src_buf, dst_buf)
+    }
+}
+
+mod tests {
+    #[test]
+    #[ignore = "needs a DOCA device supporting deflate compress"]
+    fn test_compress_round_trip() {
+        use crate::compress::*;
+        use crate::*;
+
+        let dev = devices().unwrap().get(0).unwrap();
+        assert!(dev.supports_deflate_compress().unwrap());
+        let device = dev.open().unwrap();
+
+        let compress = CompressEngine::new().unwrap();
+        let ctx = DOCAContext::new(&compress, vec![device.clone()]).unwrap();
+        let mut workq = DOCAWorkQueue::new(1, &ctx).unwrap();
+
+        let test_len = 4096;
+        let mut src_buffer = vec![7u8; test_len].into_boxed_slice();
+        let mut compressed = vec![0u8; test_len].into_boxed_slice();
+        let mut dst_buffer = vec![0u8; test_len].into_boxed_slice();
+        let inv = BufferInventory::new(16).unwrap();
+
+        let to_buffer =
+            |buffer: &mut [u8], len: usize| crate::memory::test_buffer(&device, &inv, buffer, len);
+
+        let mut run = |job_type, src_buf, dst_buf| {
+            let job = workq
+                .create_compress_job(job_type, src_buf, dst_buf)
+                .unwrap();
+            workq.submit(&job).unwrap();
+            let event = loop {
+                if let Ok(event) = workq.poll_completion() {
+                    break event;
+                }
+            };
+            job.output(&event).unwrap()
+        };
+
+        let src_buf = to_buffer(&mut src_buffer, test_len);
+        let compressed_buf = to_buffer(&mut compressed, 0);
+        let output = run(CompressJobType::DeflateCompress, src_buf, compressed_buf);
+        assert!(output.len > 0 && output.len < test_len);
+
+        let compressed_buf = to_buffer(&mut compressed, output.len);
+        let dst_buf = to_buffer(&mut dst_buffer, 0);
+        let decompressed = run(CompressJobType::DeflateDecompress, compressed_buf, dst_buf);
+        assert_eq!(decompressed.len, test_len);
+        assert_eq!(decompressed.crc, output.crc);
+        assert_eq!(src_buffer, dst_buffer);
+    }
+}
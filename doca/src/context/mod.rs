@@ -13,21 +13,28 @@
 //! - [`DOCAWorkQueue`]  is a per-thread object used to queue jobs to
 //! offload to DOCA and eventually receive their completion status.
 //!
+//! [`DOCAContext`] and the work queue are the DOCA 1.5 execution model. DOCA 2.x replaced
+//! them with the progress engine and the tasks, see [`crate::pe`].
+//!
 
+#[cfg(not(doca_2_x))]
 use crate::{DOCAError, DOCAResult, DevContext};
 
+#[cfg(not(doca_2_x))]
 use std::ptr::NonNull;
-use std::sync::Arc;
+#[cfg(not(doca_2_x))]
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 
 /// Each DOCA Engine should implement their trait to
 /// transfer the engine instance into a DOCA CTX instance
-pub trait EngineToContext {
+pub trait EngineToContext: 'static {
     /// Get a DOCA CTX from a DOCA Engine instance
     unsafe fn to_ctx(&self) -> *mut ffi::doca_ctx;
 }
 
 /// DOCA context
 /// DOCAContext is a thread-safe object.
+#[cfg(not(doca_2_x))]
 pub struct DOCAContext<T: EngineToContext> {
     inner: NonNull<ffi::doca_ctx>,
 
@@ -36,8 +43,20 @@ pub struct DOCAContext<T: EngineToContext> {
     pub(crate) engine: Arc<T>,
     #[allow(dead_code)]
     added_devs: Vec<Arc<DevContext>>,
+    // Serialize adding and removing the work queues of different threads
+    workq_lock: Mutex<()>,
 }
 
+// SAFETY: the context is started before it is shared, and the only calls on it
+// through `&self` add and remove work queues, which hold `workq_lock`.
+// The engine is kept alive by the context, so it must be shareable as well.
+#[cfg(not(doca_2_x))]
+unsafe impl<T: EngineToContext + Send + Sync> Sync for DOCAContext<T> {}
+// SAFETY: `doca_ctx` is not bound to the thread that created it.
+#[cfg(not(doca_2_x))]
+unsafe impl<T: EngineToContext + Send + Sync> Send for DOCAContext<T> {}
+
+#[cfg(not(doca_2_x))]
 impl<T: EngineToContext> DOCAContext<T> {
     /// Create a new DOCA context based on the Engine instance.
     pub fn new(engine: &Arc<T>, added_devs: Vec<Arc<DevContext>>) -> DOCAResult<Arc<Self>> {
@@ -47,6 +66,7 @@ impl<T: EngineToContext> DOCAContext<T> {
             inner: unsafe { NonNull::new_unchecked(engine.to_ctx()) },
             engine: engine.clone(),
             added_devs: Vec::new(),
+            workq_lock: Mutex::new(()),
         };
 
         // add device to it
@@ -62,6 +82,7 @@ impl<T: EngineToContext> DOCAContext<T> {
     }
 }
 
+#[cfg(not(doca_2_x))]
 impl<T: EngineToContext> Drop for DOCAContext<T> {
     fn drop(&mut self) {
         let _ = self.stop().map_err(|e| {
@@ -75,12 +96,11 @@ impl<T: EngineToContext> Drop for DOCAContext<T> {
             }
         }
 
-        // Show drop order only in `debug` mode
-        #[cfg(debug_assertions)]
-        println!("DOCA Context is dropped!");
+        trace!(ctx = ?self.inner, "DOCA Context is dropped");
     }
 }
 
+#[cfg(not(doca_2_x))]
 impl<T: EngineToContext> DOCAContext<T> {
     /// Finalizes all configurations, and starts the DOCA CTX.
     pub fn start(&mut self) -> DOCAResult<()> {
@@ -104,8 +124,17 @@ impl<T: EngineToContext> DOCAContext<T> {
     pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_ctx {
         self.inner.as_ptr()
     }
+
+    /// Lock the context to add or remove a work queue
+    #[inline]
+    pub(crate) fn lock_workqs(&self) -> MutexGuard<'_, ()> {
+        self.workq_lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
 }
 
+#[cfg(not(doca_2_x))]
 impl<T: EngineToContext> DOCAContext<T> {
     /// Add a device to a DOCA CTX.
     #[inline]
@@ -120,4 +149,5 @@ impl<T: EngineToContext> DOCAContext<T> {
 }
 
 /// WorkQueue
+#[cfg(not(doca_2_x))]
 pub mod work_queue;
@@ -72,10 +72,18 @@ pub struct DOCAWorkQueue<T: EngineToContext> {
     pub(crate) ctx: Arc<DOCAContext<T>>,
 }
 
+// SAFETY: a `doca_workq` may be used by one thread at a time, which `&mut self`
+// on `submit` and `poll_completion` ensures since the type is not `Sync`.
+// Adding it to and removing it from the shared context hold the lock of the context.
+unsafe impl<T: EngineToContext + Send + Sync> Send for DOCAWorkQueue<T> {}
+
 impl<T: EngineToContext> Drop for DOCAWorkQueue<T> {
     fn drop(&mut self) {
         // remove the worker queue from the context
-        let ret = unsafe { ffi::doca_ctx_workq_rm(self.ctx.inner_ptr(), self.inner_ptr()) };
+        let ret = {
+            let _guard = self.ctx.lock_workqs();
+            unsafe { ffi::doca_ctx_workq_rm(self.ctx.inner_ptr(), self.inner_ptr()) }
+        };
         assert_eq!(
             ret,
             DOCAError::DOCA_SUCCESS,
@@ -83,9 +91,7 @@ impl<T: EngineToContext> Drop for DOCAWorkQueue<T> {
         );
         unsafe { ffi::doca_workq_destroy(self.inner_ptr()) };
 
-        // Show drop order only in `debug` mode
-        #[cfg(debug_assertions)]
-        println!("DOCA WorkQ is dropped!");
+        trace!(workq = ?self.inner, depth = self.depth, "DOCA WorkQ is dropped");
     }
 }
 
@@ -106,7 +112,10 @@ impl<T: EngineToContext> DOCAWorkQueue<T> {
         };
 
         // add the myself to the context
-        let ret = unsafe { ffi::doca_ctx_workq_add(ctx.inner_ptr(), res.inner_ptr()) };
+        let ret = {
+            let _guard = ctx.lock_workqs();
+            unsafe { ffi::doca_ctx_workq_add(ctx.inner_ptr(), res.inner_ptr()) }
+        };
 
         if ret != DOCAError::DOCA_SUCCESS {
             return Err(ret);
@@ -167,6 +176,7 @@ impl<T: EngineToContext> DOCAWorkQueue<T> {
     }
 }
 
+#[cfg(feature = "dma")]
 mod tests {
     #[test]
     fn test_worker_queue_create() {
@@ -23,12 +23,52 @@
 //! let device_ctx = devices().unwrap().get(0).unwrap().open().unwrap();
 //! ```
 //!
+//! Besides the PCI address, a [`Device`] can be queried for its interface name,
+//! IB device name, MAC/IP addresses, PCI function type and capabilities.
+//! The [`DeviceFilter`] picks a device by any of these properties:
+//!
+//! ```
+//! use doca::DeviceFilter;
+//! let device_ctx = DeviceFilter::new().iface_name("p0").open();
+//! ```
+//!
+//! On the DPU, the representors of a local device can be listed with
+//! [`DevContext::representors`]:
+//!
+//! ``` rust, no_run
+//! use doca::device::RepFilter;
+//! let device_ctx = doca::open_device_with_pci("03:00.0").unwrap();
+//! for rep in device_ctx.representors(RepFilter::Net).unwrap().iter() {
+//!     println!("{} {}", rep.pci_addr().unwrap(), rep.vuid().unwrap());
+//! }
+//! ```
+//!
 
 use ffi::doca_error;
+
+// Renamed in DOCA 2.x, with the same signatures
+#[cfg(all(feature = "dma", not(doca_2_x)))]
+use ffi::doca_dma_get_max_buf_size;
+#[cfg(all(feature = "dma", doca_2_x))]
+use ffi::doca_dma_cap_task_memcpy_get_max_buf_size as doca_dma_get_max_buf_size;
+#[cfg(not(doca_2_x))]
+use ffi::doca_devinfo_get_is_mmap_export_dpu_supported;
+#[cfg(doca_2_x)]
+use ffi::doca_mmap_cap_is_export_pci_supported as doca_devinfo_get_is_mmap_export_dpu_supported;
+use std::net::{Ipv4Addr, Ipv6Addr};
 use std::{ptr::NonNull, sync::Arc};
 
+use crate::runtime::RuntimeError;
 use crate::{DOCAError, DOCAResult};
 
+pub use filter::DeviceFilter;
+pub use pci::PciAddress;
+
+/// Select a device by its properties
+pub mod filter;
+/// PCI address of a device
+pub mod pci;
+
 /// DOCA Device list
 pub struct DeviceList(&'static mut [*mut ffi::doca_devinfo]);
 
@@ -39,27 +79,31 @@ impl Drop for DeviceList {
     fn drop(&mut self) {
         unsafe { ffi::doca_devinfo_list_destroy(self.0.as_mut_ptr()) };
 
-        // Show drop order only in `debug` mode
-        #[cfg(debug_assertions)]
-        println!("DeviceList is dropped!");
+        trace!(devices = self.0.len(), "DeviceList is dropped");
     }
 }
 
 /// Get list of all available local devices.
 ///
+/// The error converts into a [`DOCAError`], so `?` also works in functions
+/// returning a [`DOCAResult`].
+///
 /// # Errors
 ///
-///  - `DOCA_ERROR_INVALID_VALUE`: received invalid input.
-///  - `DOCA_ERROR_NO_MEMORY`: failed to allocate enough space.
-///  - `DOCA_ERROR_NOT_FOUND`: failed to get RDMA devices list
+///  - `LibraryNotFound`: the DOCA libraries are not available with the `dlopen` feature.
+///  - `Doca(DOCA_ERROR_INVALID_VALUE)`: received invalid input.
+///  - `Doca(DOCA_ERROR_NO_MEMORY)`: failed to allocate enough space.
+///  - `Doca(DOCA_ERROR_NOT_FOUND)`: failed to get RDMA devices list.
 ///
-pub fn devices() -> DOCAResult<Arc<DeviceList>> {
+pub fn devices() -> Result<Arc<DeviceList>, RuntimeError> {
+    crate::runtime::load()?;
+
     let mut n = 0u32;
     let mut dev_list: *mut *mut ffi::doca_devinfo = std::ptr::null_mut();
     let ret = unsafe { ffi::doca_devinfo_list_create(&mut dev_list as *mut _, &mut n as *mut _) };
 
     if dev_list.is_null() || ret != doca_error::DOCA_SUCCESS {
-        return Err(ret);
+        return Err(RuntimeError::Doca(ret));
     }
 
     let devices = unsafe { std::slice::from_raw_parts_mut(dev_list, n as usize) };
@@ -102,6 +146,11 @@ impl DeviceList {
             })
             .flatten()
     }
+
+    /// Returns an iterator over the devices.
+    pub fn iter(self: &Arc<Self>) -> impl Iterator<Item = Arc<Device>> + '_ {
+        (0..self.len()).filter_map(move |i| self.get(i))
+    }
 }
 
 /// An DOCA device
@@ -118,29 +167,30 @@ unsafe impl Sync for Device {}
 unsafe impl Send for Device {}
 
 impl Device {
-    /// Return the PCIe address of the doca device, e.g "17:00.1".
-    /// The matching between the str & `doca_pci_bdf` can be seen
-    /// as below.
-    /// ---------------------------------------
-    /// -- 4 -- b -- : -- 0 -- 0 -- . -- 1 ----
-    /// --   BUS     |    DEVICE    | FUNCTION
+    /// Return the PCIe address of the doca device, e.g "0000:17:00.1".
+    /// See [`PciAddress`] for the meaning of each field.
     ///
     /// # Errors
     ///
     ///  - `DOCA_ERROR_INVALID_VALUE`: received invalid input.
     ///
-    /// 
-    pub fn name(&self) -> DOCAResult<String> {
-        let mut pci_str = vec![0_u8; 16];
-        let ret =
-            unsafe { ffi::doca_devinfo_get_pci_addr_str(self.inner_ptr(), pci_str.as_mut_ptr().cast()) };
-        
+    pub fn pci_addr(&self) -> DOCAResult<PciAddress> {
+        let mut pci_str = vec![0_u8; ffi::DOCA_DEVINFO_PCI_ADDR_SIZE as usize];
+        let ret = unsafe {
+            ffi::doca_devinfo_get_pci_addr_str(self.inner_ptr(), pci_str.as_mut_ptr().cast())
+        };
+
         if ret != doca_error::DOCA_SUCCESS {
-            println!("get name str error!!!");
             return Err(ret);
         }
 
-        Ok(String::from(std::str::from_utf8(&pci_str[5..12]).unwrap()))
+        c_buf_to_string(&pci_str).parse()
+    }
+
+    /// Return the PCIe address of the doca device as a string in the full form,
+    /// e.g "0000:17:00.1".
+    pub fn name(&self) -> DOCAResult<String> {
+        Ok(self.pci_addr()?.to_string())
     }
 
     /// Open a DOCA device and store it as a context for further use.
@@ -149,9 +199,10 @@ impl Device {
     }
 
     /// Get the maximum supported buffer size for DMA job.
+    #[cfg(feature = "dma")]
     pub fn get_max_buf_size(&self) -> DOCAResult<u64> {
         let mut num: u64 = 0;
-        let ret = unsafe { ffi::doca_dma_get_max_buf_size(self.inner_ptr(), &mut num as *mut _) };
+        let ret = unsafe { doca_dma_get_max_buf_size(self.inner_ptr(), &mut num as *mut _) };
 
         if ret != doca_error::DOCA_SUCCESS {
             return Err(ret);
@@ -160,24 +211,465 @@ impl Device {
         Ok(num)
     }
 
+    /// Return the network interface name of the device, e.g "p0".
+    pub fn iface_name(&self) -> DOCAResult<String> {
+        let mut buf = vec![0_u8; ffi::DOCA_DEVINFO_IFACE_NAME_SIZE as usize];
+        let ret = unsafe {
+            ffi::doca_devinfo_get_iface_name(
+                self.inner_ptr(),
+                buf.as_mut_ptr().cast(),
+                buf.len() as u32,
+            )
+        };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(c_buf_to_string(&buf))
+    }
+
+    /// Return the IB device name of the device, e.g "mlx5_0".
+    pub fn ibdev_name(&self) -> DOCAResult<String> {
+        let mut buf = vec![0_u8; ffi::DOCA_DEVINFO_IBDEV_NAME_SIZE as usize];
+        let ret = unsafe {
+            ffi::doca_devinfo_get_ibdev_name(
+                self.inner_ptr(),
+                buf.as_mut_ptr().cast(),
+                buf.len() as u32,
+            )
+        };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(c_buf_to_string(&buf))
+    }
+
+    /// Return the MAC address of the device.
+    pub fn mac_addr(&self) -> DOCAResult<[u8; 6]> {
+        let mut mac = [0_u8; ffi::DOCA_DEVINFO_MAC_ADDR_SIZE as usize];
+        let ret = unsafe {
+            ffi::doca_devinfo_get_mac_addr(self.inner_ptr(), mac.as_mut_ptr(), mac.len() as u32)
+        };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(mac)
+    }
+
+    /// Return the IPv4 address of the device.
+    pub fn ipv4_addr(&self) -> DOCAResult<Ipv4Addr> {
+        let mut addr = [0_u8; ffi::DOCA_DEVINFO_IPV4_ADDR_SIZE as usize];
+        let ret = unsafe {
+            ffi::doca_devinfo_get_ipv4_addr(self.inner_ptr(), addr.as_mut_ptr(), addr.len() as u32)
+        };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(Ipv4Addr::from(addr))
+    }
+
+    /// Return the IPv6 address of the device.
+    pub fn ipv6_addr(&self) -> DOCAResult<Ipv6Addr> {
+        let mut addr = [0_u8; ffi::DOCA_DEVINFO_IPV6_ADDR_SIZE as usize];
+        let ret = unsafe {
+            ffi::doca_devinfo_get_ipv6_addr(self.inner_ptr(), addr.as_mut_ptr(), addr.len() as u32)
+        };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(Ipv6Addr::from(addr))
+    }
+
+    /// Return the PCI function type of the device.
+    pub fn func_type(&self) -> DOCAResult<PciFuncType> {
+        let mut func_type: ffi::doca_pci_func_type = 0;
+        let ret = unsafe {
+            ffi::doca_devinfo_get_pci_func_type(self.inner_ptr(), &mut func_type as *mut _)
+        };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        PciFuncType::try_from(func_type)
+    }
+
+    /// Check whether the device supports the given capability.
+    ///
+    /// A capability that is not supported by the device returns `Ok(false)`,
+    /// any other failure of the query is returned as an error.
+    pub fn supports(&self, cap: DeviceCapability) -> DOCAResult<bool> {
+        match cap {
+            #[cfg(feature = "dma")]
+            DeviceCapability::DmaMemcpy => {
+                #[cfg(not(doca_2_x))]
+                let ret = unsafe {
+                    ffi::doca_dma_job_get_supported(self.inner_ptr(), ffi::DOCA_DMA_JOB_MEMCPY)
+                };
+                #[cfg(doca_2_x)]
+                let ret = unsafe { ffi::doca_dma_cap_task_memcpy_is_supported(self.inner_ptr()) };
+                supported_from_ret(ret)
+            }
+            #[cfg(feature = "comm-channel")]
+            DeviceCapability::CommChannel => {
+                let mut max_msg_size = 0_u32;
+                let ret = unsafe {
+                    ffi::doca_comm_channel_get_max_msg_size(
+                        self.inner_ptr(),
+                        &mut max_msg_size as *mut _,
+                    )
+                };
+                supported_from_ret(ret)
+            }
+            #[cfg(all(feature = "compress", not(doca_2_x)))]
+            DeviceCapability::DeflateCompress => {
+                let ret = unsafe {
+                    ffi::doca_compress_job_get_supported(
+                        self.inner_ptr(),
+                        ffi::DOCA_COMPRESS_DEFLATE_JOB,
+                    )
+                };
+                supported_from_ret(ret)
+            }
+            #[cfg(all(feature = "compress", not(doca_2_x)))]
+            DeviceCapability::DeflateDecompress => {
+                let ret = unsafe {
+                    ffi::doca_compress_job_get_supported(
+                        self.inner_ptr(),
+                        ffi::DOCA_DECOMPRESS_DEFLATE_JOB,
+                    )
+                };
+                supported_from_ret(ret)
+            }
+            #[cfg(all(feature = "sha", not(doca_2_x)))]
+            DeviceCapability::Sha1 => sha_supported(self, ffi::DOCA_SHA_JOB_SHA1),
+            #[cfg(all(feature = "sha", not(doca_2_x)))]
+            DeviceCapability::Sha256 => sha_supported(self, ffi::DOCA_SHA_JOB_SHA256),
+            #[cfg(all(feature = "sha", not(doca_2_x)))]
+            DeviceCapability::Sha512 => sha_supported(self, ffi::DOCA_SHA_JOB_SHA512),
+            #[cfg(all(feature = "regex", not(doca_2_x)))]
+            DeviceCapability::RegexSearch => {
+                let ret = unsafe {
+                    ffi::doca_regex_job_get_supported(self.inner_ptr(), ffi::DOCA_REGEX_JOB_SEARCH)
+                };
+                supported_from_ret(ret)
+            }
+            #[cfg(all(feature = "erasure-coding", doca_2_x))]
+            DeviceCapability::EcCreate => {
+                let ret = unsafe { ffi::doca_ec_cap_task_create_is_supported(self.inner_ptr()) };
+                supported_from_ret(ret)
+            }
+            #[cfg(all(feature = "erasure-coding", doca_2_x))]
+            DeviceCapability::EcRecover => {
+                let ret = unsafe { ffi::doca_ec_cap_task_recover_is_supported(self.inner_ptr()) };
+                supported_from_ret(ret)
+            }
+            #[cfg(all(feature = "aes-gcm", doca_2_x))]
+            DeviceCapability::AesGcmEncrypt => {
+                let ret =
+                    unsafe { ffi::doca_aes_gcm_cap_task_encrypt_is_supported(self.inner_ptr()) };
+                supported_from_ret(ret)
+            }
+            #[cfg(all(feature = "aes-gcm", doca_2_x))]
+            DeviceCapability::AesGcmDecrypt => {
+                let ret =
+                    unsafe { ffi::doca_aes_gcm_cap_task_decrypt_is_supported(self.inner_ptr()) };
+                supported_from_ret(ret)
+            }
+            #[cfg(all(feature = "rdma", doca_2_x))]
+            DeviceCapability::RdmaSend => {
+                let ret = unsafe { ffi::doca_rdma_cap_task_send_is_supported(self.inner_ptr()) };
+                supported_from_ret(ret)
+            }
+            #[cfg(all(feature = "rdma", doca_2_x))]
+            DeviceCapability::RdmaReceive => {
+                let ret = unsafe { ffi::doca_rdma_cap_task_receive_is_supported(self.inner_ptr()) };
+                supported_from_ret(ret)
+            }
+            #[cfg(all(feature = "rdma", doca_2_x))]
+            DeviceCapability::RdmaRead => {
+                let ret = unsafe { ffi::doca_rdma_cap_task_read_is_supported(self.inner_ptr()) };
+                supported_from_ret(ret)
+            }
+            #[cfg(all(feature = "rdma", doca_2_x))]
+            DeviceCapability::RdmaWrite => {
+                let ret = unsafe { ffi::doca_rdma_cap_task_write_is_supported(self.inner_ptr()) };
+                supported_from_ret(ret)
+            }
+            #[cfg(all(feature = "eth", doca_2_x))]
+            DeviceCapability::EthRxq => {
+                let ret = unsafe {
+                    ffi::doca_eth_rxq_cap_is_type_supported(
+                        self.inner_ptr(),
+                        ffi::DOCA_ETH_RXQ_TYPE_REGULAR,
+                        ffi::DOCA_ETH_RXQ_DATA_PATH_TYPE_CPU,
+                    )
+                };
+                supported_from_ret(ret)
+            }
+            #[cfg(all(feature = "eth", doca_2_x))]
+            DeviceCapability::EthTxq => {
+                let ret = unsafe {
+                    ffi::doca_eth_txq_cap_is_type_supported(
+                        self.inner_ptr(),
+                        ffi::DOCA_ETH_TXQ_TYPE_REGULAR,
+                        ffi::DOCA_ETH_TXQ_DATA_PATH_TYPE_CPU,
+                    )
+                };
+                supported_from_ret(ret)
+            }
+            #[cfg(all(feature = "telemetry-diag", doca_2_x))]
+            DeviceCapability::TelemetryDiag => {
+                let mut supported = 0_u8;
+                let ret = unsafe {
+                    ffi::doca_telemetry_diag_cap_is_supported(
+                        self.inner_ptr(),
+                        &mut supported as *mut _,
+                    )
+                };
+                Ok(supported_from_ret(ret)? && supported != 0)
+            }
+            DeviceCapability::ExportToDpu => {
+                let mut supported = 0_u8;
+                let ret = unsafe {
+                    doca_devinfo_get_is_mmap_export_dpu_supported(
+                        self.inner_ptr(),
+                        &mut supported as *mut _,
+                    )
+                };
+                Ok(supported_from_ret(ret)? && supported != 0)
+            }
+        }
+    }
+
+    /// Check whether the device supports DMA memcpy jobs.
+    #[cfg(feature = "dma")]
+    #[inline]
+    pub fn supports_dma_memcpy(&self) -> DOCAResult<bool> {
+        self.supports(DeviceCapability::DmaMemcpy)
+    }
+
+    /// Check whether the device can be used by a comm channel endpoint.
+    #[cfg(feature = "comm-channel")]
+    #[inline]
+    pub fn supports_comm_channel(&self) -> DOCAResult<bool> {
+        self.supports(DeviceCapability::CommChannel)
+    }
+
+    /// Check whether the device supports deflate compress jobs.
+    #[cfg(all(feature = "compress", not(doca_2_x)))]
+    #[inline]
+    pub fn supports_deflate_compress(&self) -> DOCAResult<bool> {
+        self.supports(DeviceCapability::DeflateCompress)
+    }
+
+    /// Check whether the device supports deflate decompress jobs.
+    #[cfg(all(feature = "compress", not(doca_2_x)))]
+    #[inline]
+    pub fn supports_deflate_decompress(&self) -> DOCAResult<bool> {
+        self.supports(DeviceCapability::DeflateDecompress)
+    }
+
+    /// Get the maximum supported buffer size for the compress jobs of the given type.
+    #[cfg(all(feature = "compress", not(doca_2_x)))]
+    pub fn compress_max_buf_size(
+        &self,
+        job_type: crate::compress::CompressJobType,
+    ) -> DOCAResult<u64> {
+        let mut num: u64 = 0;
+        let ret = unsafe {
+            ffi::doca_compress_get_max_buf_size(
+                self.inner_ptr(),
+                job_type.to_raw(),
+                &mut num as *mut _,
+            )
+        };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(num)
+    }
+
+    /// Get the maximum supported source buffer size for SHA jobs.
+    #[cfg(all(feature = "sha", not(doca_2_x)))]
+    pub fn sha_max_src_buf_size(&self) -> DOCAResult<u64> {
+        let mut num: u64 = 0;
+        let ret =
+            unsafe { ffi::doca_sha_get_max_src_buffer_size(self.inner_ptr(), &mut num as *mut _) };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(num)
+    }
+
+    /// Get the maximum supported block size for erasure coding tasks.
+    #[cfg(all(feature = "erasure-coding", doca_2_x))]
+    pub fn ec_max_block_size(&self) -> DOCAResult<u64> {
+        let mut num: u64 = 0;
+        let ret =
+            unsafe { ffi::doca_ec_cap_get_max_block_size(self.inner_ptr(), &mut num as *mut _) };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(num)
+    }
+
+    /// Check whether a mmap on the device can be exported to the DPU.
+    #[inline]
+    pub fn supports_export_to_dpu(&self) -> DOCAResult<bool> {
+        self.supports(DeviceCapability::ExportToDpu)
+    }
+
     /// Return the device
     pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_devinfo {
         self.inner.as_ptr()
     }
 }
 
+/// The PCI function type of a device
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PciFuncType {
+    /// Physical function
+    PF,
+    /// Virtual function
+    VF,
+    /// Scalable function
+    SF,
+}
+
+impl TryFrom<ffi::doca_pci_func_type> for PciFuncType {
+    type Error = DOCAError;
+
+    fn try_from(func_type: ffi::doca_pci_func_type) -> DOCAResult<Self> {
+        match func_type {
+            ffi::DOCA_PCI_FUNC_PF => Ok(PciFuncType::PF),
+            ffi::DOCA_PCI_FUNC_VF => Ok(PciFuncType::VF),
+            ffi::DOCA_PCI_FUNC_SF => Ok(PciFuncType::SF),
+            _ => Err(DOCAError::DOCA_ERROR_UNEXPECTED),
+        }
+    }
+}
+
+/// The capabilities that can be checked with [`Device::supports`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeviceCapability {
+    /// The device can execute DMA memcpy jobs
+    #[cfg(feature = "dma")]
+    DmaMemcpy,
+    /// The device can be used by a comm channel endpoint
+    #[cfg(feature = "comm-channel")]
+    CommChannel,
+    /// The device can execute deflate compress jobs
+    #[cfg(all(feature = "compress", not(doca_2_x)))]
+    DeflateCompress,
+    /// The device can execute deflate decompress jobs
+    #[cfg(all(feature = "compress", not(doca_2_x)))]
+    DeflateDecompress,
+    /// The device can execute SHA-1 jobs
+    #[cfg(all(feature = "sha", not(doca_2_x)))]
+    Sha1,
+    /// The device can execute SHA-256 jobs
+    #[cfg(all(feature = "sha", not(doca_2_x)))]
+    Sha256,
+    /// The device can execute SHA-512 jobs
+    #[cfg(all(feature = "sha", not(doca_2_x)))]
+    Sha512,
+    /// The device can execute RegEx search jobs
+    #[cfg(all(feature = "regex", not(doca_2_x)))]
+    RegexSearch,
+    /// The device can execute erasure coding create tasks
+    #[cfg(all(feature = "erasure-coding", doca_2_x))]
+    EcCreate,
+    /// The device can execute erasure coding recover tasks
+    #[cfg(all(feature = "erasure-coding", doca_2_x))]
+    EcRecover,
+    /// The device can execute AES-GCM encrypt tasks
+    #[cfg(all(feature = "aes-gcm", doca_2_x))]
+    AesGcmEncrypt,
+    /// The device can execute AES-GCM decrypt tasks
+    #[cfg(all(feature = "aes-gcm", doca_2_x))]
+    AesGcmDecrypt,
+    /// The device can execute RDMA send tasks
+    #[cfg(all(feature = "rdma", doca_2_x))]
+    RdmaSend,
+    /// The device can execute RDMA receive tasks
+    #[cfg(all(feature = "rdma", doca_2_x))]
+    RdmaReceive,
+    /// The device can execute RDMA read tasks
+    #[cfg(all(feature = "rdma", doca_2_x))]
+    RdmaRead,
+    /// The device can execute RDMA write tasks
+    #[cfg(all(feature = "rdma", doca_2_x))]
+    RdmaWrite,
+    /// The device can receive packets into the buffers of the CPU
+    #[cfg(all(feature = "eth", doca_2_x))]
+    EthRxq,
+    /// The device can transmit packets from the buffers of the CPU
+    #[cfg(all(feature = "eth", doca_2_x))]
+    EthTxq,
+    /// The device exposes counters to DOCA Telemetry Diagnostics
+    #[cfg(all(feature = "telemetry-diag", doca_2_x))]
+    TelemetryDiag,
+    /// A mmap on the device can be exported to the DPU
+    ExportToDpu,
+}
+
+/// Transfer the return value of a capability query into a bool
+#[inline]
+fn supported_from_ret(ret: doca_error) -> DOCAResult<bool> {
+    match ret {
+        doca_error::DOCA_SUCCESS => Ok(true),
+        doca_error::DOCA_ERROR_NOT_SUPPORTED => Ok(false),
+        _ => Err(ret),
+    }
+}
+
+/// Check whether the device supports the SHA job type
+#[cfg(all(feature = "sha", not(doca_2_x)))]
+#[inline]
+fn sha_supported(dev: &Device, job_type: ffi::doca_sha_job_types) -> DOCAResult<bool> {
+    supported_from_ret(unsafe { ffi::doca_sha_job_get_supported(dev.inner_ptr(), job_type) })
+}
+
+/// Read a NUL-terminated string written by DOCA into the buffer
+#[inline]
+fn c_buf_to_string(buf: &[u8]) -> String {
+    let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
 /// An opened Doca Device
 pub struct DevContext {
     ctx: NonNull<ffi::doca_dev>,
 }
 
+// SAFETY: DOCA lets one opened `doca_dev` be used by the contexts and mmaps of
+// several threads, and the methods taking `&self` only query the device.
+unsafe impl Sync for DevContext {}
+// SAFETY: `doca_dev` is not bound to the thread that opened it.
+unsafe impl Send for DevContext {}
+
 impl Drop for DevContext {
     fn drop(&mut self) {
-        unsafe { ffi::doca_dev_close(self.ctx.as_ptr()) };
+        // The PCI address can only be queried before the device is closed
+        trace!(pci = ?self.pci_addr().ok(), "Device Context is dropped");
 
-        // Show drop order only in `debug` mode
-        #[cfg(debug_assertions)]
-        println!("Device Context is dropped!");
+        unsafe { ffi::doca_dev_close(self.ctx.as_ptr()) };
     }
 }
 
@@ -196,6 +688,77 @@ impl DevContext {
         }))
     }
 
+    /// List the representors of the device that match the filter.
+    ///
+    /// Representor devices are only available on the DPU.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NOT_SUPPORTED`: the device has no representors, e.g. on the host.
+    ///  - `DOCA_ERROR_NO_MEMORY`: failed to allocate enough space.
+    ///
+    pub fn representors(self: &Arc<Self>, filter: RepFilter) -> DOCAResult<Arc<DeviceRepList>> {
+        let mut n = 0u32;
+        let mut dev_list: *mut *mut ffi::doca_devinfo_rep = std::ptr::null_mut();
+        let ret = unsafe {
+            ffi::doca_devinfo_rep_list_create(
+                self.inner_ptr(),
+                filter.to_raw(),
+                &mut dev_list as *mut _,
+                &mut n as *mut _,
+            )
+        };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+        if dev_list.is_null() {
+            return Err(doca_error::DOCA_ERROR_UNEXPECTED);
+        }
+
+        let list = unsafe { std::slice::from_raw_parts_mut(dev_list, n as usize) };
+
+        Ok(Arc::new(DeviceRepList {
+            list,
+            dev: self.clone(),
+        }))
+    }
+
+    /// Return the PCIe address of the opened device.
+    pub fn pci_addr(&self) -> DOCAResult<PciAddress> {
+        let mut pci_str = vec![0_u8; ffi::DOCA_DEVINFO_PCI_ADDR_SIZE as usize];
+        let ret = unsafe {
+            ffi::doca_devinfo_get_pci_addr_str(
+                ffi::doca_dev_as_devinfo(self.inner_ptr()),
+                pci_str.as_mut_ptr().cast(),
+            )
+        };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        c_buf_to_string(&pci_str).parse()
+    }
+
+    /// Return the IB device name of the opened device, e.g "mlx5_0".
+    pub fn ibdev_name(&self) -> DOCAResult<String> {
+        let mut buf = vec![0_u8; ffi::DOCA_DEVINFO_IBDEV_NAME_SIZE as usize];
+        let ret = unsafe {
+            ffi::doca_devinfo_get_ibdev_name(
+                ffi::doca_dev_as_devinfo(self.inner_ptr()),
+                buf.as_mut_ptr().cast(),
+                buf.len() as u32,
+            )
+        };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(c_buf_to_string(&buf))
+    }
+
     /// Return the DOCA Device context raw pointer
     #[inline]
     pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_dev {
@@ -203,7 +766,8 @@ impl DevContext {
     }
 }
 
-/// Open a DOCA Device with the given PCI address
+/// Open a DOCA Device with the given PCI address,
+/// both "0000:03:00.0" and "03:00.0" are accepted.
 ///
 /// Examples
 /// ```
@@ -211,15 +775,17 @@ impl DevContext {
 /// let device = open_device_with_pci("03:00.0");
 /// ```
 ///
+/// # Errors
+///
+///  - `DOCA_ERROR_INVALID_VALUE`: the PCI address is malformed, or no device has the address.
+///
 pub fn open_device_with_pci(pci: &str) -> DOCAResult<Arc<DevContext>> {
+    let pci: PciAddress = pci.parse()?;
     let dev_list = devices()?;
 
-    // println!("cmp pci {:?}", pci.as_bytes().to_vec());
     for i in 0..dev_list.num_devices() {
         let device = dev_list.get(i).unwrap();
-        let pci_addr = device.name()?;
-        // println!("get name {:?}, len = {}", pci_addr.as_bytes(), pci.len());
-        if pci_addr.eq(pci) {
+        if device.pci_addr()? == pci {
             // open the device
             return device.open();
         }
@@ -228,31 +794,61 @@ pub fn open_device_with_pci(pci: &str) -> DOCAResult<Arc<DevContext>> {
     Err(doca_error::DOCA_ERROR_INVALID_VALUE)
 }
 
-/// DOCA Device list
-pub struct DeviceRepList(&'static mut [*mut ffi::doca_devinfo_rep]);
+/// The filter used to list the representors of a local device,
+/// see [`DevContext::representors`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RepFilter {
+    /// All the representors
+    All,
+    /// The network representors
+    Net,
+    /// The emulated PCI representors
+    EmulatedPci,
+}
+
+impl RepFilter {
+    /// The raw `doca_dev_rep_filter` value
+    #[inline]
+    fn to_raw(self) -> i32 {
+        let filter = match self {
+            RepFilter::All => ffi::DOCA_DEV_REP_FILTER_ALL,
+            RepFilter::Net => ffi::DOCA_DEV_REP_FILTER_NET,
+            RepFilter::EmulatedPci => ffi::DOCA_DEV_REP_FILTER_EMULATED,
+        };
+        filter as i32
+    }
+}
+
+/// DOCA Device representor list
+pub struct DeviceRepList {
+    list: &'static mut [*mut ffi::doca_devinfo_rep],
+
+    // the local device the representors belong to,
+    // it should be closed after the list is destroyed.
+    #[allow(dead_code)]
+    dev: Arc<DevContext>,
+}
 
 unsafe impl Sync for DeviceRepList {}
 unsafe impl Send for DeviceRepList {}
 
 impl Drop for DeviceRepList {
     fn drop(&mut self) {
-        unsafe { ffi::doca_devinfo_rep_list_destroy(self.0.as_mut_ptr()) };
+        unsafe { ffi::doca_devinfo_rep_list_destroy(self.list.as_mut_ptr()) };
 
-        // Show drop order only in `debug` mode
-        #[cfg(debug_assertions)]
-        println!("DeviceList is dropped!");
+        trace!(representors = self.list.len(), "DeviceRepList is dropped");
     }
 }
 
 impl DeviceRepList {
     /// Returns the number of devices.
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.list.len()
     }
 
     /// Returns `true` if there are any devices.
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.list.is_empty()
     }
 
     /// Returns the number of devices.
@@ -262,22 +858,17 @@ impl DeviceRepList {
 
     /// Returns the device at the given `index`, or `None` if out of bounds.
     pub fn get(self: &Arc<Self>, index: usize) -> Option<Arc<DeviceRep>> {
-        self.0
-            .get(index)
-            .map(|d| {
-                let inner_ptr = NonNull::new(*d);
+        let inner = NonNull::new(*self.list.get(index)?)?;
 
-                let inner = match inner_ptr {
-                    Some(inner) => inner,
-                    None => return None,
-                };
+        Some(Arc::new(DeviceRep {
+            inner,
+            parent_devlist: self.clone(),
+        }))
+    }
 
-                Some(Arc::new(DeviceRep {
-                    inner: inner,
-                    parent_devlist: self.clone(),
-                }))
-            })
-            .flatten()
+    /// Returns an iterator over the representors.
+    pub fn iter(self: &Arc<Self>) -> impl Iterator<Item = Arc<DeviceRep>> + '_ {
+        (0..self.len()).filter_map(move |i| self.get(i))
     }
 }
 
@@ -295,29 +886,77 @@ unsafe impl Sync for DeviceRep {}
 unsafe impl Send for DeviceRep {}
 
 impl DeviceRep {
-    /// Return the PCIe address of the doca device, e.g "17:00.1".
-    /// The matching between the str & `doca_pci_bdf` can be seen
-    /// as below.
-    /// ---------------------------------------
-    /// -- 4 -- b -- : -- 0 -- 0 -- . -- 1 ----
-    /// --   BUS     |    DEVICE    | FUNCTION
+    /// Return the PCIe address of the doca device, e.g "0000:17:00.1".
+    /// See [`PciAddress`] for the meaning of each field.
     ///
     /// # Errors
     ///
     ///  - `DOCA_ERROR_INVALID_VALUE`: received invalid input.
     ///
-    /// 
+    pub fn pci_addr(&self) -> DOCAResult<PciAddress> {
+        let mut pci_str = vec![0_u8; ffi::DOCA_DEVINFO_REP_PCI_ADDR_SIZE as usize];
+        let ret = unsafe {
+            ffi::doca_devinfo_rep_get_pci_addr_str(self.inner_ptr(), pci_str.as_mut_ptr().cast())
+        };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        c_buf_to_string(&pci_str).parse()
+    }
+
+    /// Return the PCIe address of the doca device as a string in the full form,
+    /// e.g "0000:17:00.1".
     pub fn name(&self) -> DOCAResult<String> {
-        let mut pci_str = vec![0_u8; 16];
-        let ret =
-            unsafe { ffi::doca_devinfo_rep_get_pci_addr_str(self.inner_ptr(), pci_str.as_mut_ptr().cast()) };
-        
+        Ok(self.pci_addr()?.to_string())
+    }
+
+    /// Return the VUID of the representor, which identifies it
+    /// even if its PCI address changes.
+    pub fn vuid(&self) -> DOCAResult<String> {
+        let mut buf = vec![0_u8; ffi::DOCA_DEVINFO_REP_VUID_SIZE as usize];
+        let ret = unsafe {
+            ffi::doca_devinfo_rep_get_vuid(
+                self.inner_ptr(),
+                buf.as_mut_ptr().cast(),
+                buf.len() as u32,
+            )
+        };
+
         if ret != doca_error::DOCA_SUCCESS {
-            println!("get name str error!!!");
             return Err(ret);
         }
 
-        Ok(String::from(std::str::from_utf8(&pci_str[5..12]).unwrap()))
+        Ok(c_buf_to_string(&buf))
+    }
+
+    /// Return `true` if the representor is a hot-plugged device.
+    pub fn is_hotplug(&self) -> DOCAResult<bool> {
+        let mut is_hotplug = 0_u8;
+        let ret = unsafe {
+            ffi::doca_devinfo_rep_get_is_hotplug(self.inner_ptr(), &mut is_hotplug as *mut _)
+        };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(is_hotplug != 0)
+    }
+
+    /// Return the PCI function type of the representor.
+    pub fn func_type(&self) -> DOCAResult<PciFuncType> {
+        let mut func_type: ffi::doca_pci_func_type = 0;
+        let ret = unsafe {
+            ffi::doca_devinfo_rep_get_pci_func_type(self.inner_ptr(), &mut func_type as *mut _)
+        };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        PciFuncType::try_from(func_type)
     }
 
     /// Open a DOCA device and store it as a context for further use.
@@ -336,13 +975,14 @@ pub struct DevRepContext {
     ctx: NonNull<ffi::doca_dev_rep>,
 }
 
+unsafe impl Sync for DevRepContext {}
+unsafe impl Send for DevRepContext {}
+
 impl Drop for DevRepContext {
     fn drop(&mut self) {
         unsafe { ffi::doca_dev_rep_close(self.ctx.as_ptr()) };
 
-        // Show drop order only in `debug` mode
-        #[cfg(debug_assertions)]
-        println!("Device Context is dropped!");
+        trace!(dev_rep = ?self.ctx, "Device Representor Context is dropped");
     }
 }
 
@@ -368,26 +1008,22 @@ impl DevRepContext {
     }
 }
 
-/// simplied realizarion for create rep with no wrapper...
-/// I have no time temporarily
-pub fn open_device_rep_with_pci(local_dev: &Arc<DevContext>, rep_pci_addr: &str) -> DOCAResult<Arc<DevRepContext>> {
-    let mut num_devs: u32 = 0;
-    let mut dev_list: *mut *mut ffi::doca_devinfo_rep = std::ptr::null_mut();
-
-    let ret = unsafe { ffi::doca_devinfo_rep_list_create(local_dev.inner_ptr(), 2 /* DOCA_DEV_REP_FILTER_NET */, &mut dev_list, &mut num_devs) };
-    if ret != DOCAError::DOCA_SUCCESS {
-        panic!("Failed to create devinfo representations list. Representor devices are available only on DPU, do not run on Host");
-    }
-
-    let devices = unsafe { std::slice::from_raw_parts_mut(dev_list, num_devs as usize) };
-
-    let dev_list = Arc::new(DeviceRepList(devices));
-
-    for i in 0..dev_list.num_devices() {
-        let device = dev_list.get(i).unwrap();
-        let pci_addr = device.name().unwrap();
-
-        if pci_addr.eq(rep_pci_addr) {
+/// Open the network representor with the given PCI address on the local device,
+/// both "0000:af:00.0" and "af:00.0" are accepted.
+///
+/// # Errors
+///
+///  - `DOCA_ERROR_INVALID_VALUE`: the PCI address is malformed, or no representor has the address.
+///  - Any error returned by [`DevContext::representors`], e.g. when running on the host.
+///
+pub fn open_device_rep_with_pci(
+    local_dev: &Arc<DevContext>,
+    rep_pci_addr: &str,
+) -> DOCAResult<Arc<DevRepContext>> {
+    let rep_pci_addr: PciAddress = rep_pci_addr.parse()?;
+
+    for device in local_dev.representors(RepFilter::Net)?.iter() {
+        if device.pci_addr()? == rep_pci_addr {
             // open the device
             return device.open();
         }
@@ -412,7 +1048,7 @@ mod tests {
 
         for i in 0..devices.num_devices() {
             let device = devices.get(i).unwrap();
-            let pci_addr = device.name().unwrap();
+            let pci_addr = device.pci_addr().unwrap();
             println!("device pci addr {}", pci_addr);
         }
     }
@@ -424,6 +1060,43 @@ mod tests {
     }
 
     #[test]
+    fn test_dev_properties() {
+        let device = crate::device::devices().unwrap().get(0).unwrap();
+
+        let iface_name = device.iface_name().unwrap();
+        let ibdev_name = device.ibdev_name().unwrap();
+        assert!(!iface_name.is_empty());
+        assert!(!ibdev_name.is_empty());
+        println!("iface: {}, ibdev: {}", iface_name, ibdev_name);
+
+        let mac = device.mac_addr().unwrap();
+        println!("mac: {:02x?}", mac);
+
+        assert!(device.supports_export_to_dpu().is_ok());
+    }
+
+    #[test]
+    #[ignore = "needs a DOCA device on the DPU, the host side has no representors"]
+    fn test_list_representors() {
+        use crate::device::RepFilter;
+
+        let device = crate::device::devices()
+            .unwrap()
+            .get(0)
+            .unwrap()
+            .open()
+            .unwrap();
+
+        let reps = device.representors(RepFilter::Net).unwrap();
+        assert_ne!(reps.len(), 0);
+        for rep in reps.iter() {
+            assert!(rep.pci_addr().is_ok());
+            assert!(rep.vuid().is_ok());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "dma")]
     fn test_dev_max_buf() {
         let device = crate::device::devices().unwrap().get(0).unwrap();
         let ret = device.get_max_buf_size();
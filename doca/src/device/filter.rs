@@ -0,0 +1,167 @@
+//! Select a DOCA device by its properties.
+//!
+//! [`DeviceFilter`] collects the properties the wanted device should have,
+//! and finds the matching devices among all the available local devices.
+//! Every property that is not set matches any device.
+//!
+//! ``` rust, no_run
+//! use doca::device::{DeviceCapability, DeviceFilter};
+//!
+//! // Open the device behind the netdev `p0`, which must be able to do DMA
+//! let device_ctx = DeviceFilter::new()
+//!     .iface_name("p0")
+//!     .supports(DeviceCapability::DmaMemcpy)
+//!     .open()
+//!     .unwrap();
+//! ```
+//!
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+
+use super::{devices, DevContext, Device, DeviceCapability, PciAddress, PciFuncType};
+use crate::{DOCAError, DOCAResult};
+
+/// A set of properties used to pick DOCA devices
+#[derive(Clone, Debug, Default)]
+pub struct DeviceFilter {
+    pci_addr: Option<PciAddress>,
+    iface_name: Option<String>,
+    ibdev_name: Option<String>,
+    mac_addr: Option<[u8; 6]>,
+    ipv4_addr: Option<Ipv4Addr>,
+    ipv6_addr: Option<Ipv6Addr>,
+    func_type: Option<PciFuncType>,
+    caps: Vec<DeviceCapability>,
+}
+
+impl DeviceFilter {
+    /// Create a filter matching every device
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match the device with the given PCI address
+    pub fn pci_addr(&mut self, pci_addr: PciAddress) -> &mut Self {
+        self.pci_addr = Some(pci_addr);
+        self
+    }
+
+    /// Only match the device with the given network interface name, e.g "p0"
+    pub fn iface_name(&mut self, iface_name: &str) -> &mut Self {
+        self.iface_name = Some(iface_name.to_string());
+        self
+    }
+
+    /// Only match the device with the given IB device name, e.g "mlx5_0"
+    pub fn ibdev_name(&mut self, ibdev_name: &str) -> &mut Self {
+        self.ibdev_name = Some(ibdev_name.to_string());
+        self
+    }
+
+    /// Only match the device with the given MAC address
+    pub fn mac_addr(&mut self, mac_addr: [u8; 6]) -> &mut Self {
+        self.mac_addr = Some(mac_addr);
+        self
+    }
+
+    /// Only match the device with the given IPv4 address
+    pub fn ipv4_addr(&mut self, ipv4_addr: Ipv4Addr) -> &mut Self {
+        self.ipv4_addr = Some(ipv4_addr);
+        self
+    }
+
+    /// Only match the device with the given IPv6 address
+    pub fn ipv6_addr(&mut self, ipv6_addr: Ipv6Addr) -> &mut Self {
+        self.ipv6_addr = Some(ipv6_addr);
+        self
+    }
+
+    /// Only match the devices of the given PCI function type
+    pub fn func_type(&mut self, func_type: PciFuncType) -> &mut Self {
+        self.func_type = Some(func_type);
+        self
+    }
+
+    /// Only match the devices supporting the given capability.
+    /// It can be called several times to require several capabilities.
+    pub fn supports(&mut self, cap: DeviceCapability) -> &mut Self {
+        self.caps.push(cap);
+        self
+    }
+
+    /// Check whether the device matches the filter.
+    /// A property that can not be queried on the device is treated as a mismatch.
+    pub fn matches(&self, dev: &Device) -> bool {
+        fn check<T: PartialEq>(want: &Option<T>, got: impl FnOnce() -> DOCAResult<T>) -> bool {
+            match want {
+                Some(want) => got().map_or(false, |got| got == *want),
+                None => true,
+            }
+        }
+
+        check(&self.pci_addr, || dev.pci_addr())
+            && check(&self.iface_name, || dev.iface_name())
+            && check(&self.ibdev_name, || dev.ibdev_name())
+            && check(&self.mac_addr, || dev.mac_addr())
+            && check(&self.ipv4_addr, || dev.ipv4_addr())
+            && check(&self.ipv6_addr, || dev.ipv6_addr())
+            && check(&self.func_type, || dev.func_type())
+            && self
+                .caps
+                .iter()
+                .all(|cap| dev.supports(*cap).unwrap_or(false))
+    }
+
+    /// Return all the local devices matching the filter.
+    pub fn find_all(&self) -> DOCAResult<Vec<Arc<Device>>> {
+        let dev_list = devices()?;
+
+        Ok((0..dev_list.num_devices())
+            .filter_map(|i| dev_list.get(i))
+            .filter(|dev| self.matches(dev))
+            .collect())
+    }
+
+    /// Return the first local device matching the filter.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NOT_FOUND`: no device matches the filter.
+    ///
+    pub fn find(&self) -> DOCAResult<Arc<Device>> {
+        let dev_list = devices()?;
+
+        (0..dev_list.num_devices())
+            .filter_map(|i| dev_list.get(i))
+            .find(|dev| self.matches(dev))
+            .ok_or(DOCAError::DOCA_ERROR_NOT_FOUND)
+    }
+
+    /// Open the first local device matching the filter.
+    pub fn open(&self) -> DOCAResult<Arc<DevContext>> {
+        self.find()?.open()
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_filter_by_iface_name() {
+        use crate::device::{devices, DeviceFilter};
+
+        let device = devices().unwrap().get(0).unwrap();
+        let iface_name = device.iface_name().unwrap();
+
+        let found = DeviceFilter::new().iface_name(&iface_name).find().unwrap();
+        assert_eq!(found.pci_addr().unwrap(), device.pci_addr().unwrap());
+    }
+
+    #[test]
+    fn test_filter_no_match() {
+        use crate::device::DeviceFilter;
+        use crate::DOCAError;
+
+        let ret = DeviceFilter::new().iface_name("no-such-netdev").find();
+        assert_eq!(ret.err(), Some(DOCAError::DOCA_ERROR_NOT_FOUND));
+    }
+}
@@ -0,0 +1,196 @@
+//! PCI address of a DOCA device.
+//!
+//! [`PciAddress`] can be parsed from both the full form `0000:17:00.1`
+//! and the short form `17:00.1` (the domain defaults to 0),
+//! and it is always displayed in the full form.
+//! The matching between the str & the fields can be seen as below.
+//!
+//! ```text
+//! -- 0000 -- : -- 17 -- : -- 00 -- . -- 1 ----
+//! -- DOMAIN  |    BUS   |  DEVICE  | FUNCTION
+//! ```
+//!
+//! ```
+//! use doca::PciAddress;
+//!
+//! let addr: PciAddress = "17:00.1".parse().unwrap();
+//! assert_eq!(addr, "0000:17:00.1".parse().unwrap());
+//! assert_eq!(addr.to_string(), "0000:17:00.1");
+//! ```
+//!
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{DOCAError, DOCAResult};
+
+/// The max value of the 5-bit device field
+const PCI_DEVICE_MAX: u8 = 0x1f;
+/// The max value of the 3-bit function field
+const PCI_FUNCTION_MAX: u8 = 0x7;
+
+/// A PCI address, including the PCI domain
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PciAddress {
+    /// PCI domain
+    pub domain: u16,
+    /// PCI bus
+    pub bus: u8,
+    /// PCI device, 5 bits
+    pub device: u8,
+    /// PCI function, 3 bits
+    pub function: u8,
+}
+
+impl PciAddress {
+    /// Create a PCI address from its fields.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: `device` or `function` is out of range.
+    ///
+    pub fn new(domain: u16, bus: u8, device: u8, function: u8) -> DOCAResult<Self> {
+        if device > PCI_DEVICE_MAX || function > PCI_FUNCTION_MAX {
+            return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+        }
+
+        Ok(Self {
+            domain,
+            bus,
+            device,
+            function,
+        })
+    }
+}
+
+impl FromStr for PciAddress {
+    type Err = DOCAError;
+
+    fn from_str(s: &str) -> DOCAResult<Self> {
+        fn hex<T: TryFrom<u32>>(s: &str, max_len: usize) -> DOCAResult<T> {
+            if s.is_empty() || s.len() > max_len {
+                return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+            }
+            let v = u32::from_str_radix(s, 16).map_err(|_e| DOCAError::DOCA_ERROR_INVALID_VALUE)?;
+            T::try_from(v).map_err(|_e| DOCAError::DOCA_ERROR_INVALID_VALUE)
+        }
+
+        let s = s.trim();
+        let (bus_dev, function) = s
+            .rsplit_once('.')
+            .ok_or(DOCAError::DOCA_ERROR_INVALID_VALUE)?;
+
+        let mut parts = bus_dev.rsplitn(3, ':');
+        let device = parts.next().ok_or(DOCAError::DOCA_ERROR_INVALID_VALUE)?;
+        let bus = parts.next().ok_or(DOCAError::DOCA_ERROR_INVALID_VALUE)?;
+        let domain = match parts.next() {
+            Some(domain) => hex(domain, 4)?,
+            None => 0,
+        };
+
+        Self::new(domain, hex(bus, 2)?, hex(device, 2)?, hex(function, 1)?)
+    }
+}
+
+impl fmt::Display for PciAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04x}:{:02x}:{:02x}.{:x}",
+            self.domain, self.bus, self.device, self.function
+        )
+    }
+}
+
+/// `doca_pci_bdf` carries no domain, so the domain is set to 0.
+/// The struct is removed since DOCA 2.x.
+#[cfg(not(doca_2_x))]
+impl From<ffi::doca_pci_bdf> for PciAddress {
+    fn from(bdf: ffi::doca_pci_bdf) -> Self {
+        let raw = unsafe { bdf.__bindgen_anon_1.raw };
+        Self {
+            domain: 0,
+            bus: (raw >> 8) as u8,
+            device: ((raw >> 3) as u8) & PCI_DEVICE_MAX,
+            function: (raw as u8) & PCI_FUNCTION_MAX,
+        }
+    }
+}
+
+/// `doca_pci_bdf` carries no domain, so the domain is dropped.
+#[cfg(not(doca_2_x))]
+impl From<PciAddress> for ffi::doca_pci_bdf {
+    fn from(addr: PciAddress) -> Self {
+        let mut bdf = ffi::doca_pci_bdf::default();
+        bdf.__bindgen_anon_1.raw = ((addr.bus as u16) << 8)
+            | (((addr.device & PCI_DEVICE_MAX) as u16) << 3)
+            | ((addr.function & PCI_FUNCTION_MAX) as u16);
+        bdf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pci_addr() {
+        let short: PciAddress = "17:00.1".parse().unwrap();
+        let full: PciAddress = "0000:17:00.1".parse().unwrap();
+        assert_eq!(short, full);
+        assert_eq!(
+            full,
+            PciAddress {
+                domain: 0,
+                bus: 0x17,
+                device: 0,
+                function: 1
+            }
+        );
+
+        let other_domain: PciAddress = "0001:af:1f.7".parse().unwrap();
+        assert_eq!(other_domain.domain, 1);
+        assert_eq!(other_domain.bus, 0xaf);
+        assert_eq!(other_domain.device, 0x1f);
+        assert_eq!(other_domain.function, 7);
+        assert_ne!(other_domain, "af:1f.7".parse().unwrap());
+    }
+
+    #[test]
+    fn test_parse_invalid_pci_addr() {
+        for s in [
+            "",
+            "17:00",
+            "17.1",
+            "17:20.0",
+            "17:00.8",
+            "x7:00.1",
+            "00000:17:00.1",
+        ] {
+            assert_eq!(
+                s.parse::<PciAddress>(),
+                Err(DOCAError::DOCA_ERROR_INVALID_VALUE),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn test_display_pci_addr() {
+        let addr = PciAddress::new(0x1, 0x3, 0x0, 0x1).unwrap();
+        assert_eq!(addr.to_string(), "0001:03:00.1");
+        assert_eq!(addr.to_string().parse::<PciAddress>().unwrap(), addr);
+    }
+
+    #[test]
+    #[cfg(not(doca_2_x))]
+    fn test_pci_bdf_conversion() {
+        let addr: PciAddress = "0002:af:1d.5".parse().unwrap();
+        let bdf: ffi::doca_pci_bdf = addr.into();
+        let back = PciAddress::from(bdf);
+
+        // the domain is lost in `doca_pci_bdf`
+        assert_eq!(back, PciAddress { domain: 0, ..addr });
+    }
+}
@@ -7,9 +7,19 @@
 //! - [`DOCADMAJob`]: The DMA request of DOCA. It implements the trait [`ToBaseJob`],
 //! which makes it capable for being submitted to the work queue.
 //!
+//! - [`ReusableTransfer`]: A DMA request bound to a local and a remote buffer, which can be
+//! submitted again in both directions with different ranges once the previous one is finished.
+//!
 //! - [`DMAEngine`]: The DMA Engine of DOCA. Users should create an instance of the engine and
 //! execute DMA requests based on the engine.
 //!
+//! - [`DmaWorkerPool`]: A set of worker threads, each owning a [`DOCAWorkQueue`]
+//! on a shared DMA context. See the [`pool`] module.
+//!
+//! The jobs, the transfers and the pool are built on the DOCA 1.5 work queue.
+//! With DOCA 2.x the engine is created on a device, and the copies are submitted
+//! as [`crate::pe::DmaMemcpyTask`] on a progress engine.
+//!
 //! # Examples
 //!
 //! Create a DMAEngine and get the Context of the engine.
@@ -25,21 +35,49 @@
 //! ```
 //!
 
+#[cfg(not(doca_2_x))]
+use std::ops::Range;
 use std::ptr::NonNull;
 use std::sync::Arc;
 
+#[cfg(not(doca_2_x))]
 use crate::context::work_queue::ToBaseJob;
 use crate::context::EngineToContext;
-use crate::{DOCABuffer, DOCAError, DOCAResult};
+#[cfg(not(doca_2_x))]
+use crate::DOCABuffer;
+#[cfg(doca_2_x)]
+use crate::DevContext;
+use crate::{DOCAError, DOCAResult};
 
+#[cfg(not(doca_2_x))]
 pub use crate::context::work_queue::{DOCAEvent, DOCAWorkQueue};
+#[cfg(not(doca_2_x))]
 pub use crate::context::DOCAContext;
 
+#[cfg(not(doca_2_x))]
+pub use pool::{DmaCompletion, DmaRouting, DmaWorkerPool};
+
+/// Multi-queue DMA worker pool
+#[cfg(not(doca_2_x))]
+pub mod pool;
+
 /// DOCA DMA engine instance
 pub struct DMAEngine {
     inner: NonNull<ffi::doca_dma>,
+
+    // Since DOCA 2.x the engine is created on a device,
+    // which should be closed after the engine is destroyed
+    #[cfg(doca_2_x)]
+    #[allow(dead_code)]
+    dev: Arc<DevContext>,
 }
 
+// SAFETY: the engine has no method changing it through `&self`, after it is
+// created it is only handed to DOCA as the engine of a context.
+unsafe impl Sync for DMAEngine {}
+// SAFETY: `doca_dma` is not bound to the thread that created it.
+unsafe impl Send for DMAEngine {}
+
 impl Drop for DMAEngine {
     fn drop(&mut self) {
         let ret = unsafe { ffi::doca_dma_destroy(self.inner_ptr()) };
@@ -47,9 +85,7 @@ impl Drop for DMAEngine {
             panic!("Failed to destory dma engine!");
         }
 
-        // Show drop order only in `debug` mode
-        #[cfg(debug_assertions)]
-        println!("DMA Engine is dropped!");
+        trace!(dma = ?self.inner, "DMA Engine is dropped");
     }
 }
 
@@ -62,6 +98,7 @@ impl EngineToContext for DMAEngine {
 
 impl DMAEngine {
     /// Create a DOCA DMA instance.
+    #[cfg(not(doca_2_x))]
     pub fn new() -> DOCAResult<Arc<Self>> {
         let mut dma: *mut ffi::doca_dma = std::ptr::null_mut();
         let ret = unsafe { ffi::doca_dma_create(&mut dma as *mut _) };
@@ -75,12 +112,29 @@ impl DMAEngine {
         }))
     }
 
+    /// Create a DOCA DMA instance on the device.
+    #[cfg(doca_2_x)]
+    pub fn new(dev: &Arc<DevContext>) -> DOCAResult<Arc<Self>> {
+        let mut dma: *mut ffi::doca_dma = std::ptr::null_mut();
+        let ret = unsafe { ffi::doca_dma_create(dev.inner_ptr(), &mut dma as *mut _) };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(Arc::new(Self {
+            inner: unsafe { NonNull::new_unchecked(dma) },
+            dev: dev.clone(),
+        }))
+    }
+
     /// Get the inner pointer of the DOCA DMA instance.
     pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_dma {
         self.inner.as_ptr()
     }
 }
 
+#[cfg(not(doca_2_x))]
 /// A DOCA DMA request
 pub struct DOCADMAJob {
     pub(crate) inner: ffi::doca_dma_job_memcpy,
@@ -93,6 +147,13 @@ pub struct DOCADMAJob {
     dst_buff: Option<DOCABuffer>,
 }
 
+// SAFETY: the raw pointers in the DOCA job only point to the `doca_buf`s of
+// `src_buff` and `dst_buff` and to the context, which the job owns or shares,
+// so they move together with the job and no other thread can reach them.
+#[cfg(not(doca_2_x))]
+unsafe impl Send for DOCADMAJob {}
+
+#[cfg(not(doca_2_x))]
 /// Implementation of `ToBaseJob` Trait
 impl ToBaseJob for DOCADMAJob {
     fn to_base(&self) -> &ffi::doca_job {
@@ -100,7 +161,28 @@ impl ToBaseJob for DOCADMAJob {
     }
 }
 
+#[cfg(not(doca_2_x))]
 impl DOCADMAJob {
+    /// Create a DMA job on the given context
+    pub(crate) fn new(
+        ctx: &Arc<DOCAContext<DMAEngine>>,
+        src_buf: DOCABuffer,
+        dst_buf: DOCABuffer,
+    ) -> Self {
+        let mut res = Self {
+            inner: Default::default(),
+            ctx: ctx.clone(),
+            src_buff: None,
+            dst_buff: None,
+        };
+        res.set_ctx()
+            .set_flags()
+            .set_src(src_buf)
+            .set_dst(dst_buf)
+            .set_type();
+        res
+    }
+
     /// Set request's destination buffer
     pub fn set_dst(&mut self, buf: DOCABuffer) -> &mut Self {
         unsafe { self.inner.dst_buff = buf.inner_ptr() };
@@ -138,6 +220,12 @@ impl DOCADMAJob {
         self
     }
 
+    /// Get the mark for user data
+    #[inline]
+    pub fn user_data(&self) -> u64 {
+        unsafe { self.inner.base.user_data.u64_ }
+    }
+
     /// Set request's based context
     fn set_ctx(&mut self) -> &mut Self {
         unsafe { self.inner.base.ctx = self.ctx.inner_ptr() };
@@ -157,134 +245,203 @@ impl DOCADMAJob {
     }
 }
 
-/// A DOCA DMA request
-pub struct DOCADMAReusableJob {
-    pub(crate) inner_read: ffi::doca_dma_job_memcpy,
-    pub(crate) inner_write: ffi::doca_dma_job_memcpy,
+#[cfg(not(doca_2_x))]
+/// The direction of a [`ReusableTransfer`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DmaDirection {
+    /// Copy from the remote buffer into the local buffer
+    Read,
+    /// Copy from the local buffer into the remote buffer
+    Write,
+}
 
-    write: bool,
+#[cfg(not(doca_2_x))]
+/// A DOCA DMA request that is bound to a local and a remote buffer and
+/// can be submitted again and again in both directions.
+///
+/// Each submission takes the direction and the ranges to copy, both relative to the
+/// start of the registered regions. The transfer refuses to be submitted again until
+/// the completion of the previous submission is observed with [`ReusableTransfer::complete`].
+pub struct ReusableTransfer {
+    inner_read: ffi::doca_dma_job_memcpy,
+    inner_write: ffi::doca_dma_job_memcpy,
+
+    direction: DmaDirection,
+    in_flight: bool,
 
-    // FIXME: do we really need to record the context here?
-    #[allow(dead_code)]
     ctx: Arc<DOCAContext<DMAEngine>>,
 
-    local_buf: Option<DOCABuffer>,
-    remote_buf: Option<DOCABuffer>,
+    local_buf: DOCABuffer,
+    remote_buf: DOCABuffer,
 }
 
-/// Implementation of `ToBaseJob` Trait
-impl ToBaseJob for DOCADMAReusableJob {
-    fn to_base(&self) -> &ffi::doca_job {
-        if self.write {
-            &self.inner_write.base
-        } else {
-            &self.inner_read.base
+// SAFETY: the raw pointers in the two DOCA jobs only point to the `doca_buf`s of
+// `local_buf` and `remote_buf` and to the context, which the transfer owns or
+// shares, so they move together with the transfer and no other thread can reach them.
+#[cfg(not(doca_2_x))]
+unsafe impl Send for ReusableTransfer {}
+
+#[cfg(not(doca_2_x))]
+impl ReusableTransfer {
+    /// Create a reusable transfer between the local and the remote buffer
+    pub(crate) fn new(
+        ctx: &Arc<DOCAContext<DMAEngine>>,
+        local_buf: DOCABuffer,
+        remote_buf: DOCABuffer,
+    ) -> Self {
+        let mut res = Self {
+            inner_read: Default::default(),
+            inner_write: Default::default(),
+            direction: DmaDirection::Read,
+            in_flight: false,
+            ctx: ctx.clone(),
+            local_buf,
+            remote_buf,
+        };
+
+        for job in [&mut res.inner_read, &mut res.inner_write] {
+            job.base.ctx = unsafe { ctx.inner_ptr() };
+            job.base.flags = ffi::DOCA_JOB_FLAGS_NONE as i32;
+            job.base.type_ = ffi::DOCA_DMA_JOB_MEMCPY as i32;
         }
-    }
-}
 
-impl DOCADMAReusableJob {
-    /// Set request's destination buffer
-    pub fn set_local(&mut self, buf: DOCABuffer) -> &mut Self {
-        unsafe { self.inner_read.dst_buff = buf.inner_ptr() };
-        unsafe { self.inner_write.src_buff = buf.inner_ptr() };
-        self.local_buf = Some(buf);
-        self
+        unsafe {
+            res.inner_read.src_buff = res.remote_buf.inner_ptr();
+            res.inner_read.dst_buff = res.local_buf.inner_ptr();
+            res.inner_write.src_buff = res.local_buf.inner_ptr();
+            res.inner_write.dst_buff = res.remote_buf.inner_ptr();
+        }
+        res
     }
 
-    /// Set request's source buffer
-    pub fn set_remote(&mut self, buf: DOCABuffer) -> &mut Self {
-        unsafe { self.inner_read.src_buff = buf.inner_ptr() };
-        unsafe { self.inner_write.dst_buff = buf.inner_ptr() };
-        self.remote_buf = Some(buf);
+    /// Set mark for user data, it is shared by both directions
+    #[inline]
+    pub fn set_user_data(&mut self, user_data: u64) -> &mut Self {
+        self.inner_read.base.user_data.u64_ = user_data;
+        self.inner_write.base.user_data.u64_ = user_data;
         self
     }
 
-    /// Set the data pointer of the src buffer
+    /// Get the mark for user data
     #[inline]
-    pub fn set_local_data(&mut self, offset: usize, payload: usize) {
-        if let Some(f) = self.local_buf.as_mut() {
-            unsafe { f.set_data(offset, payload).expect("doca fail to set local data!") };
-        }
+    pub fn user_data(&self) -> u64 {
+        unsafe { self.inner_read.base.user_data.u64_ }
     }
 
-    /// Set the data pointer of the dst buffer
+    /// Get the direction of the last submission
     #[inline]
-    pub fn set_remote_data(&mut self, offset: usize, payload: usize) {
-        if let Some(f) = self.remote_buf.as_mut() {
-            unsafe { f.set_data(offset, payload).expect("doca fail to set remote data!") };
-       }
+    pub fn direction(&self) -> DmaDirection {
+        self.direction
     }
 
-    /// Set mark for user data
+    /// Return `true` if the last submission has not been completed yet
     #[inline]
-    pub fn set_user_data_write(&mut self, user_data: u64, write: bool) {
-        self.write = write;
-        if write {
-            self.inner_write.base.user_data.u64_ = user_data;
-        } else {
-            self.inner_read.base.user_data.u64_ = user_data;
-        }
+    pub fn is_in_flight(&self) -> bool {
+        self.in_flight
     }
 
-    /// Set request's based context
-    fn set_ctx(&mut self) -> &mut Self {
-        unsafe { self.inner_read.base.ctx = self.ctx.inner_ptr() };
-        unsafe { self.inner_write.base.ctx = self.ctx.inner_ptr() };
-        self
+    /// Submit the transfer to the work queue.
+    ///
+    /// `local` and `remote` are the ranges to copy, relative to the start of the
+    /// registered local and remote memory regions.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_IN_USE`: the previous submission has not been completed.
+    ///  - `DOCA_ERROR_INVALID_VALUE`: a range is out of the registered region, the two ranges
+    ///  have different lengths, or the work queue belongs to another context.
+    ///  - Any error returned by `doca_buf_set_data` or `doca_workq_submit`.
+    ///
+    pub fn submit(
+        &mut self,
+        workq: &mut DOCAWorkQueue<DMAEngine>,
+        direction: DmaDirection,
+        local: Range<usize>,
+        remote: Range<usize>,
+    ) -> DOCAResult<()> {
+        if self.in_flight {
+            return Err(DOCAError::DOCA_ERROR_IN_USE);
+        }
+
+        if !Arc::ptr_eq(&self.ctx, &workq.ctx) {
+            return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+        }
+
+        Self::check_range(&self.local_buf, &local)?;
+        Self::check_range(&self.remote_buf, &remote)?;
+        if local.len() != remote.len() {
+            return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+        }
+
+        unsafe {
+            self.local_buf.set_data(local.start, local.len())?;
+            self.remote_buf.set_data(remote.start, remote.len())?;
+        }
+
+        let job = match direction {
+            DmaDirection::Read => &self.inner_read,
+            DmaDirection::Write => &self.inner_write,
+        };
+        let ret = unsafe { ffi::doca_workq_submit(workq.inner_ptr(), &job.base as *const _) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        self.direction = direction;
+        self.in_flight = true;
+        Ok(())
     }
 
-    /// Set request's flags
-    fn set_flags(&mut self) -> &mut Self {
-        self.inner_read.base.flags = ffi::DOCA_JOB_FLAGS_NONE as i32;
-        self.inner_write.base.flags = ffi::DOCA_JOB_FLAGS_NONE as i32;
-        self
+    /// Observe a completion event of the work queue.
+    ///
+    /// Return `Ok(true)` if the event belongs to this transfer and the copy succeeded,
+    /// and `Ok(false)` if the event belongs to another job. Once the event of the
+    /// transfer is observed, it can be submitted again, even if the copy failed.
+    ///
+    /// # Errors
+    ///
+    ///  - The DOCA status of the event, if it belongs to this transfer and the copy failed.
+    ///
+    pub fn complete(&mut self, event: &DOCAEvent) -> DOCAResult<bool> {
+        if !self.in_flight || event.user_mark() != self.user_data() {
+            return Ok(false);
+        }
+
+        self.in_flight = false;
+        match event.result() {
+            DOCAError::DOCA_SUCCESS => Ok(true),
+            e => Err(e),
+        }
     }
 
-    /// Set request's type
-    fn set_type(&mut self) -> &mut Self {
-        self.inner_read.base.type_ = ffi::DOCA_DMA_JOB_MEMCPY as i32;
-        self.inner_write.base.type_ = ffi::DOCA_DMA_JOB_MEMCPY as i32;
-        self
+    /// Check that the range fits in the registered region of the buffer
+    #[inline]
+    fn check_range(buf: &DOCABuffer, range: &Range<usize>) -> DOCAResult<()> {
+        if range.start > range.end || range.end > buf.head.payload {
+            return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+        }
+        Ok(())
     }
 }
 
+#[cfg(not(doca_2_x))]
 impl DOCAWorkQueue<DMAEngine> {
     /// Create a DMA job
     pub fn create_dma_job(&self, src_buf: DOCABuffer, dst_buf: DOCABuffer) -> DOCADMAJob {
-        let mut res = DOCADMAJob {
-            inner: Default::default(),
-            ctx: self.ctx.clone(),
-            src_buff: None,
-            dst_buff: None,
-        };
-        res.set_ctx()
-            .set_flags()
-            .set_src(src_buf)
-            .set_dst(dst_buf)
-            .set_type();
-        res
+        DOCADMAJob::new(&self.ctx, src_buf, dst_buf)
     }
 
-    /// Create a DMA job 
-    pub fn create_dma_reusable_job(&self, local_buf: DOCABuffer, remote_buf: DOCABuffer) -> DOCADMAReusableJob {
-        let mut res = DOCADMAReusableJob {
-            inner_read: Default::default(),
-            inner_write: Default::default(),
-            write: false,
-            ctx: self.ctx.clone(),
-            local_buf: None,
-            remote_buf: None,
-        };
-        res.set_ctx()
-            .set_flags()
-            .set_local(local_buf)
-            .set_remote(remote_buf)
-            .set_type();
-        res
+    /// Create a reusable transfer between a local and a remote buffer
+    pub fn create_reusable_transfer(
+        &self,
+        local_buf: DOCABuffer,
+        remote_buf: DOCABuffer,
+    ) -> ReusableTransfer {
+        ReusableTransfer::new(&self.ctx, local_buf, remote_buf)
     }
 }
 
+#[cfg(not(doca_2_x))]
 mod tests {
 
     #[test]
@@ -330,6 +487,57 @@ mod tests {
         let _ = workq.create_dma_job(src_buf, dst_buf);
     }
 
+    #[test]
+    fn test_reusable_transfer_check_range() {
+        use super::*;
+        use crate::dma::DMAEngine;
+        use crate::*;
+        use std::ptr::NonNull;
+
+        let device = devices().unwrap().get(0).unwrap().open().unwrap();
+        let dma = DMAEngine::new().unwrap();
+        let ctx = DOCAContext::new(&dma, vec![device]).unwrap();
+        let mut workq = DOCAWorkQueue::new(1, &ctx).unwrap();
+
+        let local_mmap = Arc::new(DOCAMmap::new().unwrap());
+        let remote_mmap = Arc::new(DOCAMmap::new().unwrap());
+        let inv = BufferInventory::new(1024).unwrap();
+
+        let test_len = 64;
+        let mut local_buffer = vec![0u8; test_len].into_boxed_slice();
+        let mut remote_buffer = vec![0u8; test_len].into_boxed_slice();
+
+        let local_raw = RawPointer {
+            inner: NonNull::new(local_buffer.as_mut_ptr() as _).unwrap(),
+            payload: test_len,
+        };
+        let remote_raw = RawPointer {
+            inner: NonNull::new(remote_buffer.as_mut_ptr() as _).unwrap(),
+            payload: test_len,
+        };
+
+        let local_buf = DOCARegisteredMemory::new(&local_mmap, local_raw)
+            .unwrap()
+            .to_buffer(&inv)
+            .unwrap();
+        let remote_buf = DOCARegisteredMemory::new(&remote_mmap, remote_raw)
+            .unwrap()
+            .to_buffer(&inv)
+            .unwrap();
+
+        let mut transfer = workq.create_reusable_transfer(local_buf, remote_buf);
+
+        // out of the registered region
+        let ret = transfer.submit(&mut workq, DmaDirection::Write, 0..128, 0..128);
+        assert_eq!(ret, Err(DOCAError::DOCA_ERROR_INVALID_VALUE));
+
+        // different lengths
+        let ret = transfer.submit(&mut workq, DmaDirection::Read, 0..16, 0..32);
+        assert_eq!(ret, Err(DOCAError::DOCA_ERROR_INVALID_VALUE));
+
+        assert!(!transfer.is_in_flight());
+    }
+
     #[test]
     fn test_dma_context() {
         use crate::dma::DMAEngine;
@@ -0,0 +1,338 @@
+//! Multi-queue DMA worker pool.
+//!
+//! A [`DOCAWorkQueue`] is a per-thread object and its `submit` takes `&mut self`,
+//! so a single work queue can not be shared between threads.
+//! [`DmaWorkerPool`] creates one work queue per worker thread on a shared
+//! `DOCAContext<DMAEngine>`, routes the submitted jobs to these workers and merges
+//! all the completions into one channel.
+//!
+//! Creating work queues from several threads at the same time may fail with
+//! `DOCA_ERROR_NO_MEMORY` when DOCA creates the QPs, so the pool creates them
+//! one after another before any job is submitted.
+//!
+//! # Examples
+//!
+//! ``` rust, no_run
+//! use doca::dma::{DOCAContext, DmaRouting, DmaWorkerPool};
+//! use doca::DMAEngine;
+//!
+//! let device = doca::device::open_device_with_pci("03:00.0").unwrap();
+//! let dma = DMAEngine::new().unwrap();
+//! let ctx = DOCAContext::new(&dma, vec![device]).unwrap();
+//!
+//! // 4 worker threads, each with a work queue of depth 32
+//! let pool = DmaWorkerPool::new(&ctx, 4, 32, DmaRouting::RoundRobin).unwrap();
+//!
+//! // ... create jobs with `pool.create_dma_job(src, dst)` and `pool.submit(job)`
+//!
+//! for completion in pool.completions().iter().take(1) {
+//!     println!("job {} finished: {:?}", completion.user_data(), completion.result);
+//! }
+//! ```
+//!
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use super::{DMAEngine, DOCADMAJob};
+use crate::context::work_queue::DOCAWorkQueue;
+use crate::context::DOCAContext;
+use crate::{DOCABuffer, DOCAError, DOCAResult};
+
+/// The policy used by [`DmaWorkerPool`] to pick a worker for a job.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DmaRouting {
+    /// Spread the jobs over all workers in turn.
+    RoundRobin,
+    /// Hash the user data of the job, so that jobs with the same
+    /// user data are always handled (and completed in order) by the same worker.
+    UserData,
+}
+
+/// A finished DMA job reported by the [`DmaWorkerPool`].
+pub struct DmaCompletion {
+    /// Index of the worker that executed the job
+    pub worker: usize,
+    /// The return value of the job
+    pub result: DOCAError,
+    /// The job itself, so that its buffers can be reused
+    pub job: DOCADMAJob,
+}
+
+impl DmaCompletion {
+    /// Return `true` if the job finished successfully
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.result == DOCAError::DOCA_SUCCESS
+    }
+
+    /// Get the user data of the finished job
+    #[inline]
+    pub fn user_data(&self) -> u64 {
+        self.job.user_data()
+    }
+}
+
+/// A pool of worker threads, each one owns a [`DOCAWorkQueue`] on the
+/// same DMA context.
+pub struct DmaWorkerPool {
+    ctx: Arc<DOCAContext<DMAEngine>>,
+    senders: Vec<Sender<DOCADMAJob>>,
+    workers: Vec<JoinHandle<()>>,
+    routing: DmaRouting,
+    next: AtomicUsize,
+    completions: Receiver<DmaCompletion>,
+}
+
+impl Drop for DmaWorkerPool {
+    fn drop(&mut self) {
+        // Closing the job channels tells the workers to exit
+        // once all their in-flight jobs are finished.
+        self.senders.clear();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+
+        trace!("DMA Worker Pool is dropped");
+    }
+}
+
+impl DmaWorkerPool {
+    /// Create a pool of `num_workers` threads, each with a work queue of depth `depth`.
+    ///
+    /// The work queues are created one by one, a worker is only spawned after the
+    /// previous one has finished its setup.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: `num_workers` or `depth` is zero.
+    ///  - Any error returned by `DOCAWorkQueue::new` in one of the workers.
+    ///
+    pub fn new(
+        ctx: &Arc<DOCAContext<DMAEngine>>,
+        num_workers: usize,
+        depth: u32,
+        routing: DmaRouting,
+    ) -> DOCAResult<Self> {
+        if num_workers == 0 || depth == 0 {
+            return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+        }
+
+        let (completion_tx, completion_rx) = mpsc::channel();
+        let mut res = Self {
+            ctx: ctx.clone(),
+            senders: Vec::with_capacity(num_workers),
+            workers: Vec::with_capacity(num_workers),
+            routing,
+            next: AtomicUsize::new(0),
+            completions: completion_rx,
+        };
+
+        for id in 0..num_workers {
+            let (job_tx, job_rx) = mpsc::channel();
+            let (ready_tx, ready_rx) = mpsc::channel();
+            let ctx = ctx.clone();
+            let completion_tx = completion_tx.clone();
+
+            let handle = thread::Builder::new()
+                .name(format!("doca-dma-worker-{}", id))
+                .spawn(move || worker_main(id, ctx, depth, job_rx, completion_tx, ready_tx))
+                .map_err(|_e| DOCAError::DOCA_ERROR_NO_MEMORY)?;
+
+            res.senders.push(job_tx);
+            res.workers.push(handle);
+
+            // Wait until the work queue is created, so that no two
+            // workers create their queues at the same time.
+            ready_rx
+                .recv()
+                .unwrap_or(Err(DOCAError::DOCA_ERROR_UNEXPECTED))?;
+        }
+
+        Ok(res)
+    }
+
+    /// Create a DMA job on the context shared by all workers
+    pub fn create_dma_job(&self, src_buf: DOCABuffer, dst_buf: DOCABuffer) -> DOCADMAJob {
+        DOCADMAJob::new(&self.ctx, src_buf, dst_buf)
+    }
+
+    /// Submit a job to the worker chosen by the routing policy.
+    /// Return the index of the chosen worker.
+    pub fn submit(&self, job: DOCADMAJob) -> DOCAResult<usize> {
+        let worker = match self.routing {
+            DmaRouting::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed) % self.len(),
+            DmaRouting::UserData => {
+                let mut hasher = DefaultHasher::new();
+                job.user_data().hash(&mut hasher);
+                hasher.finish() as usize % self.len()
+            }
+        };
+
+        self.submit_to(worker, job)?;
+        Ok(worker)
+    }
+
+    /// Submit a job to the given worker.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the worker index is out of bounds.
+    ///  - `DOCA_ERROR_UNEXPECTED`: the worker has exited.
+    ///
+    pub fn submit_to(&self, worker: usize, job: DOCADMAJob) -> DOCAResult<()> {
+        self.senders
+            .get(worker)
+            .ok_or(DOCAError::DOCA_ERROR_INVALID_VALUE)?
+            .send(job)
+            .map_err(|_e| DOCAError::DOCA_ERROR_UNEXPECTED)
+    }
+
+    /// The channel where the completions of all workers are merged
+    pub fn completions(&self) -> &Receiver<DmaCompletion> {
+        &self.completions
+    }
+
+    /// Returns the number of workers.
+    pub fn len(&self) -> usize {
+        self.senders.len()
+    }
+
+    /// Returns `true` if there are no workers.
+    pub fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+}
+
+/// The main loop of a worker.
+///
+/// The user data of a job is replaced by the slot index while it is in flight,
+/// and restored before the job is handed back in the completion.
+fn worker_main(
+    id: usize,
+    ctx: Arc<DOCAContext<DMAEngine>>,
+    depth: u32,
+    jobs: Receiver<DOCADMAJob>,
+    completions: Sender<DmaCompletion>,
+    ready: Sender<DOCAResult<()>>,
+) {
+    let mut workq = match DOCAWorkQueue::new(depth, &ctx) {
+        Ok(workq) => {
+            let _ = ready.send(Ok(()));
+            workq
+        }
+        Err(e) => {
+            let _ = ready.send(Err(e));
+            return;
+        }
+    };
+
+    let depth = depth as usize;
+    let mut in_flight: Vec<Option<(u64, DOCADMAJob)>> = (0..depth).map(|_| None).collect();
+    let mut free_slots: Vec<usize> = (0..depth).rev().collect();
+    let mut pending: VecDeque<DOCADMAJob> = VecDeque::new();
+    let mut closed = false;
+
+    let complete = |job: DOCADMAJob, result: DOCAError| {
+        let _ = completions.send(DmaCompletion {
+            worker: id,
+            result,
+            job,
+        });
+    };
+
+    loop {
+        // Fetch new jobs, block only if there is nothing to poll
+        if !closed {
+            if free_slots.len() == depth && pending.is_empty() {
+                match jobs.recv() {
+                    Ok(job) => pending.push_back(job),
+                    Err(_) => closed = true,
+                }
+            }
+            loop {
+                match jobs.try_recv() {
+                    Ok(job) => pending.push_back(job),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        closed = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        if closed && pending.is_empty() && free_slots.len() == depth {
+            break;
+        }
+
+        // Submit as many jobs as the work queue can hold
+        while let Some(&slot) = free_slots.last() {
+            let mut job = match pending.pop_front() {
+                Some(job) => job,
+                None => break,
+            };
+
+            let user_data = job.user_data();
+            job.set_user_data(slot as u64);
+
+            match workq.submit(&job) {
+                Ok(()) => {
+                    free_slots.pop();
+                    in_flight[slot] = Some((user_data, job));
+                }
+                Err(DOCAError::DOCA_ERROR_NO_MEMORY) => {
+                    // The queue is full, retry after some jobs are finished
+                    job.set_user_data(user_data);
+                    pending.push_front(job);
+                    break;
+                }
+                Err(e) => {
+                    job.set_user_data(user_data);
+                    complete(job, e);
+                }
+            }
+        }
+
+        // Retrieve the finished jobs
+        loop {
+            let (event, ret) = workq.progress_retrieve();
+            if ret == DOCAError::DOCA_ERROR_AGAIN {
+                break;
+            }
+
+            let slot = event.user_mark() as usize;
+            if let Some((user_data, mut job)) = in_flight.get_mut(slot).and_then(Option::take) {
+                free_slots.push(slot);
+                job.set_user_data(user_data);
+                complete(job, ret);
+            }
+        }
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_create_worker_pool() {
+        use crate::dma::{DMAEngine, DOCAContext, DmaRouting, DmaWorkerPool};
+
+        let device = crate::device::devices()
+            .unwrap()
+            .get(0)
+            .unwrap()
+            .open()
+            .unwrap();
+
+        let dma = DMAEngine::new().unwrap();
+        let ctx = DOCAContext::new(&dma, vec![device]).unwrap();
+
+        let pool = DmaWorkerPool::new(&ctx, 4, 16, DmaRouting::RoundRobin).unwrap();
+        assert_eq!(pool.len(), 4);
+    }
+}
@@ -0,0 +1,722 @@
+//! Wrapper for DOCA Erasure Coding. It provides the ability of
+//! computing Reed-Solomon redundancy blocks and recovering lost data blocks
+//! using hardware acceleration.
+//!
+//! It basically contains the following core structs:
+//! - [`EcEngine`]: The Erasure Coding Engine of DOCA, created on a device.
+//!
+//! - [`CodingMatrix`]: A coding matrix of the engine, creating `redundancy_blocks`
+//! redundancy blocks from `data_blocks` data blocks. It is described by a serializable
+//! [`CodingMatrixDesc`], so that every node of a cluster can build the same matrix.
+//! [`RecoverMatrix`] is derived from it for a given set of missing blocks.
+//!
+//! - [`EcCreateTask`]: Compute the redundancy blocks of the data blocks.
+//!
+//! - [`EcRecoverTask`]: Rebuild the missing data blocks from the available blocks.
+//!
+//! The blocks of a task are laid out back to back in the data of a buffer,
+//! so all of them have the length of the data divided by the number of blocks.
+//!
+//! The Erasure Coding library is only available since DOCA 2.0, which replaced the
+//! work queue with the progress engine, so the engine is built on the progress engine
+//! (see [`crate::pe`]) rather than on `DOCAContext` and `DOCAWorkQueue`, and requires
+//! the `erasure-coding` and `doca-2-x` features. The tasks are enabled on the context with
+//! [`PeContextBuilder::ec_create`] and [`PeContextBuilder::ec_recover`].
+//!
+//! # Examples
+//!
+//! ``` rust, no_run
+//! use doca::erasure_coding::{CodingMatrix, CodingMatrixDesc, EcCreateTask, EcEngine, MatrixKind};
+//! use doca::pe::{PeContext, ProgressEngine};
+//!
+//! let device = doca::device::open_device_with_pci("03:00.0").unwrap();
+//! let pe = ProgressEngine::new().unwrap();
+//! let ec = EcEngine::new(&device).unwrap();
+//!
+//! let ctx = PeContext::builder(&ec, &pe)
+//!     .ec_create(16, |task, result| {
+//!         println!("task {} finished: {:?}", task.user_data(), result);
+//!     })
+//!     .start()
+//!     .unwrap();
+//!
+//! // 4 data blocks protected by 2 redundancy blocks
+//! let desc = CodingMatrixDesc::new(MatrixKind::Cauchy, 4, 2);
+//! let matrix = CodingMatrix::new(&ec, desc).unwrap();
+//!
+//! # fn buffers() -> (doca::DOCABuffer, doca::DOCABuffer) { unimplemented!() }
+//! # let (data_buf, rdnc_buf) = buffers();
+//! let task = EcCreateTask::new(&ctx, &matrix, data_buf, rdnc_buf, 1).unwrap();
+//! task.submit().map_err(|(e, _task)| e).unwrap();
+//! pe.progress_until_idle().unwrap();
+//! ```
+//!
+
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::context::EngineToContext;
+use crate::pe::task::{self, RawTask, TaskCompletion};
+use crate::pe::{CtxCallbacks, OnCompletion, PeContext, PeContextBuilder, Task};
+use crate::{DOCABuffer, DOCAError, DOCAResult, DevContext};
+
+/// DOCA Erasure Coding engine instance
+pub struct EcEngine {
+    inner: NonNull<ffi::doca_ec>,
+
+    // The engine is created on a device,
+    // which should be closed after the engine is destroyed
+    #[allow(dead_code)]
+    dev: Arc<DevContext>,
+}
+
+unsafe impl Sync for EcEngine {}
+unsafe impl Send for EcEngine {}
+
+impl Drop for EcEngine {
+    fn drop(&mut self) {
+        let ret = unsafe { ffi::doca_ec_destroy(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            panic!("Failed to destory erasure coding engine!");
+        }
+
+        trace!(ec = ?self.inner, "EC Engine is dropped");
+    }
+}
+
+/// Implementation `EngineToContext` Trait for Erasure Coding Engine
+impl EngineToContext for EcEngine {
+    unsafe fn to_ctx(&self) -> *mut ffi::doca_ctx {
+        ffi::doca_ec_as_ctx(self.inner_ptr())
+    }
+}
+
+impl EcEngine {
+    /// Create a DOCA Erasure Coding instance on the device.
+    pub fn new(dev: &Arc<DevContext>) -> DOCAResult<Arc<Self>> {
+        let mut ec: *mut ffi::doca_ec = std::ptr::null_mut();
+        let ret = unsafe { ffi::doca_ec_create(dev.inner_ptr(), &mut ec as *mut _) };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(Arc::new(Self {
+            inner: unsafe { NonNull::new_unchecked(ec) },
+            dev: dev.clone(),
+        }))
+    }
+
+    /// Get the inner pointer of the DOCA Erasure Coding instance.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_ec {
+        self.inner.as_ptr()
+    }
+}
+
+/// The kinds of coding matrix
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatrixKind {
+    /// A Cauchy matrix generated by DOCA
+    Cauchy,
+    /// A Vandermonde matrix generated by DOCA
+    Vandermonde,
+    /// User provided coefficients over GF(2^8): the coefficient of the data block `i`
+    /// in the redundancy block `j` is at `i * redundancy_blocks + j`
+    Raw(Vec<u8>),
+}
+
+/// A (de)serializable description of a coding matrix, building the same
+/// matrix on every node
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodingMatrixDesc {
+    /// The kind of the matrix
+    pub kind: MatrixKind,
+    /// The number of data blocks
+    pub data_blocks: usize,
+    /// The number of redundancy blocks
+    pub redundancy_blocks: usize,
+}
+
+impl CodingMatrixDesc {
+    /// Describe a coding matrix
+    pub fn new(kind: MatrixKind, data_blocks: usize, redundancy_blocks: usize) -> Self {
+        Self {
+            kind,
+            data_blocks,
+            redundancy_blocks,
+        }
+    }
+
+    /// Convert a CodingMatrixDesc to Vec<u8> for socket sending
+    #[inline]
+    pub fn serialize(&self) -> DOCAResult<Vec<u8>> {
+        serde_json::to_vec(self).map_err(|_| DOCAError::DOCA_ERROR_INVALID_VALUE)
+    }
+
+    /// Convert a u8 slice received to CodingMatrixDesc
+    #[inline]
+    pub fn deserialize(src: &[u8]) -> DOCAResult<Self> {
+        serde_json::from_slice(src).map_err(|_| DOCAError::DOCA_ERROR_INVALID_VALUE)
+    }
+}
+
+/// A coding matrix of the erasure coding engine
+pub struct CodingMatrix {
+    inner: NonNull<ffi::doca_ec_matrix>,
+    desc: CodingMatrixDesc,
+
+    // Ensure that the engine is destroyed after the matrix
+    #[allow(dead_code)]
+    engine: Arc<EcEngine>,
+}
+
+unsafe impl Sync for CodingMatrix {}
+unsafe impl Send for CodingMatrix {}
+
+impl Drop for CodingMatrix {
+    fn drop(&mut self) {
+        let ret = unsafe { ffi::doca_ec_matrix_destroy(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            panic!("Failed to destroy the coding matrix: {:?}", ret);
+        }
+
+        trace!(matrix = ?self.inner, "EC Coding Matrix is dropped");
+    }
+}
+
+impl CodingMatrix {
+    /// Create the coding matrix described by `desc`.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: a raw matrix does not have
+    ///  `data_blocks * redundancy_blocks` coefficients.
+    ///  - Any error returned by `doca_ec_matrix_create`.
+    ///
+    pub fn new(engine: &Arc<EcEngine>, desc: CodingMatrixDesc) -> DOCAResult<Arc<Self>> {
+        let mut matrix: *mut ffi::doca_ec_matrix = std::ptr::null_mut();
+        let ret = match &desc.kind {
+            MatrixKind::Cauchy | MatrixKind::Vandermonde => {
+                let matrix_type = if desc.kind == MatrixKind::Cauchy {
+                    ffi::DOCA_EC_MATRIX_TYPE_CAUCHY
+                } else {
+                    ffi::DOCA_EC_MATRIX_TYPE_VANDERMONDE
+                };
+                unsafe {
+                    ffi::doca_ec_matrix_create(
+                        engine.inner_ptr(),
+                        matrix_type,
+                        desc.data_blocks,
+                        desc.redundancy_blocks,
+                        &mut matrix as *mut _,
+                    )
+                }
+            }
+            MatrixKind::Raw(coefficients) => {
+                if coefficients.len() != desc.data_blocks * desc.redundancy_blocks {
+                    return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+                }
+                // DOCA copies the coefficients
+                let mut coefficients = coefficients.clone();
+                unsafe {
+                    ffi::doca_ec_matrix_create_from_raw(
+                        engine.inner_ptr(),
+                        coefficients.as_mut_ptr(),
+                        desc.data_blocks,
+                        desc.redundancy_blocks,
+                        &mut matrix as *mut _,
+                    )
+                }
+            }
+        };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(Arc::new(Self {
+            inner: unsafe { NonNull::new_unchecked(matrix) },
+            desc,
+            engine: engine.clone(),
+        }))
+    }
+
+    /// Get the description of the matrix
+    #[inline]
+    pub fn desc(&self) -> &CodingMatrixDesc {
+        &self.desc
+    }
+
+    /// Create the matrix recovering the missing data blocks, given by their indices.
+    /// The indices of the redundancy blocks follow the data blocks.
+    pub fn recover_matrix(self: &Arc<Self>, missing: &[u32]) -> DOCAResult<Arc<RecoverMatrix>> {
+        let mut missing = missing.to_vec();
+        let mut matrix: *mut ffi::doca_ec_matrix = std::ptr::null_mut();
+        let ret = unsafe {
+            ffi::doca_ec_matrix_create_recover(
+                self.engine.inner_ptr(),
+                self.inner_ptr(),
+                missing.as_mut_ptr(),
+                missing.len(),
+                &mut matrix as *mut _,
+            )
+        };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(Arc::new(RecoverMatrix {
+            inner: unsafe { NonNull::new_unchecked(matrix) },
+            missing,
+            coding: self.clone(),
+        }))
+    }
+
+    /// Get the inner pointer of the DOCA coding matrix.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_ec_matrix {
+        self.inner.as_ptr()
+    }
+}
+
+/// A matrix recovering a set of missing data blocks, see [`CodingMatrix::recover_matrix`]
+pub struct RecoverMatrix {
+    inner: NonNull<ffi::doca_ec_matrix>,
+    missing: Vec<u32>,
+
+    // Ensure that the coding matrix is destroyed after the recover matrix
+    coding: Arc<CodingMatrix>,
+}
+
+unsafe impl Sync for RecoverMatrix {}
+unsafe impl Send for RecoverMatrix {}
+
+impl Drop for RecoverMatrix {
+    fn drop(&mut self) {
+        let ret = unsafe { ffi::doca_ec_matrix_destroy(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            panic!("Failed to destroy the recover matrix: {:?}", ret);
+        }
+
+        trace!(matrix = ?self.inner, "EC Recover Matrix is dropped");
+    }
+}
+
+impl RecoverMatrix {
+    /// Get the indices of the missing blocks
+    #[inline]
+    pub fn missing(&self) -> &[u32] {
+        &self.missing
+    }
+
+    /// Get the coding matrix it is derived from
+    #[inline]
+    pub fn coding_matrix(&self) -> &Arc<CodingMatrix> {
+        &self.coding
+    }
+
+    /// Get the inner pointer of the DOCA recover matrix.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_ec_matrix {
+        self.inner.as_ptr()
+    }
+}
+
+/// What an erasure coding task owns
+pub struct EcTaskState<M> {
+    // Ensure that the context is stopped after all its tasks are freed
+    #[allow(dead_code)]
+    ctx: Arc<PeContext<EcEngine>>,
+    matrix: Arc<M>,
+    src_buf: DOCABuffer,
+    dst_buf: DOCABuffer,
+}
+
+impl RawTask for ffi::doca_ec_task_create {
+    type State = EcTaskState<CodingMatrix>;
+
+    unsafe fn as_task(task: *mut Self) -> *mut ffi::doca_task {
+        ffi::doca_ec_task_create_as_task(task)
+    }
+}
+
+impl TaskCompletion for ffi::doca_ec_task_create {
+    type Error = DOCAError;
+
+    fn callback(callbacks: &mut CtxCallbacks) -> &mut OnCompletion<EcCreateTask> {
+        &mut callbacks.ec_create
+    }
+
+    fn error(status: DOCAError) -> DOCAError {
+        status
+    }
+}
+
+impl RawTask for ffi::doca_ec_task_recover {
+    type State = EcTaskState<RecoverMatrix>;
+
+    unsafe fn as_task(task: *mut Self) -> *mut ffi::doca_task {
+        ffi::doca_ec_task_recover_as_task(task)
+    }
+}
+
+impl TaskCompletion for ffi::doca_ec_task_recover {
+    type Error = DOCAError;
+
+    fn callback(callbacks: &mut CtxCallbacks) -> &mut OnCompletion<EcRecoverTask> {
+        &mut callbacks.ec_recover
+    }
+
+    fn error(status: DOCAError) -> DOCAError {
+        status
+    }
+}
+
+/// A DOCA erasure coding create task, appending the redundancy blocks of
+/// the data blocks of the source buffer to the destination buffer.
+pub type EcCreateTask = Task<ffi::doca_ec_task_create>;
+
+impl EcCreateTask {
+    /// Allocate a create task on the started context.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NO_MEMORY`: the number of tasks set by
+    ///  [`PeContextBuilder::ec_create`] are all allocated.
+    ///  - `DOCA_ERROR_BAD_STATE`: the context is not running.
+    ///
+    pub fn new(
+        ctx: &Arc<PeContext<EcEngine>>,
+        matrix: &Arc<CodingMatrix>,
+        data_buf: DOCABuffer,
+        rdnc_buf: DOCABuffer,
+        user_data: u64,
+    ) -> DOCAResult<Self> {
+        let state = EcTaskState {
+            ctx: ctx.clone(),
+            matrix: matrix.clone(),
+            src_buf: data_buf,
+            dst_buf: rdnc_buf,
+        };
+
+        unsafe {
+            Self::alloc(state, user_data, |state, task_user_data, task| {
+                ffi::doca_ec_task_create_allocate_init(
+                    ctx.engine().inner_ptr(),
+                    state.matrix.inner_ptr(),
+                    state.src_buf.inner_ptr(),
+                    state.dst_buf.inner_ptr(),
+                    task_user_data,
+                    task,
+                )
+            })
+        }
+        .map_err(|(e, _state)| e)
+    }
+
+    /// Free the task and return its data and redundancy buffers.
+    pub fn into_buffers(self) -> (DOCABuffer, DOCABuffer) {
+        let state = self.into_state();
+        (state.src_buf, state.dst_buf)
+    }
+
+    /// Get the buffer of the data blocks
+    #[inline]
+    pub fn data_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().src_buf
+    }
+
+    /// Get the buffer of the redundancy blocks
+    #[inline]
+    pub fn rdnc_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().dst_buf
+    }
+}
+
+/// A DOCA erasure coding recover task, rebuilding the missing data blocks.
+///
+/// The source buffer holds, in index order, as many available blocks as data blocks,
+/// and the missing data blocks are appended to the destination buffer in index order.
+pub type EcRecoverTask = Task<ffi::doca_ec_task_recover>;
+
+impl EcRecoverTask {
+    /// Allocate a recover task on the started context.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NO_MEMORY`: the number of tasks set by
+    ///  [`PeContextBuilder::ec_recover`] are all allocated.
+    ///  - `DOCA_ERROR_BAD_STATE`: the context is not running.
+    ///
+    pub fn new(
+        ctx: &Arc<PeContext<EcEngine>>,
+        matrix: &Arc<RecoverMatrix>,
+        available_buf: DOCABuffer,
+        recovered_buf: DOCABuffer,
+        user_data: u64,
+    ) -> DOCAResult<Self> {
+        let state = EcTaskState {
+            ctx: ctx.clone(),
+            matrix: matrix.clone(),
+            src_buf: available_buf,
+            dst_buf: recovered_buf,
+        };
+
+        unsafe {
+            Self::alloc(state, user_data, |state, task_user_data, task| {
+                ffi::doca_ec_task_recover_allocate_init(
+                    ctx.engine().inner_ptr(),
+                    state.matrix.inner_ptr(),
+                    state.src_buf.inner_ptr(),
+                    state.dst_buf.inner_ptr(),
+                    task_user_data,
+                    task,
+                )
+            })
+        }
+        .map_err(|(e, _state)| e)
+    }
+
+    /// Free the task and return its available and recovered buffers.
+    pub fn into_buffers(self) -> (DOCABuffer, DOCABuffer) {
+        let state = self.into_state();
+        (state.src_buf, state.dst_buf)
+    }
+
+    /// Get the buffer of the available blocks
+    #[inline]
+    pub fn available_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().src_buf
+    }
+
+    /// Get the buffer of the recovered blocks
+    #[inline]
+    pub fn recovered_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().dst_buf
+    }
+}
+
+impl PeContextBuilder<EcEngine> {
+    /// Enable the create tasks on the context.
+    ///
+    /// At most `num_tasks` tasks can be allocated at the same time. `on_completion` is
+    /// called with every completed task and its result.
+    pub fn ec_create<F>(&mut self, num_tasks: u32, on_completion: F) -> &mut Self
+    where
+        F: FnMut(EcCreateTask, DOCAResult<()>) + 'static,
+    {
+        self.callbacks.ec_create = Some(Box::new(on_completion));
+        self.confs.push(Box::new(move |ec: &EcEngine| {
+            let ret = unsafe {
+                ffi::doca_ec_task_create_set_conf(
+                    ec.inner_ptr(),
+                    Some(task::success_cb::<ffi::doca_ec_task_create>),
+                    Some(task::error_cb::<ffi::doca_ec_task_create>),
+                    num_tasks,
+                )
+            };
+
+            if ret != DOCAError::DOCA_SUCCESS {
+                return Err(ret);
+            }
+            Ok(())
+        }));
+        self
+    }
+
+    /// Enable the recover tasks on the context.
+    ///
+    /// At most `num_tasks` tasks can be allocated at the same time. `on_completion` is
+    /// called with every completed task and its result.
+    pub fn ec_recover<F>(&mut self, num_tasks: u32, on_completion: F) -> &mut Self
+    where
+        F: FnMut(EcRecoverTask, DOCAResult<()>) + 'static,
+    {
+        self.callbacks.ec_recover = Some(Box::new(on_completion));
+        self.confs.push(Box::new(move |ec: &EcEngine| {
+            let ret = unsafe {
+                ffi::doca_ec_task_recover_set_conf(
+                    ec.inner_ptr(),
+                    Some(task::success_cb::<ffi::doca_ec_task_recover>),
+                    Some(task::error_cb::<ffi::doca_ec_task_recover>),
+                    num_tasks,
+                )
+            };
+
+            if ret != DOCAError::DOCA_SUCCESS {
+                return Err(ret);
+            }
+            Ok(())
+        }));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    /// Multiply in GF(2^8) with the polynomial 0x11d, as the hardware
+    fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+        let mut p = 0_u8;
+        while b != 0 {
+            if b & 1 != 0 {
+                p ^= a;
+            }
+            let carry = a & 0x80 != 0;
+            a <<= 1;
+            if carry {
+                a ^= 0x1d;
+            }
+            b >>= 1;
+        }
+        p
+    }
+
+    /// A reference encoder of a raw coding matrix, see `MatrixKind::Raw`
+    fn reference_encode(
+        matrix: &[u8],
+        data_blocks: usize,
+        rdnc_blocks: usize,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let block_size = data.len() / data_blocks;
+        let mut rdnc = vec![0_u8; rdnc_blocks * block_size];
+
+        for j in 0..rdnc_blocks {
+            for i in 0..data_blocks {
+                let coef = matrix[i * rdnc_blocks + j];
+                let block = &data[i * block_size..(i + 1) * block_size];
+                for (r, d) in rdnc[j * block_size..(j + 1) * block_size]
+                    .iter_mut()
+                    .zip(block)
+                {
+                    *r ^= gf_mul(coef, *d);
+                }
+            }
+        }
+        rdnc
+    }
+
+    #[test]
+    fn test_reference_encoder() {
+        use crate::erasure_coding::{CodingMatrixDesc, MatrixKind};
+
+        assert_eq!(gf_mul(0x80, 2), 0x1d);
+        assert_eq!(gf_mul(0x53, 1), 0x53);
+        assert_eq!(gf_mul(0x53, 0), 0);
+
+        // all-ones coefficients make a XOR parity
+        let data: Vec<u8> = (0..12).collect();
+        let parity = reference_encode(&[1, 1, 1], 3, 1, &data);
+        assert_eq!(parity, vec![12, 13, 14, 15]);
+
+        let desc = CodingMatrixDesc::new(MatrixKind::Raw(vec![1, 2, 3, 4, 5, 6]), 3, 2);
+        assert_eq!(
+            CodingMatrixDesc::deserialize(&desc.serialize().unwrap()),
+            Ok(desc)
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a DOCA device supporting erasure coding"]
+    fn test_ec_create_and_recover() {
+        use crate::device::DeviceCapability;
+        use crate::erasure_coding::*;
+        use crate::pe::ProgressEngine;
+        use crate::*;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let dev = devices().unwrap().get(0).unwrap();
+        assert!(dev.supports(DeviceCapability::EcCreate).unwrap());
+        let device = dev.open().unwrap();
+
+        let pe = ProgressEngine::new().unwrap();
+        let ec = EcEngine::new(&device).unwrap();
+
+        let done = Rc::new(RefCell::new(Vec::new()));
+        let (create_done, recover_done) = (done.clone(), done.clone());
+        let ctx = PeContext::builder(&ec, &pe)
+            .ec_create(1, move |task, result| {
+                assert_eq!(result, Ok(()));
+                create_done.borrow_mut().push(task.into_buffers());
+            })
+            .ec_recover(1, move |task, result| {
+                assert_eq!(result, Ok(()));
+                recover_done.borrow_mut().push(task.into_buffers());
+            })
+            .start()
+            .unwrap();
+
+        let (data_blocks, rdnc_blocks, block_size) = (4, 2, 64);
+        let mut data: Vec<u8> = (0..data_blocks * block_size)
+            .map(|i| (i * 7) as u8)
+            .collect();
+        let mut rdnc = vec![0_u8; rdnc_blocks * block_size];
+        let mut available = vec![0_u8; data_blocks * block_size];
+        let mut recovered = vec![0_u8; block_size];
+        let inv = BufferInventory::new(8).unwrap();
+
+        let to_buffer =
+            |buffer: &mut [u8], len: usize| crate::memory::test_buffer(&device, &inv, buffer, len);
+        let data_len = data.len();
+        let data_buf = to_buffer(&mut data, data_len);
+        let rdnc_buf = to_buffer(&mut rdnc, 0);
+
+        // the redundancy of a raw matrix matches the reference encoder
+        let coefficients: Vec<u8> = (1..=(data_blocks * rdnc_blocks) as u8).collect();
+        let desc = CodingMatrixDesc::new(
+            MatrixKind::Raw(coefficients.clone()),
+            data_blocks,
+            rdnc_blocks,
+        );
+        let raw_matrix = CodingMatrix::new(&ec, desc).unwrap();
+        EcCreateTask::new(&ctx, &raw_matrix, data_buf, rdnc_buf, 0)
+            .unwrap()
+            .submit()
+            .map_err(|(e, _task)| e)
+            .unwrap();
+        pe.progress_until_idle().unwrap();
+        assert_eq!(
+            rdnc,
+            reference_encode(&coefficients, data_blocks, rdnc_blocks, &data)
+        );
+
+        // encode again with a Cauchy matrix into the same buffers
+        let (data_buf, mut rdnc_buf) = done.borrow_mut().pop().unwrap();
+        unsafe { rdnc_buf.set_data(0, 0).unwrap() };
+        let desc = CodingMatrixDesc::new(MatrixKind::Cauchy, data_blocks, rdnc_blocks);
+        let matrix = CodingMatrix::new(&ec, desc).unwrap();
+        EcCreateTask::new(&ctx, &matrix, data_buf, rdnc_buf, 0)
+            .unwrap()
+            .submit()
+            .map_err(|(e, _task)| e)
+            .unwrap();
+        pe.progress_until_idle().unwrap();
+
+        // lose the data block 1, the available blocks are 0, 2, 3 and the first redundancy block
+        let missing = 1;
+        for (dst, src) in [0, 2, 3].iter().enumerate() {
+            available[dst * block_size..(dst + 1) * block_size]
+                .copy_from_slice(&data[src * block_size..(src + 1) * block_size]);
+        }
+        available[3 * block_size..].copy_from_slice(&rdnc[..block_size]);
+
+        let available_len = available.len();
+        let available_buf = to_buffer(&mut available, available_len);
+        let recovered_buf = to_buffer(&mut recovered, 0);
+        let recover = matrix.recover_matrix(&[missing as u32]).unwrap();
+        EcRecoverTask::new(&ctx, &recover, available_buf, recovered_buf, 0)
+            .unwrap()
+            .submit()
+            .map_err(|(e, _task)| e)
+            .unwrap();
+        pe.progress_until_idle().unwrap();
+
+        assert_eq!(
+            recovered,
+            data[missing * block_size..(missing + 1) * block_size]
+        );
+        assert_eq!(done.borrow().len(), 2);
+    }
+}
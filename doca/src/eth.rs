@@ -0,0 +1,701 @@
+//! Wrapper for DOCA Ethernet. It provides the ability of receiving and
+//! transmitting packets on the ports of the device from the CPU.
+//!
+//! It basically contains the following core structs:
+//! - [`EthRxq`] and [`EthTxq`]: The receive and transmit queues of DOCA, created on
+//! a device. They are the engines of the contexts, like the other engines of DOCA 2.x.
+//!
+//! - [`EthRecvTask`] and [`EthSendTask`]: Receive a packet into a buffer, and transmit
+//! the packet in the data of a buffer.
+//!
+//! - [`RxQueue`] and [`TxQueue`]: Start a queue on a progress engine, and receive or
+//! transmit the packets by bursts. [`RxQueue::recv_burst`] returns the received packets
+//! as [`EthPacket`]s, with the buffers, the packet lengths and the metadata.
+//!
+//! The packet buffers are drawn from a [`BufferInventory`] over a registered
+//! [`crate::DOCAMmap`], e.g. with [`packet_buffers`].
+//! Packets are only received once DOCA Flow steers them to the queue,
+//! see [`PeContext::flow_queue_id`].
+//!
+//! The queues are only available since DOCA 2.5, so they are built on the
+//! progress engine (see [`crate::pe`]) and require the `eth` and `doca-2-x` features.
+//!
+//! # Examples
+//!
+//! ``` rust, no_run
+//! use doca::eth::{packet_buffers, EthRxq, RxQueue};
+//! use doca::pe::ProgressEngine;
+//!
+//! let device = doca::device::open_device_with_pci("03:00.0").unwrap();
+//! let pe = ProgressEngine::new().unwrap();
+//! let rxq = EthRxq::new(&device, 32, 1500).unwrap();
+//! let rx = RxQueue::new(&rxq, &pe, 32).unwrap();
+//!
+//! # fn registered() -> (std::sync::Arc<doca::DOCAMmap>, doca::RawPointer) { unimplemented!() }
+//! let (mmap, region) = registered();
+//! let inv = doca::BufferInventory::new(32).unwrap();
+//! for buf in packet_buffers(&mmap, region, 1500, &inv).unwrap() {
+//!     rx.post(buf).map_err(|(e, _buf)| e).unwrap();
+//! }
+//!
+//! loop {
+//!     for packet in rx.recv_burst(32) {
+//!         println!("received {} bytes", packet.len());
+//!         rx.post(packet.buf).map_err(|(e, _buf)| e).unwrap();
+//!     }
+//! }
+//! ```
+//!
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::ptr::NonNull;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::context::EngineToContext;
+use crate::pe::task::{self, RawTask, TaskCompletion};
+use crate::pe::{CtxCallbacks, OnCompletion, PeContext, PeContextBuilder, ProgressEngine, Task};
+use crate::{
+    BufferInventory, DOCABuffer, DOCAError, DOCAMmap, DOCARegisteredMemory, DOCAResult, DevContext,
+    RawPointer,
+};
+
+/// DOCA Ethernet receive queue instance
+pub struct EthRxq {
+    inner: NonNull<ffi::doca_eth_rxq>,
+
+    // The queue is created on a device,
+    // which should be closed after the queue is destroyed
+    #[allow(dead_code)]
+    dev: Arc<DevContext>,
+}
+
+unsafe impl Sync for EthRxq {}
+unsafe impl Send for EthRxq {}
+
+impl Drop for EthRxq {
+    fn drop(&mut self) {
+        let ret = unsafe { ffi::doca_eth_rxq_destroy(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            panic!("Failed to destory eth rxq!");
+        }
+
+        trace!(rxq = ?self.inner, "Eth Rxq is dropped");
+    }
+}
+
+/// Implementation `EngineToContext` Trait for Ethernet receive queue
+impl EngineToContext for EthRxq {
+    unsafe fn to_ctx(&self) -> *mut ffi::doca_ctx {
+        ffi::doca_eth_rxq_as_doca_ctx(self.inner_ptr())
+    }
+}
+
+impl EthRxq {
+    /// Create a DOCA Ethernet receive queue on the device.
+    ///
+    /// At most `max_burst_size` receive tasks can be in flight, and the packets are
+    /// at most `max_packet_size` bytes long.
+    pub fn new(
+        dev: &Arc<DevContext>,
+        max_burst_size: u32,
+        max_packet_size: u32,
+    ) -> DOCAResult<Arc<Self>> {
+        let mut rxq: *mut ffi::doca_eth_rxq = std::ptr::null_mut();
+        let ret = unsafe {
+            ffi::doca_eth_rxq_create(
+                dev.inner_ptr(),
+                max_burst_size,
+                max_packet_size,
+                &mut rxq as *mut _,
+            )
+        };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        let res = Arc::new(Self {
+            inner: unsafe { NonNull::new_unchecked(rxq) },
+            dev: dev.clone(),
+        });
+
+        // The packets are received by the tasks, into the buffers of the user
+        let ret =
+            unsafe { ffi::doca_eth_rxq_set_type(res.inner_ptr(), ffi::DOCA_ETH_RXQ_TYPE_REGULAR) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(res)
+    }
+
+    /// Get the inner pointer of the DOCA Ethernet receive queue.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_eth_rxq {
+        self.inner.as_ptr()
+    }
+}
+
+impl PeContext<EthRxq> {
+    /// Get the id of the queue in DOCA Flow, to steer the packets to the started queue.
+    pub fn flow_queue_id(&self) -> DOCAResult<u16> {
+        let mut id: u16 = 0;
+        let ret = unsafe {
+            ffi::doca_eth_rxq_get_flow_queue_id(self.engine().inner_ptr(), &mut id as *mut _)
+        };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+        Ok(id)
+    }
+}
+
+/// DOCA Ethernet transmit queue instance
+pub struct EthTxq {
+    inner: NonNull<ffi::doca_eth_txq>,
+
+    // The queue is created on a device,
+    // which should be closed after the queue is destroyed
+    #[allow(dead_code)]
+    dev: Arc<DevContext>,
+}
+
+unsafe impl Sync for EthTxq {}
+unsafe impl Send for EthTxq {}
+
+impl Drop for EthTxq {
+    fn drop(&mut self) {
+        let ret = unsafe { ffi::doca_eth_txq_destroy(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            panic!("Failed to destory eth txq!");
+        }
+
+        trace!(txq = ?self.inner, "Eth Txq is dropped");
+    }
+}
+
+/// Implementation `EngineToContext` Trait for Ethernet transmit queue
+impl EngineToContext for EthTxq {
+    unsafe fn to_ctx(&self) -> *mut ffi::doca_ctx {
+        ffi::doca_eth_txq_as_doca_ctx(self.inner_ptr())
+    }
+}
+
+impl EthTxq {
+    /// Create a DOCA Ethernet transmit queue on the device.
+    ///
+    /// At most `max_burst_size` send tasks can be in flight.
+    pub fn new(dev: &Arc<DevContext>, max_burst_size: u32) -> DOCAResult<Arc<Self>> {
+        let mut txq: *mut ffi::doca_eth_txq = std::ptr::null_mut();
+        let ret = unsafe {
+            ffi::doca_eth_txq_create(dev.inner_ptr(), max_burst_size, &mut txq as *mut _)
+        };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        let res = Arc::new(Self {
+            inner: unsafe { NonNull::new_unchecked(txq) },
+            dev: dev.clone(),
+        });
+
+        let ret =
+            unsafe { ffi::doca_eth_txq_set_type(res.inner_ptr(), ffi::DOCA_ETH_TXQ_TYPE_REGULAR) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(res)
+    }
+
+    /// Get the inner pointer of the DOCA Ethernet transmit queue.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_eth_txq {
+        self.inner.as_ptr()
+    }
+}
+
+/// Split a memory region, which is the memory range of the started mmap,
+/// into buffers of `packet_size` bytes.
+///
+/// The last buffer is dropped if it is shorter than `packet_size`.
+pub fn packet_buffers(
+    mmap: &Arc<DOCAMmap>,
+    region: RawPointer,
+    packet_size: usize,
+    inv: &Arc<BufferInventory>,
+) -> DOCAResult<Vec<DOCABuffer>> {
+    if packet_size == 0 {
+        return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+    }
+
+    (0..region.payload / packet_size)
+        .map(|i| {
+            let chunk = RawPointer {
+                inner: unsafe {
+                    NonNull::new_unchecked(region.inner.as_ptr().add(i * packet_size))
+                },
+                payload: packet_size,
+            };
+            // The chunk is already in the memory range of the mmap,
+            // so it is recorded without setting the memory range again
+            DOCARegisteredMemory::new_from_remote(mmap, chunk)?.to_buffer(inv)
+        })
+        .collect()
+}
+
+/// The metadata of a received packet
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PacketMeta {
+    /// The tag set by the DOCA Flow pipe which steered the packet
+    pub flow_tag: u32,
+    /// The RSS hash of the packet
+    pub rx_hash: u32,
+    /// Whether the L3 header and its checksum are valid
+    pub l3_ok: bool,
+    /// Whether the L4 header and its checksum are valid
+    pub l4_ok: bool,
+}
+
+/// What a receive or send task owns
+pub struct EthTaskState<T: EngineToContext> {
+    // Ensure that the context is stopped after all its tasks are freed
+    #[allow(dead_code)]
+    ctx: Arc<PeContext<T>>,
+    pkt: DOCABuffer,
+}
+
+impl RawTask for ffi::doca_eth_rxq_task_recv {
+    type State = EthTaskState<EthRxq>;
+
+    unsafe fn as_task(task: *mut Self) -> *mut ffi::doca_task {
+        ffi::doca_eth_rxq_task_recv_as_doca_task(task)
+    }
+}
+
+impl TaskCompletion for ffi::doca_eth_rxq_task_recv {
+    type Error = DOCAError;
+
+    fn callback(callbacks: &mut CtxCallbacks) -> &mut OnCompletion<EthRecvTask> {
+        &mut callbacks.eth_recv
+    }
+
+    fn error(status: DOCAError) -> DOCAError {
+        status
+    }
+}
+
+impl RawTask for ffi::doca_eth_txq_task_send {
+    type State = EthTaskState<EthTxq>;
+
+    unsafe fn as_task(task: *mut Self) -> *mut ffi::doca_task {
+        ffi::doca_eth_txq_task_send_as_doca_task(task)
+    }
+}
+
+impl TaskCompletion for ffi::doca_eth_txq_task_send {
+    type Error = DOCAError;
+
+    fn callback(callbacks: &mut CtxCallbacks) -> &mut OnCompletion<EthSendTask> {
+        &mut callbacks.eth_send
+    }
+
+    fn error(status: DOCAError) -> DOCAError {
+        status
+    }
+}
+
+/// A DOCA Ethernet receive task, receiving a packet into the buffer.
+pub type EthRecvTask = Task<ffi::doca_eth_rxq_task_recv>;
+
+impl EthRecvTask {
+    /// Allocate a receive task on the started context.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NO_MEMORY`: the number of tasks set by
+    ///  [`PeContextBuilder::eth_recv`] are all allocated.
+    ///  - `DOCA_ERROR_BAD_STATE`: the context is not running.
+    ///
+    pub fn new(ctx: &Arc<PeContext<EthRxq>>, pkt: DOCABuffer, user_data: u64) -> DOCAResult<Self> {
+        Self::try_alloc(ctx, pkt, user_data).map_err(|(e, _pkt)| e)
+    }
+
+    /// Allocate the task, or return the error and the packet buffer
+    fn try_alloc(
+        ctx: &Arc<PeContext<EthRxq>>,
+        pkt: DOCABuffer,
+        user_data: u64,
+    ) -> Result<Self, (DOCAError, DOCABuffer)> {
+        let state = EthTaskState {
+            ctx: ctx.clone(),
+            pkt,
+        };
+
+        unsafe {
+            Self::alloc(state, user_data, |state, task_user_data, task| {
+                ffi::doca_eth_rxq_task_recv_allocate_init(
+                    ctx.engine().inner_ptr(),
+                    state.pkt.inner_ptr(),
+                    task_user_data,
+                    task,
+                )
+            })
+        }
+        .map_err(|(e, state)| (e, state.pkt))
+    }
+
+    /// Get the metadata of the received packet.
+    pub fn meta(&self) -> DOCAResult<PacketMeta> {
+        let mut meta = PacketMeta::default();
+        let (mut l3_ok, mut l4_ok) = (0_u8, 0_u8);
+
+        let task = unsafe { self.inner_ptr() };
+        for ret in unsafe {
+            [
+                ffi::doca_eth_rxq_task_recv_get_flow_tag(task, &mut meta.flow_tag as *mut _),
+                ffi::doca_eth_rxq_task_recv_get_rx_hash(task, &mut meta.rx_hash as *mut _),
+                ffi::doca_eth_rxq_task_recv_get_l3_ok(task, &mut l3_ok as *mut _),
+                ffi::doca_eth_rxq_task_recv_get_l4_ok(task, &mut l4_ok as *mut _),
+            ]
+        } {
+            if ret != DOCAError::DOCA_SUCCESS {
+                return Err(ret);
+            }
+        }
+
+        meta.l3_ok = l3_ok != 0;
+        meta.l4_ok = l4_ok != 0;
+        Ok(meta)
+    }
+
+    /// Free the task and return its packet buffer.
+    pub fn into_buffer(self) -> DOCABuffer {
+        self.into_state().pkt
+    }
+
+    /// Get the packet buffer
+    #[inline]
+    pub fn pkt_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().pkt
+    }
+}
+
+/// A DOCA Ethernet send task, transmitting the packet in the data of the buffer.
+pub type EthSendTask = Task<ffi::doca_eth_txq_task_send>;
+
+impl EthSendTask {
+    /// Allocate a send task on the started context.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NO_MEMORY`: the number of tasks set by
+    ///  [`PeContextBuilder::eth_send`] are all allocated.
+    ///  - `DOCA_ERROR_BAD_STATE`: the context is not running.
+    ///
+    pub fn new(ctx: &Arc<PeContext<EthTxq>>, pkt: DOCABuffer, user_data: u64) -> DOCAResult<Self> {
+        Self::try_alloc(ctx, pkt, user_data).map_err(|(e, _pkt)| e)
+    }
+
+    /// Allocate the task, or return the error and the packet buffer
+    fn try_alloc(
+        ctx: &Arc<PeContext<EthTxq>>,
+        pkt: DOCABuffer,
+        user_data: u64,
+    ) -> Result<Self, (DOCAError, DOCABuffer)> {
+        let state = EthTaskState {
+            ctx: ctx.clone(),
+            pkt,
+        };
+
+        unsafe {
+            Self::alloc(state, user_data, |state, task_user_data, task| {
+                ffi::doca_eth_txq_task_send_allocate_init(
+                    ctx.engine().inner_ptr(),
+                    state.pkt.inner_ptr(),
+                    task_user_data,
+                    task,
+                )
+            })
+        }
+        .map_err(|(e, state)| (e, state.pkt))
+    }
+
+    /// Free the task and return its packet buffer.
+    pub fn into_buffer(self) -> DOCABuffer {
+        self.into_state().pkt
+    }
+
+    /// Get the packet buffer
+    #[inline]
+    pub fn pkt_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().pkt
+    }
+}
+
+impl PeContextBuilder<EthRxq> {
+    /// Enable the receive tasks on the context.
+    ///
+    /// At most `num_tasks` tasks can be allocated at the same time. `on_completion` is
+    /// called with every completed task and its result.
+    pub fn eth_recv<F>(&mut self, num_tasks: u32, on_completion: F) -> &mut Self
+    where
+        F: FnMut(EthRecvTask, DOCAResult<()>) + 'static,
+    {
+        self.callbacks.eth_recv = Some(Box::new(on_completion));
+        self.confs.push(Box::new(move |rxq: &EthRxq| {
+            let ret = unsafe {
+                ffi::doca_eth_rxq_task_recv_set_conf(
+                    rxq.inner_ptr(),
+                    Some(task::success_cb::<ffi::doca_eth_rxq_task_recv>),
+                    Some(task::error_cb::<ffi::doca_eth_rxq_task_recv>),
+                    num_tasks,
+                )
+            };
+
+            if ret != DOCAError::DOCA_SUCCESS {
+                return Err(ret);
+            }
+            Ok(())
+        }));
+        self
+    }
+}
+
+impl PeContextBuilder<EthTxq> {
+    /// Enable the send tasks on the context.
+    ///
+    /// At most `num_tasks` tasks can be allocated at the same time. `on_completion` is
+    /// called with every completed task and its result.
+    pub fn eth_send<F>(&mut self, num_tasks: u32, on_completion: F) -> &mut Self
+    where
+        F: FnMut(EthSendTask, DOCAResult<()>) + 'static,
+    {
+        self.callbacks.eth_send = Some(Box::new(on_completion));
+        self.confs.push(Box::new(move |txq: &EthTxq| {
+            let ret = unsafe {
+                ffi::doca_eth_txq_task_send_set_conf(
+                    txq.inner_ptr(),
+                    Some(task::success_cb::<ffi::doca_eth_txq_task_send>),
+                    Some(task::error_cb::<ffi::doca_eth_txq_task_send>),
+                    num_tasks,
+                )
+            };
+
+            if ret != DOCAError::DOCA_SUCCESS {
+                return Err(ret);
+            }
+            Ok(())
+        }));
+        self
+    }
+}
+
+/// A packet received by a [`RxQueue`]
+pub struct EthPacket {
+    /// The buffer of the packet, its data is the packet
+    pub buf: DOCABuffer,
+    /// The metadata of the packet, or the error of the receive task
+    pub meta: DOCAResult<PacketMeta>,
+    len: usize,
+}
+
+impl EthPacket {
+    /// Get the length of the packet, zero if the receive task fails
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return `true` if nothing is received into the buffer
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A started receive queue, receiving the packets into the posted buffers by bursts.
+///
+/// It is driven by the progress engine of the current thread, so it is not `Send`.
+pub struct RxQueue {
+    ctx: Arc<PeContext<EthRxq>>,
+    received: Rc<RefCell<VecDeque<EthPacket>>>,
+}
+
+impl RxQueue {
+    /// Start the receive queue on the progress engine,
+    /// with at most `num_tasks` buffers posted at the same time.
+    pub fn new(rxq: &Arc<EthRxq>, pe: &Arc<ProgressEngine>, num_tasks: u32) -> DOCAResult<Self> {
+        let received = Rc::new(RefCell::new(VecDeque::new()));
+        let received_cb = received.clone();
+        let ctx = PeContext::builder(rxq, pe)
+            .eth_recv(num_tasks, move |task, result| {
+                let meta = result.and_then(|_| task.meta());
+                let buf = task.into_buffer();
+                // the length is read once, so that a failure shows in the metadata
+                let (len, meta) = match (meta, buf.data_len()) {
+                    (Ok(meta), Ok(len)) => (len, Ok(meta)),
+                    (Ok(_), Err(e)) | (Err(e), _) => (0, Err(e)),
+                };
+                received_cb
+                    .borrow_mut()
+                    .push_back(EthPacket { buf, meta, len });
+            })
+            .start()?;
+
+        Ok(Self { ctx, received })
+    }
+
+    /// Post an empty buffer to receive a packet into.
+    /// If it fails, the error and the buffer are returned.
+    pub fn post(&self, buf: DOCABuffer) -> Result<(), (DOCAError, DOCABuffer)> {
+        EthRecvTask::try_alloc(&self.ctx, buf, 0)?
+            .submit()
+            .map_err(|(e, task)| (e, task.into_buffer()))
+    }
+
+    /// Receive at most `max` packets, running the progress engine until
+    /// no more completion is ready. Every posted buffer comes back in a packet,
+    /// even if the receive task fails.
+    pub fn recv_burst(&self, max: usize) -> Vec<EthPacket> {
+        let pe = self.ctx.pe();
+        while self.received.borrow().len() < max && pe.progress() {}
+
+        let mut received = self.received.borrow_mut();
+        let n = max.min(received.len());
+        received.drain(..n).collect()
+    }
+
+    /// Get the started context of the queue
+    #[inline]
+    pub fn ctx(&self) -> &Arc<PeContext<EthRxq>> {
+        &self.ctx
+    }
+}
+
+/// A started transmit queue, transmitting the packets by bursts.
+///
+/// It is driven by the progress engine of the current thread, so it is not `Send`.
+pub struct TxQueue {
+    ctx: Arc<PeContext<EthTxq>>,
+    completed: Rc<RefCell<VecDeque<(DOCABuffer, DOCAResult<()>)>>>,
+}
+
+impl TxQueue {
+    /// Start the transmit queue on the progress engine,
+    /// with at most `num_tasks` packets in flight at the same time.
+    pub fn new(txq: &Arc<EthTxq>, pe: &Arc<ProgressEngine>, num_tasks: u32) -> DOCAResult<Self> {
+        let completed = Rc::new(RefCell::new(VecDeque::new()));
+        let completed_cb = completed.clone();
+        let ctx = PeContext::builder(txq, pe)
+            .eth_send(num_tasks, move |task, result| {
+                completed_cb
+                    .borrow_mut()
+                    .push_back((task.into_buffer(), result));
+            })
+            .start()?;
+
+        Ok(Self { ctx, completed })
+    }
+
+    /// Transmit the packets in the data of the buffers.
+    ///
+    /// The buffers come back from [`TxQueue::reclaim_burst`] once transmitted.
+    /// If a packet can not be submitted, e.g. too many packets are in flight,
+    /// the error and the buffers which are not submitted are returned.
+    pub fn send_burst<I>(&self, bufs: I) -> Result<(), (DOCAError, Vec<DOCABuffer>)>
+    where
+        I: IntoIterator<Item = DOCABuffer>,
+    {
+        let mut bufs = bufs.into_iter();
+        for buf in bufs.by_ref() {
+            let ret = EthSendTask::try_alloc(&self.ctx, buf, 0)
+                .and_then(|task| task.submit().map_err(|(e, task)| (e, task.into_buffer())));
+
+            if let Err((e, buf)) = ret {
+                return Err((e, std::iter::once(buf).chain(bufs).collect()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Take back at most `max` transmitted buffers with the results of their
+    /// send tasks, running the progress engine until no more completion is ready.
+    pub fn reclaim_burst(&self, max: usize) -> Vec<(DOCABuffer, DOCAResult<()>)> {
+        let pe = self.ctx.pe();
+        while self.completed.borrow().len() < max && pe.progress() {}
+
+        let mut completed = self.completed.borrow_mut();
+        let n = max.min(completed.len());
+        completed.drain(..n).collect()
+    }
+
+    /// Get the started context of the queue
+    #[inline]
+    pub fn ctx(&self) -> &Arc<PeContext<EthTxq>> {
+        &self.ctx
+    }
+}
+
+mod tests {
+    #[test]
+    #[ignore = "needs a DOCA device supporting the Ethernet queues"]
+    fn test_eth_queues() {
+        use crate::device::DeviceCapability;
+        use crate::eth::*;
+        use crate::pe::ProgressEngine;
+        use crate::*;
+        use std::ptr::NonNull;
+
+        let dev = devices().unwrap().get(0).unwrap();
+        assert!(dev.supports(DeviceCapability::EthTxq).unwrap());
+        let device = dev.open().unwrap();
+        let pe = ProgressEngine::new().unwrap();
+
+        let (num_packets, packet_size) = (8, 128);
+        let mut memory = vec![0u8; 2 * num_packets * packet_size].into_boxed_slice();
+        let region = RawPointer {
+            inner: NonNull::new(memory.as_mut_ptr() as _).unwrap(),
+            payload: memory.len(),
+        };
+        let mut mmap = DOCAMmap::new().unwrap();
+        mmap.add_device(&device).unwrap();
+        mmap.set_memrange(region).unwrap();
+        mmap.start().unwrap();
+        let mmap = Arc::new(mmap);
+
+        let inv = BufferInventory::new(2 * num_packets).unwrap();
+        let mut bufs = packet_buffers(&mmap, region, packet_size, &inv).unwrap();
+        assert_eq!(bufs.len(), 2 * num_packets);
+        let rx_bufs = bufs.split_off(num_packets);
+
+        // the receive queue gets nothing until DOCA Flow steers packets to it
+        let rxq = EthRxq::new(&device, num_packets as u32, packet_size as u32).unwrap();
+        let rx = RxQueue::new(&rxq, &pe, num_packets as u32).unwrap();
+        rx.ctx().flow_queue_id().unwrap();
+        for buf in rx_bufs {
+            rx.post(buf).map_err(|(e, _buf)| e).unwrap();
+        }
+
+        // broadcast frames of the minimal length
+        let txq = EthTxq::new(&device, num_packets as u32).unwrap();
+        let tx = TxQueue::new(&txq, &pe, num_packets as u32).unwrap();
+        for buf in bufs.iter_mut() {
+            unsafe { buf.set_data(0, 64).unwrap() };
+        }
+        for packet in memory.chunks_mut(packet_size).take(num_packets) {
+            packet[..6].fill(0xff);
+        }
+        tx.send_burst(bufs).map_err(|(e, _bufs)| e).unwrap();
+
+        let mut sent = 0;
+        while sent < num_packets {
+            for (_buf, result) in tx.reclaim_burst(num_packets) {
+                assert_eq!(result, Ok(()));
+                sent += 1;
+            }
+        }
+    }
+}
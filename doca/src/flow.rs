@@ -0,0 +1,1092 @@
+//! Wrapper for DOCA Flow. It provides the ability of programming the steering
+//! rules of the ports of the device: the packets are matched by pipes, modified
+//! by actions and forwarded to a port, another pipe, or the receive queues.
+//!
+//! It basically contains the following core structs:
+//! - [`DOCAFlow`]: The initialized DOCA Flow library, created with [`FlowConfig`].
+//!
+//! - [`FlowPort`]: A started port of DOCA Flow.
+//!
+//! - [`FlowPipe`]: A pipe on a port, created with [`FlowPipeBuilder`] from the match
+//! template, the actions and the forwarding of its entries.
+//!
+//! - [`FlowEntry`]: A rule added to a pipe, which is removed with [`FlowEntry::remove`]
+//! or when dropped.
+//!
+//! The packet headers to match or to modify are described by [`FlowMatch`] and
+//! [`FlowActions`]. [`FlowMatchBuilder`] checks the fields are consistent,
+//! e.g. a port is only matched with a L4 protocol.
+//! As in DOCA Flow, a field set in the match of a pipe is either the same value for
+//! every entry, or, if all its bits are set, the value given by each entry.
+//!
+//! DOCA Flow runs on DPDK ports, so the application initializes the DPDK EAL and
+//! probes the ports before [`FlowConfig::init`].
+//! The library is wrapped with the configuration objects of DOCA 2.7, and requires
+//! the `flow` and `doca-2-x` features.
+//!
+//! # Examples
+//!
+//! ``` rust, no_run
+//! use doca::flow::{FlowConfig, FlowFwd, FlowMatch, FlowPipe, FlowPort, L4Proto};
+//!
+//! let device = doca::device::open_device_with_pci("03:00.0").unwrap();
+//! let flow = FlowConfig::new().pipe_queues(1).init().unwrap();
+//! let port = FlowPort::start(&flow, 0, &device).unwrap();
+//!
+//! // Forward the TCP packets to a port by their destination ports
+//! let template = FlowMatch::builder()
+//!     .l4(L4Proto::Tcp)
+//!     .dst_port(u16::MAX)
+//!     .build()
+//!     .unwrap();
+//! let pipe = FlowPipe::builder(&port, "tcp")
+//!     .root(true)
+//!     .match_on(template)
+//!     .fwd(FlowFwd::Port(1))
+//!     .build()
+//!     .unwrap();
+//!
+//! let http = FlowMatch::builder().l4(L4Proto::Tcp).dst_port(80).build().unwrap();
+//! let entry = pipe.add_entry(0, &http, None, None).unwrap();
+//!
+//! // The rule is removed once the entry is dropped, or explicitly to see the error
+//! entry.remove().unwrap();
+//! ```
+//!
+
+use std::ffi::{c_void, CString};
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::{DOCAError, DOCAResult, DevContext};
+
+/// DOCA Flow is a process-wide library, set while a [`DOCAFlow`] exists
+static FLOW_INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+/// The configuration of DOCA Flow, which initializes the library.
+pub struct FlowConfig {
+    pipe_queues: u16,
+    nr_counters: u32,
+    mode_args: String,
+}
+
+impl Default for FlowConfig {
+    fn default() -> Self {
+        Self {
+            pipe_queues: 1,
+            nr_counters: 0,
+            mode_args: "vnf,hws".to_string(),
+        }
+    }
+}
+
+impl FlowConfig {
+    /// Create the default configuration, with one queue and the `vnf,hws` mode
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of queues to add and remove the entries on
+    pub fn pipe_queues(&mut self, pipe_queues: u16) -> &mut Self {
+        self.pipe_queues = pipe_queues;
+        self
+    }
+
+    /// Set the number of counters
+    pub fn nr_counters(&mut self, nr_counters: u32) -> &mut Self {
+        self.nr_counters = nr_counters;
+        self
+    }
+
+    /// Set the mode arguments, e.g. `vnf,hws` or `switch,hws`
+    pub fn mode_args(&mut self, mode_args: &str) -> &mut Self {
+        self.mode_args = mode_args.to_string();
+        self
+    }
+
+    /// Initialize DOCA Flow. It can only be initialized once at the same time.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_IN_USE`: DOCA Flow is already initialized by a live [`DOCAFlow`].
+    ///  - Any error returned by the configuration or `doca_flow_init`.
+    ///
+    pub fn init(&self) -> DOCAResult<Arc<DOCAFlow>> {
+        if FLOW_INITIALIZED
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return Err(DOCAError::DOCA_ERROR_IN_USE);
+        }
+
+        let ret = self.init_flow();
+        if ret != DOCAError::DOCA_SUCCESS {
+            FLOW_INITIALIZED.store(false, Ordering::Release);
+            return Err(ret);
+        }
+
+        Ok(Arc::new(DOCAFlow {
+            pipe_queues: self.pipe_queues,
+            queue_locks: (0..self.pipe_queues).map(|_| Mutex::new(())).collect(),
+        }))
+    }
+
+    /// Configure and initialize the library
+    fn init_flow(&self) -> DOCAError {
+        let mode_args = match CString::new(self.mode_args.as_str()) {
+            Ok(mode_args) => mode_args,
+            Err(_) => return DOCAError::DOCA_ERROR_INVALID_VALUE,
+        };
+
+        let mut cfg: *mut ffi::doca_flow_cfg = std::ptr::null_mut();
+        let ret = unsafe { ffi::doca_flow_cfg_create(&mut cfg as *mut _) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            return ret;
+        }
+
+        unsafe {
+            let mut ret = ffi::doca_flow_cfg_set_pipe_queues(cfg, self.pipe_queues);
+            if ret == DOCAError::DOCA_SUCCESS {
+                ret = ffi::doca_flow_cfg_set_nr_counters(cfg, self.nr_counters);
+            }
+            if ret == DOCAError::DOCA_SUCCESS {
+                ret = ffi::doca_flow_cfg_set_mode_args(cfg, mode_args.as_ptr());
+            }
+            if ret == DOCAError::DOCA_SUCCESS {
+                ret = ffi::doca_flow_init(cfg);
+            }
+            ffi::doca_flow_cfg_destroy(cfg);
+            ret
+        }
+    }
+}
+
+/// The initialized DOCA Flow library, which is destroyed after all its ports are stopped
+pub struct DOCAFlow {
+    pipe_queues: u16,
+    // DOCA Flow does not lock the queues, each one must be used by one thread at a time
+    queue_locks: Vec<Mutex<()>>,
+}
+
+impl Drop for DOCAFlow {
+    fn drop(&mut self) {
+        unsafe { ffi::doca_flow_destroy() };
+        FLOW_INITIALIZED.store(false, Ordering::Release);
+
+        trace!("DOCA Flow is dropped");
+    }
+}
+
+impl DOCAFlow {
+    /// Get the number of queues
+    #[inline]
+    pub fn pipe_queues(&self) -> u16 {
+        self.pipe_queues
+    }
+
+    /// Lock the queue, held while its entries are added, removed or processed
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the queue does not exist.
+    ///
+    fn lock_queue(&self, pipe_queue: u16) -> DOCAResult<MutexGuard<'_, ()>> {
+        let lock = self
+            .queue_locks
+            .get(pipe_queue as usize)
+            .ok_or(DOCAError::DOCA_ERROR_INVALID_VALUE)?;
+        Ok(lock.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+/// A started port of DOCA Flow
+pub struct FlowPort {
+    inner: NonNull<ffi::doca_flow_port>,
+    port_id: u16,
+
+    // The port is stopped before the library is destroyed and the device is closed
+    flow: Arc<DOCAFlow>,
+    #[allow(dead_code)]
+    dev: Arc<DevContext>,
+}
+
+// SAFETY: the only call made on the port through `&self` which is not thread-safe
+// in DOCA Flow is processing the entries of a queue, which holds the lock of the queue.
+unsafe impl Sync for FlowPort {}
+// SAFETY: the port is not bound to the thread that started it.
+unsafe impl Send for FlowPort {}
+
+impl Drop for FlowPort {
+    fn drop(&mut self) {
+        let ret = unsafe { ffi::doca_flow_port_stop(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            panic!("Failed to stop the flow port: {:?}", ret);
+        }
+
+        trace!(port = ?self.inner, "Flow Port is dropped");
+    }
+}
+
+impl FlowPort {
+    /// Start the DPDK port `port_id` of the device in DOCA Flow.
+    pub fn start(
+        flow: &Arc<DOCAFlow>,
+        port_id: u16,
+        dev: &Arc<DevContext>,
+    ) -> DOCAResult<Arc<Self>> {
+        let devargs = CString::new(port_id.to_string()).unwrap();
+
+        let mut cfg: *mut ffi::doca_flow_port_cfg = std::ptr::null_mut();
+        let ret = unsafe { ffi::doca_flow_port_cfg_create(&mut cfg as *mut _) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        let mut port: *mut ffi::doca_flow_port = std::ptr::null_mut();
+        let ret = unsafe {
+            let mut ret = ffi::doca_flow_port_cfg_set_devargs(cfg, devargs.as_ptr());
+            if ret == DOCAError::DOCA_SUCCESS {
+                ret = ffi::doca_flow_port_cfg_set_dev(cfg, dev.inner_ptr());
+            }
+            if ret == DOCAError::DOCA_SUCCESS {
+                ret = ffi::doca_flow_port_start(cfg, &mut port as *mut _);
+            }
+            ffi::doca_flow_port_cfg_destroy(cfg);
+            ret
+        };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(Arc::new(Self {
+            inner: unsafe { NonNull::new_unchecked(port) },
+            port_id,
+            flow: flow.clone(),
+            dev: dev.clone(),
+        }))
+    }
+
+    /// Pair the port with another one, e.g. the representor of a host port.
+    pub fn pair(&self, other: &FlowPort) -> DOCAResult<()> {
+        let ret = unsafe { ffi::doca_flow_port_pair(self.inner_ptr(), other.inner_ptr()) };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+        Ok(())
+    }
+
+    /// Push the entries added or removed on the queue to the hardware,
+    /// waiting at most `timeout_us` microseconds.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the queue does not exist.
+    ///  - Any error returned by `doca_flow_entries_process`.
+    ///
+    pub fn process_entries(&self, pipe_queue: u16, timeout_us: u64) -> DOCAResult<()> {
+        let _queue = self.flow.lock_queue(pipe_queue)?;
+        self.process_queue(pipe_queue, timeout_us)
+    }
+
+    /// Push the entries of the queue, whose lock is held by the caller
+    fn process_queue(&self, pipe_queue: u16, timeout_us: u64) -> DOCAResult<()> {
+        let ret =
+            unsafe { ffi::doca_flow_entries_process(self.inner_ptr(), pipe_queue, timeout_us, 0) };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+        Ok(())
+    }
+
+    /// Get the DPDK port id of the port
+    #[inline]
+    pub fn port_id(&self) -> u16 {
+        self.port_id
+    }
+
+    /// Get the inner pointer of the DOCA Flow port.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_flow_port {
+        self.inner.as_ptr()
+    }
+}
+
+/// The L4 protocols
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum L4Proto {
+    /// TCP
+    Tcp,
+    /// UDP
+    Udp,
+}
+
+/// The L3 header of a match or of the actions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum L3Header {
+    V4 {
+        src: Option<Ipv4Addr>,
+        dst: Option<Ipv4Addr>,
+    },
+    V6 {
+        src: Option<Ipv6Addr>,
+        dst: Option<Ipv6Addr>,
+    },
+}
+
+/// The outer headers of a match or of the actions, only the fields set are written
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Headers {
+    src_mac: Option<[u8; 6]>,
+    dst_mac: Option<[u8; 6]>,
+    eth_type: Option<u16>,
+    l3: Option<L3Header>,
+    l4: Option<L4Proto>,
+    src_port: Option<u16>,
+    dst_port: Option<u16>,
+}
+
+impl Headers {
+    /// Write the fields into the DOCA header, in network byte order.
+    ///
+    /// The anonymous unions of the header are reached through the names given by
+    /// bindgen: the first one holds the L3 header, and the second one the L4 header.
+    fn write(&self, hdr: &mut ffi::doca_flow_header_format) {
+        if let Some(mac) = self.src_mac {
+            hdr.eth.src_mac = mac;
+        }
+        if let Some(mac) = self.dst_mac {
+            hdr.eth.dst_mac = mac;
+        }
+        if let Some(eth_type) = self.eth_type {
+            hdr.eth.type_ = eth_type.to_be();
+        }
+
+        match self.l3 {
+            Some(L3Header::V4 { src, dst }) => {
+                hdr.l3_type = ffi::DOCA_FLOW_L3_TYPE_IP4;
+                let ip4 = unsafe { &mut hdr.__bindgen_anon_1.ip4 };
+                if let Some(addr) = src {
+                    ip4.src_ip = u32::from_ne_bytes(addr.octets());
+                }
+                if let Some(addr) = dst {
+                    ip4.dst_ip = u32::from_ne_bytes(addr.octets());
+                }
+            }
+            Some(L3Header::V6 { src, dst }) => {
+                hdr.l3_type = ffi::DOCA_FLOW_L3_TYPE_IP6;
+                let ip6 = unsafe { &mut hdr.__bindgen_anon_1.ip6 };
+                if let Some(addr) = src {
+                    ip6.src_ip = ipv6_words(addr);
+                }
+                if let Some(addr) = dst {
+                    ip6.dst_ip = ipv6_words(addr);
+                }
+            }
+            None => {}
+        }
+
+        if let Some(proto) = self.l4 {
+            hdr.l4_type_ext = match proto {
+                L4Proto::Tcp => ffi::DOCA_FLOW_L4_TYPE_EXT_TCP,
+                L4Proto::Udp => ffi::DOCA_FLOW_L4_TYPE_EXT_UDP,
+            };
+            let transport = unsafe { &mut hdr.__bindgen_anon_2.transport };
+            if let Some(port) = self.src_port {
+                transport.src_port = port.to_be();
+            }
+            if let Some(port) = self.dst_port {
+                transport.dst_port = port.to_be();
+            }
+        }
+    }
+}
+
+/// The words of an IPv6 address, keeping the network byte order in memory
+#[inline]
+fn ipv6_words(addr: Ipv6Addr) -> [u32; 4] {
+    let octets = addr.octets();
+    let mut words = [0u32; 4];
+    for (word, chunk) in words.iter_mut().zip(octets.chunks_exact(4)) {
+        *word = u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    words
+}
+
+/// Errors of the fields of a [`FlowMatch`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlowMatchError {
+    /// A port is matched without a L4 protocol
+    PortWithoutL4,
+    /// Both IPv4 and IPv6 fields are matched
+    ConflictingL3,
+    /// The ether type is not the one of the matched IP version
+    EthTypeMismatch(u16),
+}
+
+impl fmt::Display for FlowMatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlowMatchError::PortWithoutL4 => write!(f, "a port is matched without a L4 protocol"),
+            FlowMatchError::ConflictingL3 => write!(f, "both IPv4 and IPv6 fields are matched"),
+            FlowMatchError::EthTypeMismatch(eth_type) => {
+                write!(
+                    f,
+                    "ether type {:#06x} does not match the IP version",
+                    eth_type
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for FlowMatchError {}
+
+const ETH_TYPE_IPV4: u16 = 0x0800;
+const ETH_TYPE_IPV6: u16 = 0x86dd;
+
+/// The packet fields matched by a pipe or an entry, built by [`FlowMatchBuilder`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FlowMatch {
+    outer: Headers,
+}
+
+impl FlowMatch {
+    /// Create a builder of the match
+    pub fn builder() -> FlowMatchBuilder {
+        FlowMatchBuilder::default()
+    }
+
+    /// Get the DOCA match
+    pub(crate) fn to_raw(&self) -> ffi::doca_flow_match {
+        let mut raw = ffi::doca_flow_match::default();
+        self.outer.write(&mut raw.outer);
+        raw
+    }
+}
+
+/// Collect the fields of a [`FlowMatch`], and check they are consistent.
+#[derive(Clone, Debug, Default)]
+pub struct FlowMatchBuilder {
+    src_mac: Option<[u8; 6]>,
+    dst_mac: Option<[u8; 6]>,
+    eth_type: Option<u16>,
+    ipv4: bool,
+    ipv6: bool,
+    ipv4_src: Option<Ipv4Addr>,
+    ipv4_dst: Option<Ipv4Addr>,
+    ipv6_src: Option<Ipv6Addr>,
+    ipv6_dst: Option<Ipv6Addr>,
+    l4: Option<L4Proto>,
+    src_port: Option<u16>,
+    dst_port: Option<u16>,
+}
+
+impl FlowMatchBuilder {
+    /// Match the source MAC address
+    pub fn src_mac(&mut self, mac: [u8; 6]) -> &mut Self {
+        self.src_mac = Some(mac);
+        self
+    }
+
+    /// Match the destination MAC address
+    pub fn dst_mac(&mut self, mac: [u8; 6]) -> &mut Self {
+        self.dst_mac = Some(mac);
+        self
+    }
+
+    /// Match the ether type
+    pub fn eth_type(&mut self, eth_type: u16) -> &mut Self {
+        self.eth_type = Some(eth_type);
+        self
+    }
+
+    /// Match the IPv4 packets
+    pub fn ipv4(&mut self) -> &mut Self {
+        self.ipv4 = true;
+        self
+    }
+
+    /// Match the IPv6 packets
+    pub fn ipv6(&mut self) -> &mut Self {
+        self.ipv6 = true;
+        self
+    }
+
+    /// Match the IPv4 source address
+    pub fn ipv4_src(&mut self, addr: Ipv4Addr) -> &mut Self {
+        self.ipv4_src = Some(addr);
+        self
+    }
+
+    /// Match the IPv4 destination address
+    pub fn ipv4_dst(&mut self, addr: Ipv4Addr) -> &mut Self {
+        self.ipv4_dst = Some(addr);
+        self
+    }
+
+    /// Match the IPv6 source address
+    pub fn ipv6_src(&mut self, addr: Ipv6Addr) -> &mut Self {
+        self.ipv6_src = Some(addr);
+        self
+    }
+
+    /// Match the IPv6 destination address
+    pub fn ipv6_dst(&mut self, addr: Ipv6Addr) -> &mut Self {
+        self.ipv6_dst = Some(addr);
+        self
+    }
+
+    /// Match the L4 protocol. It implies IPv4 if no IP version is matched.
+    pub fn l4(&mut self, proto: L4Proto) -> &mut Self {
+        self.l4 = Some(proto);
+        self
+    }
+
+    /// Match the L4 source port
+    pub fn src_port(&mut self, port: u16) -> &mut Self {
+        self.src_port = Some(port);
+        self
+    }
+
+    /// Match the L4 destination port
+    pub fn dst_port(&mut self, port: u16) -> &mut Self {
+        self.dst_port = Some(port);
+        self
+    }
+
+    /// Check the fields and build the match.
+    pub fn build(&self) -> Result<FlowMatch, FlowMatchError> {
+        let v4 = self.ipv4 || self.ipv4_src.is_some() || self.ipv4_dst.is_some();
+        let v6 = self.ipv6 || self.ipv6_src.is_some() || self.ipv6_dst.is_some();
+        if v4 && v6 {
+            return Err(FlowMatchError::ConflictingL3);
+        }
+
+        if (self.src_port.is_some() || self.dst_port.is_some()) && self.l4.is_none() {
+            return Err(FlowMatchError::PortWithoutL4);
+        }
+
+        // DOCA Flow parses the L4 header after the L3 one,
+        // so a L4 protocol alone is matched over IPv4
+        let l3 = if v6 {
+            Some(L3Header::V6 {
+                src: self.ipv6_src,
+                dst: self.ipv6_dst,
+            })
+        } else if v4 || self.l4.is_some() {
+            Some(L3Header::V4 {
+                src: self.ipv4_src,
+                dst: self.ipv4_dst,
+            })
+        } else {
+            None
+        };
+
+        match (self.eth_type, l3) {
+            (Some(t), Some(L3Header::V4 { .. })) if t != ETH_TYPE_IPV4 => {
+                return Err(FlowMatchError::EthTypeMismatch(t))
+            }
+            (Some(t), Some(L3Header::V6 { .. })) if t != ETH_TYPE_IPV6 => {
+                return Err(FlowMatchError::EthTypeMismatch(t))
+            }
+            _ => {}
+        }
+
+        Ok(FlowMatch {
+            outer: Headers {
+                src_mac: self.src_mac,
+                dst_mac: self.dst_mac,
+                eth_type: self.eth_type,
+                l3,
+                l4: self.l4,
+                src_port: self.src_port,
+                dst_port: self.dst_port,
+            },
+        })
+    }
+}
+
+/// The modifications of the packet headers by a pipe or an entry
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FlowActions {
+    outer: Headers,
+}
+
+impl FlowActions {
+    /// Create the actions, which modify nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the source MAC address
+    pub fn set_src_mac(&mut self, mac: [u8; 6]) -> &mut Self {
+        self.outer.src_mac = Some(mac);
+        self
+    }
+
+    /// Set the destination MAC address
+    pub fn set_dst_mac(&mut self, mac: [u8; 6]) -> &mut Self {
+        self.outer.dst_mac = Some(mac);
+        self
+    }
+
+    /// Set the IPv4 source and destination addresses, which are kept if `None`
+    pub fn set_ipv4(&mut self, src: Option<Ipv4Addr>, dst: Option<Ipv4Addr>) -> &mut Self {
+        self.outer.l3 = Some(L3Header::V4 { src, dst });
+        self
+    }
+
+    /// Set the L4 source and destination ports of the protocol, which are kept if `None`
+    pub fn set_ports(&mut self, proto: L4Proto, src: Option<u16>, dst: Option<u16>) -> &mut Self {
+        self.outer.l4 = Some(proto);
+        self.outer.src_port = src;
+        self.outer.dst_port = dst;
+        self
+    }
+
+    /// Get the DOCA actions
+    pub(crate) fn to_raw(&self) -> ffi::doca_flow_actions {
+        let mut raw = ffi::doca_flow_actions::default();
+        self.outer.write(&mut raw.outer);
+        raw
+    }
+}
+
+/// Where the matched packets go
+#[derive(Clone)]
+pub enum FlowFwd {
+    /// Forward to the port
+    Port(u16),
+    /// Forward to the next pipe on the same port
+    Pipe(Arc<FlowPipe>),
+    /// Spread the packets over the receive queues, e.g. of [`crate::eth`],
+    /// by the hash of their IP addresses and ports
+    Rss(Vec<u16>),
+    /// Drop the packets
+    Drop,
+}
+
+impl FlowFwd {
+    /// Get the DOCA forwarding, which points into `self`.
+    ///
+    /// As for the headers, the anonymous unions are reached through the names given
+    /// by bindgen: the first one holds RSS, the second one the port and the third one
+    /// the next pipe.
+    fn to_raw(&self) -> ffi::doca_flow_fwd {
+        let mut raw = ffi::doca_flow_fwd::default();
+        match self {
+            FlowFwd::Port(port_id) => {
+                raw.type_ = ffi::DOCA_FLOW_FWD_PORT;
+                unsafe { raw.__bindgen_anon_1.__bindgen_anon_2.port_id = *port_id };
+            }
+            FlowFwd::Pipe(pipe) => {
+                raw.type_ = ffi::DOCA_FLOW_FWD_PIPE;
+                unsafe { raw.__bindgen_anon_1.__bindgen_anon_3.next_pipe = pipe.inner_ptr() };
+            }
+            FlowFwd::Rss(queues) => {
+                raw.type_ = ffi::DOCA_FLOW_FWD_RSS;
+                let rss = unsafe { &mut raw.__bindgen_anon_1.__bindgen_anon_1 };
+                rss.rss_type = ffi::DOCA_FLOW_RESOURCE_TYPE_NON_SHARED;
+                let cfg = unsafe { &mut rss.__bindgen_anon_1.rss };
+                cfg.outer_flags = ffi::DOCA_FLOW_RSS_IPV4
+                    | ffi::DOCA_FLOW_RSS_IPV6
+                    | ffi::DOCA_FLOW_RSS_TCP
+                    | ffi::DOCA_FLOW_RSS_UDP;
+                // DOCA only reads the queues
+                cfg.queues_array = queues.as_ptr() as *mut u16;
+                cfg.nr_queues = queues.len() as i32;
+            }
+            FlowFwd::Drop => raw.type_ = ffi::DOCA_FLOW_FWD_DROP,
+        }
+        raw
+    }
+
+    /// Get the next pipe, which must outlive the pipes and entries forwarding to it
+    fn next_pipe(&self) -> Option<Arc<FlowPipe>> {
+        match self {
+            FlowFwd::Pipe(pipe) => Some(pipe.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// A pipe of DOCA Flow, destroyed when dropped
+pub struct FlowPipe {
+    inner: NonNull<ffi::doca_flow_pipe>,
+
+    // The pipe is destroyed before the port is stopped and the pipes it forwards to
+    port: Arc<FlowPort>,
+    #[allow(dead_code)]
+    next_pipes: Vec<Arc<FlowPipe>>,
+}
+
+// SAFETY: the entries are added through `&self` on a queue of DOCA Flow, which is
+// used by one thread at a time: `add_entry` holds the lock of the queue.
+// The pipe is only destroyed when dropped.
+unsafe impl Sync for FlowPipe {}
+// SAFETY: the pipe is not bound to the thread that created it.
+unsafe impl Send for FlowPipe {}
+
+impl Drop for FlowPipe {
+    fn drop(&mut self) {
+        unsafe { ffi::doca_flow_pipe_destroy(self.inner_ptr()) };
+
+        trace!(pipe = ?self.inner, "Flow Pipe is dropped");
+    }
+}
+
+impl FlowPipe {
+    /// Create a builder of a pipe on the port
+    pub fn builder(port: &Arc<FlowPort>, name: &str) -> FlowPipeBuilder {
+        FlowPipeBuilder {
+            port: port.clone(),
+            name: name.to_string(),
+            root: false,
+            nr_entries: None,
+            match_on: None,
+            match_mask: None,
+            actions: None,
+            fwd: None,
+            fwd_miss: None,
+        }
+    }
+
+    /// Add an entry to the pipe on the queue, and push it to the hardware.
+    ///
+    /// The fields of the pipe match which are given by the entries are read from `m`.
+    /// The actions and the forwarding of the pipe are used if `None`.
+    pub fn add_entry(
+        self: &Arc<Self>,
+        pipe_queue: u16,
+        m: &FlowMatch,
+        actions: Option<&FlowActions>,
+        fwd: Option<&FlowFwd>,
+    ) -> DOCAResult<FlowEntry> {
+        let _queue = self.port.flow.lock_queue(pipe_queue)?;
+
+        let raw_match = m.to_raw();
+        let raw_actions = actions.map(|a| a.to_raw());
+        let raw_fwd = fwd.map(|f| f.to_raw());
+
+        let mut entry: *mut ffi::doca_flow_pipe_entry = std::ptr::null_mut();
+        let ret = unsafe {
+            ffi::doca_flow_pipe_add_entry(
+                pipe_queue,
+                self.inner_ptr(),
+                &raw_match as *const _,
+                raw_actions
+                    .as_ref()
+                    .map_or(std::ptr::null(), |a| a as *const _),
+                std::ptr::null(),
+                raw_fwd.as_ref().map_or(std::ptr::null(), |f| f as *const _),
+                ffi::DOCA_FLOW_NO_WAIT,
+                std::ptr::null_mut::<c_void>(),
+                &mut entry as *mut _,
+            )
+        };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        if let Err(ret) = self.port.process_queue(pipe_queue, 0) {
+            // the entry is not handed out, so take it back from the queue
+            unsafe { ffi::doca_flow_pipe_remove_entry(pipe_queue, ffi::DOCA_FLOW_NO_WAIT, entry) };
+            return Err(ret);
+        }
+
+        Ok(FlowEntry {
+            inner: unsafe { NonNull::new_unchecked(entry) },
+            pipe_queue,
+            removed: false,
+            pipe: self.clone(),
+            next_pipe: fwd.and_then(FlowFwd::next_pipe),
+        })
+    }
+
+    /// Get the port of the pipe
+    #[inline]
+    pub fn port(&self) -> &Arc<FlowPort> {
+        &self.port
+    }
+
+    /// Get the inner pointer of the DOCA Flow pipe.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_flow_pipe {
+        self.inner.as_ptr()
+    }
+}
+
+/// Collect the configuration of a [`FlowPipe`], then create it.
+pub struct FlowPipeBuilder {
+    port: Arc<FlowPort>,
+    name: String,
+    root: bool,
+    nr_entries: Option<u32>,
+    match_on: Option<FlowMatch>,
+    match_mask: Option<FlowMatch>,
+    actions: Option<FlowActions>,
+    fwd: Option<FlowFwd>,
+    fwd_miss: Option<FlowFwd>,
+}
+
+impl FlowPipeBuilder {
+    /// Set whether the pipe is the first one the packets of the port go through
+    pub fn root(&mut self, root: bool) -> &mut Self {
+        self.root = root;
+        self
+    }
+
+    /// Set the maximum number of entries
+    pub fn nr_entries(&mut self, nr_entries: u32) -> &mut Self {
+        self.nr_entries = Some(nr_entries);
+        self
+    }
+
+    /// Set the match template of the pipe
+    pub fn match_on(&mut self, m: FlowMatch) -> &mut Self {
+        self.match_on = Some(m);
+        self
+    }
+
+    /// Set the mask of the match template, the bits of the fields which are compared
+    pub fn match_mask(&mut self, mask: FlowMatch) -> &mut Self {
+        self.match_mask = Some(mask);
+        self
+    }
+
+    /// Set the actions template of the pipe
+    pub fn actions(&mut self, actions: FlowActions) -> &mut Self {
+        self.actions = Some(actions);
+        self
+    }
+
+    /// Set where the matched packets go
+    pub fn fwd(&mut self, fwd: FlowFwd) -> &mut Self {
+        self.fwd = Some(fwd);
+        self
+    }
+
+    /// Set where the packets which match no entry go
+    pub fn fwd_miss(&mut self, fwd: FlowFwd) -> &mut Self {
+        self.fwd_miss = Some(fwd);
+        self
+    }
+
+    /// Create the pipe.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the name contains a nul byte, or a forwarded
+    ///  pipe is on another port.
+    ///  - Any error returned by `doca_flow_pipe_create`.
+    ///
+    pub fn build(&mut self) -> DOCAResult<Arc<FlowPipe>> {
+        let name =
+            CString::new(self.name.as_str()).map_err(|_| DOCAError::DOCA_ERROR_INVALID_VALUE)?;
+        let next_pipes: Vec<Arc<FlowPipe>> = [&self.fwd, &self.fwd_miss]
+            .into_iter()
+            .filter_map(|fwd| fwd.as_ref().and_then(FlowFwd::next_pipe))
+            .collect();
+        if next_pipes.iter().any(|p| !Arc::ptr_eq(&p.port, &self.port)) {
+            return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+        }
+
+        let raw_match = self.match_on.unwrap_or_default().to_raw();
+        let raw_mask = self.match_mask.map(|m| m.to_raw());
+        let mut raw_actions = self.actions.map(|a| a.to_raw());
+        let raw_fwd = self.fwd.as_ref().map(FlowFwd::to_raw);
+        let raw_fwd_miss = self.fwd_miss.as_ref().map(FlowFwd::to_raw);
+
+        let mut cfg: *mut ffi::doca_flow_pipe_cfg = std::ptr::null_mut();
+        let ret =
+            unsafe { ffi::doca_flow_pipe_cfg_create(&mut cfg as *mut _, self.port.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        let mut pipe: *mut ffi::doca_flow_pipe = std::ptr::null_mut();
+        let ret = unsafe {
+            let mut ret = ffi::doca_flow_pipe_cfg_set_name(cfg, name.as_ptr());
+            if ret == DOCAError::DOCA_SUCCESS {
+                ret = ffi::doca_flow_pipe_cfg_set_type(cfg, ffi::DOCA_FLOW_PIPE_BASIC);
+            }
+            if ret == DOCAError::DOCA_SUCCESS {
+                ret = ffi::doca_flow_pipe_cfg_set_is_root(cfg, self.root);
+            }
+            if let (Some(nr_entries), DOCAError::DOCA_SUCCESS) = (self.nr_entries, ret) {
+                ret = ffi::doca_flow_pipe_cfg_set_nr_entries(cfg, nr_entries);
+            }
+            if ret == DOCAError::DOCA_SUCCESS {
+                ret = ffi::doca_flow_pipe_cfg_set_match(
+                    cfg,
+                    &raw_match as *const _,
+                    raw_mask
+                        .as_ref()
+                        .map_or(std::ptr::null(), |m| m as *const _),
+                );
+            }
+            if let (Some(actions), DOCAError::DOCA_SUCCESS) = (raw_actions.as_mut(), ret) {
+                let mut actions_arr = [actions as *mut ffi::doca_flow_actions];
+                ret = ffi::doca_flow_pipe_cfg_set_actions(
+                    cfg,
+                    actions_arr.as_mut_ptr(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    1,
+                );
+            }
+            if ret == DOCAError::DOCA_SUCCESS {
+                ret = ffi::doca_flow_pipe_create(
+                    cfg,
+                    raw_fwd.as_ref().map_or(std::ptr::null(), |f| f as *const _),
+                    raw_fwd_miss
+                        .as_ref()
+                        .map_or(std::ptr::null(), |f| f as *const _),
+                    &mut pipe as *mut _,
+                );
+            }
+            ffi::doca_flow_pipe_cfg_destroy(cfg);
+            ret
+        };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(Arc::new(FlowPipe {
+            inner: unsafe { NonNull::new_unchecked(pipe) },
+            port: self.port.clone(),
+            next_pipes,
+        }))
+    }
+}
+
+/// An entry of a pipe, removed from the hardware when dropped
+///
+/// A removal failing on drop is only reported as a `warn` event with the `tracing`
+/// feature, use [`FlowEntry::remove`] to handle the error.
+pub struct FlowEntry {
+    inner: NonNull<ffi::doca_flow_pipe_entry>,
+    pipe_queue: u16,
+    removed: bool,
+
+    // The entry is removed before the pipe and the pipe it forwards to are destroyed
+    pipe: Arc<FlowPipe>,
+    #[allow(dead_code)]
+    next_pipe: Option<Arc<FlowPipe>>,
+}
+
+// SAFETY: the entry has no method changing it through `&self`, it is only removed
+// by `remove` or when dropped, which hold the lock of its queue.
+unsafe impl Sync for FlowEntry {}
+// SAFETY: the entry is not bound to the thread that added it, it is removed on
+// its queue whichever thread drops it.
+unsafe impl Send for FlowEntry {}
+
+impl Drop for FlowEntry {
+    fn drop(&mut self) {
+        if self.removed {
+            return;
+        }
+
+        // the error can not be returned from drop, use `FlowEntry::remove` to handle it
+        if let Err(_ret) = self.remove_entry() {
+            warn!(entry = ?self.inner, error = ?_ret, "Failed to remove the flow entry");
+        }
+    }
+}
+
+impl FlowEntry {
+    /// Remove the entry from the hardware, and return the error if it fails.
+    pub fn remove(mut self) -> DOCAResult<()> {
+        self.removed = true;
+        self.remove_entry()
+    }
+
+    /// Remove the entry and push the removal to the hardware
+    fn remove_entry(&self) -> DOCAResult<()> {
+        let _queue = self.pipe.port.flow.lock_queue(self.pipe_queue)?;
+        let ret = unsafe {
+            ffi::doca_flow_pipe_remove_entry(
+                self.pipe_queue,
+                ffi::DOCA_FLOW_NO_WAIT,
+                self.inner_ptr(),
+            )
+        };
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        self.pipe.port.process_queue(self.pipe_queue, 0)
+    }
+
+    /// Get the pipe of the entry
+    #[inline]
+    pub fn pipe(&self) -> &Arc<FlowPipe> {
+        &self.pipe
+    }
+
+    /// Get the inner pointer of the DOCA Flow entry.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_flow_pipe_entry {
+        self.inner.as_ptr()
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_flow_match_builder() {
+        use crate::flow::*;
+
+        assert_eq!(
+            FlowMatch::builder().dst_port(80).build(),
+            Err(FlowMatchError::PortWithoutL4)
+        );
+        assert_eq!(
+            FlowMatch::builder()
+                .ipv4_src(Ipv4Addr::LOCALHOST)
+                .ipv6_dst(Ipv6Addr::LOCALHOST)
+                .build(),
+            Err(FlowMatchError::ConflictingL3)
+        );
+        assert_eq!(
+            FlowMatch::builder().eth_type(0x86dd).ipv4().build(),
+            Err(FlowMatchError::EthTypeMismatch(0x86dd))
+        );
+
+        let m = FlowMatch::builder()
+            .eth_type(0x0800)
+            .ipv4_dst(Ipv4Addr::new(10, 0, 0, 1))
+            .l4(L4Proto::Udp)
+            .dst_port(4791)
+            .build()
+            .unwrap();
+        let raw = m.to_raw();
+        assert_eq!(raw.outer.eth.type_, 0x0800u16.to_be());
+        assert_eq!(raw.outer.l3_type, ffi::DOCA_FLOW_L3_TYPE_IP4);
+        assert_eq!(raw.outer.l4_type_ext, ffi::DOCA_FLOW_L4_TYPE_EXT_UDP);
+        unsafe {
+            assert_eq!(
+                raw.outer.__bindgen_anon_1.ip4.dst_ip.to_ne_bytes(),
+                [10, 0, 0, 1]
+            );
+            assert_eq!(
+                raw.outer.__bindgen_anon_2.transport.dst_port,
+                4791u16.to_be()
+            );
+            assert_eq!(raw.outer.__bindgen_anon_2.transport.src_port, 0);
+        }
+
+        // a L4 protocol alone is matched over IPv4
+        let raw = FlowMatch::builder()
+            .l4(L4Proto::Tcp)
+            .build()
+            .unwrap()
+            .to_raw();
+        assert_eq!(raw.outer.l3_type, ffi::DOCA_FLOW_L3_TYPE_IP4);
+    }
+}
@@ -9,7 +9,11 @@
 //! - [`DOCAMmap`] should be dropped before the [`DevContext`] registered into it
 //!
 //! - The [`context`] module contains wrapper of the execution
-//! model in DOCA, including a submodule [`work_queue`].
+//! model in DOCA 1.5, including a submodule [`work_queue`].
+//!
+//! - The [`pe`] module contains wrapper of the execution model in DOCA 2.x,
+//! the progress engine and the tasks. The DOCA release is selected by the
+//! `doca-1-5` (default) and `doca-2-x` features.
 //!
 //! - The [`device`] module provides wrapper for
 //! managing DOCA devices.
@@ -19,7 +23,52 @@
 //!
 //! - The [`dma`] module provides wrapper for DOCA DMA engine,
 //! which provides the ability to copy data between memory
-//! using hardware acceleration.
+//! using hardware acceleration. It requires the `dma` feature.
+//!
+//! - The [`runtime`] module checks whether DOCA is available,
+//! which matters when the libraries are loaded at runtime
+//! with the `dlopen` feature.
+//!
+//! - The [`comm_chan`] module provides wrapper for DOCA Comm Channel,
+//! a message channel between the host and the DPU. It requires
+//! the `comm-channel` feature.
+//!
+//! - The [`compress`] module provides wrapper for DOCA Compress engine,
+//! which compresses and decompresses data with deflate using hardware
+//! acceleration. It requires the `compress` feature and DOCA 1.5.
+//!
+//! - The [`sha`] module provides wrapper for DOCA SHA engine, which
+//! computes SHA-1/256/512 digests, at once or over several jobs,
+//! using hardware acceleration. It requires the `sha` feature and DOCA 1.5.
+//!
+//! - The [`regex`] module provides wrapper for DOCA RegEx engine, which
+//! searches data with compiled rules using hardware acceleration.
+//! It requires the `regex` feature and DOCA 1.5.
+//!
+//! - The [`erasure_coding`] module provides wrapper for DOCA Erasure Coding
+//! engine, which creates Reed-Solomon redundancy blocks and recovers lost
+//! data blocks. It requires the `erasure-coding` feature and DOCA 2.x.
+//!
+//! - The [`aes_gcm`] module provides wrapper for DOCA AES-GCM engine, which
+//! encrypts and authenticates data using hardware acceleration.
+//! It requires the `aes-gcm` feature and DOCA 2.5 or newer.
+//!
+//! - The [`rdma`] module provides wrapper for DOCA RDMA engine, which
+//! sends messages to, and reads and writes the memory of a remote peer.
+//! It requires the `rdma` feature and DOCA 2.x.
+//!
+//! - The [`eth`] module provides wrapper for DOCA Ethernet receive and transmit
+//! queues, which receive and transmit packets by bursts from the CPU.
+//! It requires the `eth` feature and DOCA 2.5 or newer.
+//!
+//! - The [`flow`] module provides wrapper for DOCA Flow, which programs the
+//! steering rules of the ports with pipes and entries.
+//! It requires the `flow` feature and DOCA 2.7 or newer.
+//!
+//! - The [`telemetry`] module reads the port counters of an opened device,
+//! either on demand or periodically on a background thread.
+//! The PCIe and DMA counters of DOCA Telemetry Diagnostics are read with them
+//! with the `telemetry-diag` feature and DOCA 2.9 or newer.
 //!
 //!
 //!
@@ -38,19 +87,76 @@ use std::io::{BufRead, BufReader, Read, Write};
 use std::ptr::NonNull;
 use std::slice;
 
-pub use device::{devices, open_device_with_pci, DevContext, Device, DeviceList};
-pub use dma::{DMAEngine, DOCAEvent, DOCAWorkQueue};
+pub use device::{
+    devices, open_device_with_pci, DevContext, Device, DeviceFilter, DeviceList, PciAddress,
+};
+#[cfg(not(doca_2_x))]
+pub use context::work_queue::{DOCAEvent, DOCAWorkQueue};
+#[cfg(feature = "dma")]
+pub use dma::DMAEngine;
 pub use memory::buffer::{BufferInventory, DOCABuffer, RawPointer, RawPointerMsg};
 pub use memory::registered_memory::DOCARegisteredMemory;
 pub use memory::DOCAMmap;
 
+/// Emit a `tracing` event at `trace` level, a no-op without the `tracing` feature.
+///
+/// The fields are only evaluated when the event is enabled.
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::trace!($($arg)*);
+    };
+}
+
+/// Emit a `tracing` event at `warn` level, a no-op without the `tracing` feature.
+///
+/// Used for the failures which can not be returned, e.g. in `drop`.
+#[allow(unused_macros)]
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::warn!($($arg)*);
+    };
+}
+
 pub mod context;
 pub mod device;
+#[cfg(feature = "dma")]
 pub mod dma;
 pub mod memory;
+#[cfg(doca_2_x)]
+pub mod pe;
 
+#[cfg(feature = "comm-channel")]
 pub mod comm_chan;
 
+#[cfg(all(feature = "compress", not(doca_2_x)))]
+pub mod compress;
+
+#[cfg(all(feature = "sha", not(doca_2_x)))]
+pub mod sha;
+
+#[cfg(all(feature = "regex", not(doca_2_x)))]
+pub mod regex;
+
+#[cfg(all(feature = "erasure-coding", doca_2_x))]
+pub mod erasure_coding;
+
+#[cfg(all(feature = "aes-gcm", doca_2_x))]
+pub mod aes_gcm;
+
+#[cfg(all(feature = "rdma", doca_2_x))]
+pub mod rdma;
+
+#[cfg(all(feature = "eth", doca_2_x))]
+pub mod eth;
+
+#[cfg(all(feature = "flow", doca_2_x))]
+pub mod flow;
+
+pub mod runtime;
+pub mod telemetry;
+
 /// Error type
 pub type DOCAError = doca_error;
 
@@ -37,8 +37,14 @@
 //! ```
 use core::ffi::c_void;
 use ffi::doca_error;
+
+// Renamed in DOCA 2.x, with the same signature
+#[cfg(not(doca_2_x))]
+use ffi::doca_buf_refcount_rm;
+#[cfg(doca_2_x)]
+use ffi::doca_buf_dec_refcount as doca_buf_refcount_rm;
 use std::ptr::NonNull;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 // use std::convert::From;
 
 use crate::memory::DOCAMmap;
@@ -57,6 +63,13 @@ pub struct RawPointer {
     pub payload: usize,
 }
 
+// SAFETY: a `RawPointer` only describes a memory region, it owns nothing and
+// never dereferences the pointer itself. The memory is only accessed by DOCA,
+// and its validity is up to the creator of the descriptor on any thread.
+unsafe impl Send for RawPointer {}
+// SAFETY: see `Send`, a shared `RawPointer` can only be copied or read.
+unsafe impl Sync for RawPointer {}
+
 /// a (de)serializable struct for passing RawPointer between nodes
 #[derive(Serialize, Deserialize)]
 pub struct RawPointerMsg {
@@ -143,16 +156,20 @@ pub struct DOCABuffer {
     pub(crate) mmap: Arc<DOCAMmap>,
 }
 
+// SAFETY: a `doca_buf` is not bound to a thread, and `DOCABuffer` is its only
+// owner, so there is no other handle to race with after a move. Returning it to
+// the inventory on drop holds the lock of the inventory.
+unsafe impl Send for DOCABuffer {}
+
 impl Drop for DOCABuffer {
     fn drop(&mut self) {
-        let ret = unsafe { ffi::doca_buf_refcount_rm(self.inner_ptr(), std::ptr::null_mut()) };
+        let _guard = self.inv.lock();
+        let ret = unsafe { doca_buf_refcount_rm(self.inner_ptr(), std::ptr::null_mut()) };
         if ret != doca_error::DOCA_SUCCESS {
             panic!("Failed to remove refcount of doca buffer");
         }
 
-        // Show drop order only in `debug` mode
-        #[cfg(debug_assertions)]
-        println!("DOCA Buffer is dropped!");
+        trace!(buf = ?self.inner, "DOCA Buffer is dropped");
     }
 }
 
@@ -172,6 +189,19 @@ impl DOCABuffer {
         Ok(data)
     }
 
+    /// Get the length of the buffer's data.
+    pub fn data_len(&self) -> DOCAResult<usize> {
+        let mut len: usize = 0;
+
+        let ret = unsafe { ffi::doca_buf_get_data_len(self.inner_ptr(), &mut len as *mut _) };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(len)
+    }
+
     /// Set data pointer and data length
     /// The data pointer and length should fix in the head region.
     /// Therefore, we adopt usize (in offset), instead of passing the raw pointers
@@ -201,15 +231,22 @@ impl DOCABuffer {
 /// Each buffer obtained from an inventory is a descriptor that points to a memory region from a doca_mmap memory range of the user's choice.
 pub struct BufferInventory {
     inner: NonNull<ffi::doca_buf_inventory>,
+    // `doca_buf_inventory` is not thread-safe, every allocation from it
+    // and every release to it holds the lock
+    lock: Mutex<()>,
 }
 
+// SAFETY: the only calls on the inventory through `&self` are the allocations
+// and the releases of its buffers, which are serialized by `lock`.
+unsafe impl Sync for BufferInventory {}
+// SAFETY: the inventory is not bound to the thread that created it.
+unsafe impl Send for BufferInventory {}
+
 impl Drop for BufferInventory {
     fn drop(&mut self) {
         unsafe { ffi::doca_buf_inventory_destroy(self.inner.as_ptr()) };
 
-        // Show drop order only in `debug` mode
-        #[cfg(debug_assertions)]
-        println!("Buffer Inventory is dropped!");
+        trace!(inv = ?self.inner, "Buffer Inventory is dropped");
     }
 }
 
@@ -225,9 +262,12 @@ impl BufferInventory {
         // currently we don't use `user_data` field
         let mut buf_inv: *mut ffi::doca_buf_inventory = std::ptr::null_mut();
         // DOCA_BUF_EXTENSION_NONE = 0;
+        #[cfg(not(doca_2_x))]
         let ret = unsafe {
             ffi::doca_buf_inventory_create(std::ptr::null(), num, 0, &mut buf_inv as *mut _)
         };
+        #[cfg(doca_2_x)]
+        let ret = unsafe { ffi::doca_buf_inventory_create(num, &mut buf_inv as *mut _) };
 
         if ret != doca_error::DOCA_SUCCESS {
             return Err(ret);
@@ -235,6 +275,7 @@ impl BufferInventory {
 
         let mut res = Self {
             inner: unsafe { NonNull::new_unchecked(buf_inv) },
+            lock: Mutex::new(()),
         };
         res.start()?;
 
@@ -246,6 +287,12 @@ impl BufferInventory {
         self.inner.as_ptr()
     }
 
+    /// Lock the inventory to allocate a buffer from it or release one to it
+    #[inline]
+    pub(crate) fn lock(&self) -> MutexGuard<'_, ()> {
+        self.lock.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
     /// Start element retrieval from inventory.
     fn start(&mut self) -> DOCAResult<()> {
         let ret = unsafe { ffi::doca_buf_inventory_start(self.inner_ptr()) };
@@ -36,9 +36,18 @@ pub mod registered_memory;
 
 use core::ffi::c_void;
 use ffi::{doca_error, doca_mmap_set_memrange, doca_mmap_set_permissions};
+
+// Renamed in DOCA 2.x, with the same signatures
+#[cfg(not(doca_2_x))]
+use ffi::{doca_mmap_dev_add, doca_mmap_dev_rm, doca_mmap_export_dpu};
+#[cfg(doca_2_x)]
+use ffi::{
+    doca_mmap_add_dev as doca_mmap_dev_add, doca_mmap_export_pci as doca_mmap_export_dpu,
+    doca_mmap_rm_dev as doca_mmap_dev_rm,
+};
 // use page_size;
 use std::ptr::NonNull;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 
 use crate::device::DevContext;
 use crate::{DOCAError, DOCAResult, RawPointer};
@@ -55,8 +64,18 @@ pub struct DOCAMmap {
     ctx: Vec<Arc<DevContext>>,
     // Control the drop behavior
     ok: bool,
+    // Serialize the calls changing the state of the mmap through `&self`
+    lock: Mutex<()>,
 }
 
+// SAFETY: `doca_mmap` is not bound to the thread that created it. The methods
+// taking `&self` that change the mmap (`rm_device`, `set_memrange`, `start`)
+// and the buffer allocation, which reads it, hold `lock`, so they never run at
+// the same time; the other changes need `&mut self`.
+unsafe impl Sync for DOCAMmap {}
+// SAFETY: see above, the mmap can be used and destroyed from any thread.
+unsafe impl Send for DOCAMmap {}
+
 // The `drop` function in DOCAMmap should be considered carefully.
 // Since the operation `doca_mmap_dev_rm` is not permitted for:
 // - un-started/stopped memory map object.
@@ -78,7 +97,7 @@ impl Drop for DOCAMmap {
             }
 
             for dev in &self.ctx {
-                let ret = unsafe { ffi::doca_mmap_dev_rm(self.inner_ptr(), dev.inner_ptr()) };
+                let ret = unsafe { doca_mmap_dev_rm(self.inner_ptr(), dev.inner_ptr()) };
 
                 if ret != doca_error::DOCA_SUCCESS {
                     panic!(
@@ -92,9 +111,7 @@ impl Drop for DOCAMmap {
         self.ctx.clear();
         unsafe { ffi::doca_mmap_destroy(self.inner.as_ptr()) };
 
-        // Show drop order only in `debug` mode
-        #[cfg(debug_assertions)]
-        println!("DOCA mmap is dropped!");
+        trace!(mmap = ?self.inner, "DOCA mmap is dropped");
     }
 }
 
@@ -114,9 +131,14 @@ impl DOCAMmap {
         let mut pool: *mut ffi::doca_mmap = std::ptr::null_mut();
 
         // currently we don't use any user data
-        let null_ptr: *mut ffi::doca_data = std::ptr::null_mut();
-
-        let ret = unsafe { ffi::doca_mmap_create(null_ptr, &mut pool as *mut _) };
+        #[cfg(not(doca_2_x))]
+        let ret = {
+            let null_ptr: *mut ffi::doca_data = std::ptr::null_mut();
+            unsafe { ffi::doca_mmap_create(null_ptr, &mut pool as *mut _) }
+        };
+        // The user data is set separately since DOCA 2.x
+        #[cfg(doca_2_x)]
+        let ret = unsafe { ffi::doca_mmap_create(&mut pool as *mut _) };
 
         if ret != doca_error::DOCA_SUCCESS {
             return Err(ret);
@@ -126,6 +148,7 @@ impl DOCAMmap {
             inner: unsafe { NonNull::new_unchecked(pool) },
             ctx: Vec::new(),
             ok: true,
+            lock: Mutex::new(()),
         };
 
         Ok(res)
@@ -142,6 +165,12 @@ impl DOCAMmap {
         self.inner.as_ptr()
     }
 
+    /// Lock the mmap for a DOCA call through `&self`
+    #[inline]
+    pub(crate) fn lock(&self) -> MutexGuard<'_, ()> {
+        self.lock.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
     /// Creates a memory map object representing the **remote** memory.
     /// It should be bound to a `DevContext`.
     ///
@@ -183,6 +212,7 @@ impl DOCAMmap {
             inner: unsafe { NonNull::new_unchecked(pool) },
             ctx: vec![dev.clone()],
             ok: false,
+            lock: Mutex::new(()),
         })
     }
 
@@ -204,7 +234,7 @@ impl DOCAMmap {
             .ok_or(doca_error::DOCA_ERROR_INVALID_VALUE)?;
 
         let ret = unsafe {
-            ffi::doca_mmap_export_dpu(
+            doca_mmap_export_dpu(
                 self.inner_ptr(),
                 dev.inner_ptr(),
                 &mut export_desc as *const _ as *mut _,
@@ -224,9 +254,48 @@ impl DOCAMmap {
         })
     }
 
+    /// Export the **local mmap** information to a buffer for RDMA,
+    /// so that a remote peer can read and write the memory with RDMA tasks,
+    /// see [`crate::rdma`]. The remote peer creates the mmap with `new_from_export`.
+    ///
+    /// The permissions of the mmap should allow the RDMA access, e.g. `DOCA_ACCESS_FLAG_RDMA_READ`.
+    ///
+    /// Input:
+    /// - dev_index: the index of the local device that the mmap is registered on.
+    ///
+    #[cfg(doca_2_x)]
+    pub fn export_rdma(&mut self, dev_index: usize) -> DOCAResult<RawPointer> {
+        let mut len: usize = 0;
+        let mut export_desc: *mut c_void = std::ptr::null_mut();
+        let dev = self
+            .ctx
+            .get(dev_index)
+            .ok_or(doca_error::DOCA_ERROR_INVALID_VALUE)?;
+
+        let ret = unsafe {
+            ffi::doca_mmap_export_rdma(
+                self.inner_ptr(),
+                dev.inner_ptr(),
+                &mut export_desc as *mut *mut c_void as *mut *const c_void,
+                &mut len as *mut usize,
+            )
+        };
+
+        if ret != doca_error::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        self.ok = false;
+
+        Ok(RawPointer {
+            inner: NonNull::new(export_desc).ok_or(DOCAError::DOCA_ERROR_INVALID_VALUE)?,
+            payload: len,
+        })
+    }
+
     /// Register DOCA memory map on a given device.
     pub fn add_device(&mut self, dev: &Arc<DevContext>) -> DOCAResult<usize> {
-        let ret = unsafe { ffi::doca_mmap_dev_add(self.inner_ptr(), dev.inner_ptr()) };
+        let ret = unsafe { doca_mmap_dev_add(self.inner_ptr(), dev.inner_ptr()) };
 
         if ret != doca_error::DOCA_SUCCESS {
             return Err(ret);
@@ -240,8 +309,9 @@ impl DOCAMmap {
     /// Notice that, the given index from `add_device`
     /// will change after the user calls the function.
     pub fn rm_device(&self, _dev_idx: usize) -> DOCAResult<()> {
+        let _guard = self.lock();
         let ret =
-            unsafe { ffi::doca_mmap_dev_rm(self.inner_ptr(), self.ctx[_dev_idx].inner_ptr()) };
+            unsafe { doca_mmap_dev_rm(self.inner_ptr(), self.ctx[_dev_idx].inner_ptr()) };
 
         if ret != doca_error::DOCA_SUCCESS {
             return Err(ret);
@@ -256,6 +326,7 @@ impl DOCAMmap {
     /// The memory can be used for DMA for all the contexts already in the mmap.
     ///
     pub fn set_memrange(&self, mr: RawPointer) -> DOCAResult<()> {
+        let _guard = self.lock();
         let ret = unsafe {
             doca_mmap_set_memrange(
                 self.inner_ptr(), 
@@ -296,6 +367,7 @@ impl DOCAMmap {
     /// Allows execution of different operations on the mmap.
     ///
     pub fn start(&self) -> DOCAResult<()> {
+        let _guard = self.lock();
         let ret = unsafe { ffi::doca_mmap_start(self.inner_ptr()) };
 
         if ret != doca_error::DOCA_SUCCESS {
@@ -307,6 +379,30 @@ impl DOCAMmap {
 
 }
 
+/// Register `buffer` in its own mmap on the device and get a DOCA buffer of it,
+/// whose data is the first `len` bytes. Shared by the tests of the engines.
+#[cfg(test)]
+pub(crate) fn test_buffer(
+    device: &Arc<DevContext>,
+    inv: &Arc<crate::BufferInventory>,
+    buffer: &mut [u8],
+    len: usize,
+) -> crate::DOCABuffer {
+    let mut mmap = DOCAMmap::new().unwrap();
+    mmap.add_device(device).unwrap();
+    let mmap = Arc::new(mmap);
+
+    let raw = RawPointer {
+        inner: NonNull::new(buffer.as_mut_ptr() as _).unwrap(),
+        payload: buffer.len(),
+    };
+    let registered_memory = registered_memory::DOCARegisteredMemory::new(&mmap, raw).unwrap();
+    mmap.start().unwrap();
+    let mut buf = registered_memory.to_buffer(inv).unwrap();
+    unsafe { buf.set_data(0, len).unwrap() };
+    buf
+}
+
 mod tests {
 
     // a simple test to create a memory pool and
@@ -336,6 +432,7 @@ mod tests {
 
     // Test show that the `rm_device` is forbidden on a exported mmap
     #[test]
+    #[cfg(not(doca_2_x))]
     fn test_mmap_rm_device() {
         use crate::*;
         use std::ptr::NonNull;
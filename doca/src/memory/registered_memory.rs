@@ -10,6 +10,12 @@ use crate::memory::DOCAMmap;
 use crate::{DOCAResult, RawPointer};
 
 use ffi::doca_error;
+
+// Renamed in DOCA 2.x, with the same signature
+#[cfg(not(doca_2_x))]
+use ffi::doca_buf_inventory_buf_by_args;
+#[cfg(doca_2_x)]
+use ffi::doca_buf_inventory_buf_get_by_args as doca_buf_inventory_buf_by_args;
 use std::ptr::NonNull;
 use std::sync::Arc;
 
@@ -45,8 +51,10 @@ impl DOCARegisteredMemory {
     /// Allocate a buffer from the registered memory
     pub fn to_buffer(self, inv: &Arc<BufferInventory>) -> DOCAResult<DOCABuffer> {
         let mut buffer: *mut ffi::doca_buf = std::ptr::null_mut();
+        let _inv_guard = inv.lock();
+        let _mmap_guard = self.mmap.lock();
         let ret = unsafe {
-            ffi::doca_buf_inventory_buf_by_args(
+            doca_buf_inventory_buf_by_args(
                 inv.inner_ptr(),
                 self.mmap.inner_ptr(),
                 self.register_memory.get_inner().as_ptr(), // head ptr
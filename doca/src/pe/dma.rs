@@ -0,0 +1,170 @@
+//! DMA memcpy tasks of the DOCA 2.x DMA engine.
+//!
+//! The tasks are enabled on the context with [`PeContextBuilder::dma_memcpy`],
+//! which sets the maximum number of tasks allocated at the same time
+//! and the completion callback.
+
+use std::sync::Arc;
+
+use super::task::{self, RawTask, Task, TaskCompletion};
+use super::{CtxCallbacks, OnCompletion, PeContext, PeContextBuilder};
+use crate::{DMAEngine, DOCABuffer, DOCAError, DOCAResult};
+
+/// What a DMA memcpy task owns
+pub struct DmaTaskState {
+    // Ensure that the context is stopped after all its tasks are freed
+    #[allow(dead_code)]
+    ctx: Arc<PeContext<DMAEngine>>,
+    src_buf: DOCABuffer,
+    dst_buf: DOCABuffer,
+}
+
+impl RawTask for ffi::doca_dma_task_memcpy {
+    type State = DmaTaskState;
+
+    unsafe fn as_task(task: *mut Self) -> *mut ffi::doca_task {
+        ffi::doca_dma_task_memcpy_as_task(task)
+    }
+}
+
+impl TaskCompletion for ffi::doca_dma_task_memcpy {
+    type Error = DOCAError;
+
+    fn callback(callbacks: &mut CtxCallbacks) -> &mut OnCompletion<DmaMemcpyTask> {
+        &mut callbacks.dma_memcpy
+    }
+
+    fn error(status: DOCAError) -> DOCAError {
+        status
+    }
+}
+
+/// A DOCA DMA memcpy task, copying the data of the source buffer
+/// into the destination buffer.
+pub type DmaMemcpyTask = Task<ffi::doca_dma_task_memcpy>;
+
+impl DmaMemcpyTask {
+    /// Allocate a memcpy task on the started context.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NO_MEMORY`: the number of tasks set by
+    ///  [`PeContextBuilder::dma_memcpy`] are all allocated.
+    ///  - `DOCA_ERROR_BAD_STATE`: the context is not running.
+    ///
+    pub fn new(
+        ctx: &Arc<PeContext<DMAEngine>>,
+        src_buf: DOCABuffer,
+        dst_buf: DOCABuffer,
+        user_data: u64,
+    ) -> DOCAResult<Self> {
+        let state = DmaTaskState {
+            ctx: ctx.clone(),
+            src_buf,
+            dst_buf,
+        };
+
+        unsafe {
+            Self::alloc(state, user_data, |state, task_user_data, task| {
+                ffi::doca_dma_task_memcpy_alloc_init(
+                    ctx.engine().inner_ptr(),
+                    state.src_buf.inner_ptr(),
+                    state.dst_buf.inner_ptr(),
+                    task_user_data,
+                    task,
+                )
+            })
+        }
+        .map_err(|(e, _state)| e)
+    }
+
+    /// Free the task and return its source and destination buffers.
+    pub fn into_buffers(self) -> (DOCABuffer, DOCABuffer) {
+        let state = self.into_state();
+        (state.src_buf, state.dst_buf)
+    }
+
+    /// Get the source buffer
+    #[inline]
+    pub fn src_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().src_buf
+    }
+
+    /// Get the destination buffer
+    #[inline]
+    pub fn dst_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().dst_buf
+    }
+}
+
+impl PeContextBuilder<DMAEngine> {
+    /// Enable the memcpy tasks on the context.
+    ///
+    /// At most `num_tasks` tasks can be allocated at the same time. `on_completion` is
+    /// called with every completed task and its result, either `Ok(())` or the error
+    /// status of the task. The task can be submitted again from the callback,
+    /// otherwise it is freed when dropped.
+    pub fn dma_memcpy<F>(&mut self, num_tasks: u32, on_completion: F) -> &mut Self
+    where
+        F: FnMut(DmaMemcpyTask, DOCAResult<()>) + 'static,
+    {
+        self.callbacks.dma_memcpy = Some(Box::new(on_completion));
+        self.confs.push(Box::new(move |dma: &DMAEngine| {
+            let ret = unsafe {
+                ffi::doca_dma_task_memcpy_set_conf(
+                    dma.inner_ptr(),
+                    Some(task::success_cb::<ffi::doca_dma_task_memcpy>),
+                    Some(task::error_cb::<ffi::doca_dma_task_memcpy>),
+                    num_tasks,
+                )
+            };
+
+            if ret != DOCAError::DOCA_SUCCESS {
+                return Err(ret);
+            }
+            Ok(())
+        }));
+        self
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_dma_memcpy_task() {
+        use crate::pe::{DmaMemcpyTask, PeContext, ProgressEngine};
+        use crate::*;
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let device = devices().unwrap().get(0).unwrap().open().unwrap();
+        let pe = ProgressEngine::new().unwrap();
+        let dma = DMAEngine::new(&device).unwrap();
+
+        let done = Rc::new(Cell::new(0));
+        let done_cb = done.clone();
+        let ctx = PeContext::builder(&dma, &pe)
+            .dma_memcpy(1, move |task, result| {
+                assert_eq!(result, Ok(()));
+                assert_eq!(task.user_data(), 7);
+                done_cb.set(done_cb.get() + 1);
+            })
+            .start()
+            .unwrap();
+
+        let test_len = 64;
+        let mut src_buffer = vec![1u8; test_len].into_boxed_slice();
+        let mut dst_buffer = vec![0u8; test_len].into_boxed_slice();
+        let inv = BufferInventory::new(16).unwrap();
+
+        let src_buf = crate::memory::test_buffer(&device, &inv, &mut src_buffer, test_len);
+        // the copy is appended after the data of the destination, which is empty
+        let dst_buf = crate::memory::test_buffer(&device, &inv, &mut dst_buffer, 0);
+
+        let task = DmaMemcpyTask::new(&ctx, src_buf, dst_buf, 7).unwrap();
+        task.submit().map_err(|(e, _task)| e).unwrap();
+        pe.progress_until_idle().unwrap();
+
+        assert_eq!(done.get(), 1);
+        assert_eq!(src_buffer, dst_buffer);
+    }
+}
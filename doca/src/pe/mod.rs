@@ -0,0 +1,518 @@
+//! DOCA 2.x execution model.
+//!
+//! DOCA 2.x replaced the work queue, the jobs and the events of DOCA 1.5
+//! (see [`crate::context`]) with the following components.
+//! - [`ProgressEngine`] is a per-thread object driving the contexts connected to it.
+//! Each call to [`ProgressEngine::progress`] runs the callbacks of at most one completed task.
+//!
+//! - [`PeContext`] is a started DOCA CTX of an engine, connected to a progress engine.
+//! It is created with [`PeContextBuilder`], which also takes the callbacks:
+//! the context state-change callback and, per task type, the task completion callback.
+//!
+//! - The tasks, e.g. [`DmaMemcpyTask`] or the tasks of [`crate::erasure_coding`],
+//! [`crate::aes_gcm`], [`crate::rdma`] and [`crate::eth`], are all a [`Task`] of their
+//! DOCA task type. They are allocated from a started context and submitted.
+//! DOCA owns a submitted task until it completes, then the task
+//! is handed back to the completion callback with its result.
+//! The callback can submit it again, or drop it to free it.
+//!
+//! The callbacks are never re-entered: [`ProgressEngine::progress`] does nothing when
+//! called from a callback, and a context dropped in a callback (e.g. with the last task
+//! holding it) is stopped once the callback returns.
+//!
+//! # Examples
+//!
+//! ``` rust, no_run
+//! use doca::pe::{DmaMemcpyTask, PeContext, ProgressEngine};
+//! use doca::DMAEngine;
+//!
+//! let device = doca::device::open_device_with_pci("03:00.0").unwrap();
+//! let pe = ProgressEngine::new().unwrap();
+//! let dma = DMAEngine::new(&device).unwrap();
+//!
+//! let ctx = PeContext::builder(&dma, &pe)
+//!     .on_state_changed(|prev, next| println!("{:?} -> {:?}", prev, next))
+//!     .dma_memcpy(16, |task, result| {
+//!         println!("task {} finished: {:?}", task.user_data(), result);
+//!     })
+//!     .start()
+//!     .unwrap();
+//!
+//! # fn buffers() -> (doca::DOCABuffer, doca::DOCABuffer) { unimplemented!() }
+//! # let (src_buf, dst_buf) = buffers();
+//! let task = DmaMemcpyTask::new(&ctx, src_buf, dst_buf, 1).unwrap();
+//! task.submit().map_err(|(e, _task)| e).unwrap();
+//!
+//! // Run the completion callbacks until the task is done
+//! pe.progress_until_idle().unwrap();
+//! ```
+//!
+
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::ffi::c_void;
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+#[cfg(feature = "aes-gcm")]
+use crate::aes_gcm::{AesGcmDecryptTask, AesGcmEncryptTask, AesGcmError};
+use crate::context::EngineToContext;
+#[cfg(feature = "erasure-coding")]
+use crate::erasure_coding::{EcCreateTask, EcRecoverTask};
+#[cfg(feature = "eth")]
+use crate::eth::{EthRecvTask, EthSendTask};
+#[cfg(feature = "rdma")]
+use crate::rdma::{RdmaReadTask, RdmaReceiveTask, RdmaSendTask, RdmaWriteTask};
+use crate::{DOCAError, DOCAResult};
+
+#[cfg(feature = "dma")]
+pub use dma::DmaMemcpyTask;
+pub use task::{RawTask, Task};
+
+/// DMA tasks
+#[cfg(feature = "dma")]
+pub mod dma;
+pub(crate) mod task;
+
+/// DOCA progress engine, a per-thread object (not thread-safe)
+/// polling the completions of the tasks submitted to the connected contexts.
+pub struct ProgressEngine {
+    inner: NonNull<ffi::doca_pe>,
+    // Set while DOCA may run the callbacks of the connected contexts
+    in_callbacks: Cell<bool>,
+    // The contexts dropped meanwhile, stopped once the callbacks return
+    stopping: RefCell<Vec<StoppingCtx>>,
+}
+
+// A progress engine can be moved to another thread, but it must not be shared.
+unsafe impl Send for ProgressEngine {}
+
+impl Drop for ProgressEngine {
+    fn drop(&mut self) {
+        let ret = unsafe { ffi::doca_pe_destroy(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            panic!("Failed to destroy the progress engine: {:?}", ret);
+        }
+
+        trace!(pe = ?self.inner, "DOCA progress engine is dropped");
+    }
+}
+
+impl ProgressEngine {
+    /// Create a DOCA progress engine.
+    // Shared by the contexts of its thread, never across threads
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new() -> DOCAResult<Arc<Self>> {
+        let mut pe: *mut ffi::doca_pe = std::ptr::null_mut();
+        let ret = unsafe { ffi::doca_pe_create(&mut pe as *mut _) };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(Arc::new(Self {
+            inner: unsafe { NonNull::new_unchecked(pe) },
+            in_callbacks: Cell::new(false),
+            stopping: RefCell::new(Vec::new()),
+        }))
+    }
+
+    /// Run the callback of at most one completed task or context event.
+    /// Return `true` if something has been done.
+    ///
+    /// Called from a callback, it does nothing and returns `false`.
+    #[inline]
+    pub fn progress(&self) -> bool {
+        if self.in_callbacks.get() {
+            return false;
+        }
+        self.enter(|| unsafe { ffi::doca_pe_progress(self.inner_ptr()) != 0 })
+    }
+
+    /// Run `f`, during which DOCA may call the callbacks of the connected contexts.
+    /// The contexts dropped meanwhile are stopped after the outermost call returns.
+    fn enter<R>(&self, f: impl FnOnce() -> R) -> R {
+        let nested = self.in_callbacks.replace(true);
+        let ret = f();
+        if !nested {
+            self.in_callbacks.set(false);
+            loop {
+                // Stopping a context may drop other ones, take them in batches
+                let stopping = std::mem::take(&mut *self.stopping.borrow_mut());
+                if stopping.is_empty() {
+                    break;
+                }
+                drop(stopping);
+            }
+        }
+        ret
+    }
+
+    /// Get the number of the submitted tasks which are not completed yet.
+    pub fn num_inflight_tasks(&self) -> DOCAResult<usize> {
+        let mut num = 0_usize;
+        let ret =
+            unsafe { ffi::doca_pe_get_num_inflight_tasks(self.inner_ptr(), &mut num as *mut _) };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(num)
+    }
+
+    /// Progress until all the submitted tasks are completed,
+    /// including the ones submitted again by the completion callbacks.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_BAD_STATE`: called from a callback, where the engine can not progress.
+    ///
+    pub fn progress_until_idle(&self) -> DOCAResult<()> {
+        if self.in_callbacks.get() {
+            return Err(DOCAError::DOCA_ERROR_BAD_STATE);
+        }
+        while self.num_inflight_tasks()? > 0 {
+            self.progress();
+        }
+        Ok(())
+    }
+
+    /// Get the inner pointer of the DOCA progress engine.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_pe {
+        self.inner.as_ptr()
+    }
+}
+
+/// The states of a DOCA context
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CtxState {
+    /// The context is created or stopped, it can be configured
+    Idle,
+    /// The context is being started
+    Starting,
+    /// The context is started, tasks can be submitted
+    Running,
+    /// The context is being stopped, the in-flight tasks are flushed
+    Stopping,
+}
+
+impl TryFrom<ffi::doca_ctx_states> for CtxState {
+    type Error = DOCAError;
+
+    fn try_from(state: ffi::doca_ctx_states) -> DOCAResult<Self> {
+        match state {
+            ffi::DOCA_CTX_STATE_IDLE => Ok(CtxState::Idle),
+            ffi::DOCA_CTX_STATE_STARTING => Ok(CtxState::Starting),
+            ffi::DOCA_CTX_STATE_RUNNING => Ok(CtxState::Running),
+            ffi::DOCA_CTX_STATE_STOPPING => Ok(CtxState::Stopping),
+            _ => Err(DOCAError::DOCA_ERROR_INVALID_VALUE),
+        }
+    }
+}
+
+/// The completion callback of a task type, called with the task and its result
+pub(crate) type OnCompletion<T, R = DOCAResult<()>> = Option<Box<dyn FnMut(T, R)>>;
+
+/// The callbacks of a context, its address is the user data of the DOCA CTX.
+/// Each task type adds its own completion callback.
+#[derive(Default)]
+pub(crate) struct CtxCallbacks {
+    state_changed: Option<Box<dyn FnMut(CtxState, CtxState)>>,
+    #[cfg(feature = "dma")]
+    pub(crate) dma_memcpy: OnCompletion<DmaMemcpyTask>,
+    #[cfg(feature = "erasure-coding")]
+    pub(crate) ec_create: OnCompletion<EcCreateTask>,
+    #[cfg(feature = "erasure-coding")]
+    pub(crate) ec_recover: OnCompletion<EcRecoverTask>,
+    #[cfg(feature = "aes-gcm")]
+    pub(crate) aes_gcm_encrypt: OnCompletion<AesGcmEncryptTask>,
+    #[cfg(feature = "aes-gcm")]
+    pub(crate) aes_gcm_decrypt: OnCompletion<AesGcmDecryptTask, Result<(), AesGcmError>>,
+    #[cfg(feature = "rdma")]
+    pub(crate) rdma_send: OnCompletion<RdmaSendTask>,
+    #[cfg(feature = "rdma")]
+    pub(crate) rdma_receive: OnCompletion<RdmaReceiveTask>,
+    #[cfg(feature = "rdma")]
+    pub(crate) rdma_read: OnCompletion<RdmaReadTask>,
+    #[cfg(feature = "rdma")]
+    pub(crate) rdma_write: OnCompletion<RdmaWriteTask>,
+    #[cfg(feature = "eth")]
+    pub(crate) eth_recv: OnCompletion<EthRecvTask>,
+    #[cfg(feature = "eth")]
+    pub(crate) eth_send: OnCompletion<EthSendTask>,
+}
+
+impl CtxCallbacks {
+    /// Run `f` with the callbacks from the user data of the DOCA CTX,
+    /// or return `None` if the user data has been cleared.
+    ///
+    /// # Safety
+    ///
+    /// Only called from the DOCA callbacks. They are not re-entered (see [`ProgressEngine::enter`])
+    /// and the callbacks are freed only after the context is stopped, so the reference is unique
+    /// and valid during `f`.
+    #[inline]
+    pub(crate) unsafe fn with_user_data<R>(
+        user_data: ffi::doca_data,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> Option<R> {
+        let callbacks = (user_data.ptr as *mut Self).as_mut()?;
+        Some(f(callbacks))
+    }
+}
+
+/// The state-change callback registered on every context
+unsafe extern "C" fn state_changed_cb(
+    user_data: ffi::doca_data,
+    _ctx: *mut ffi::doca_ctx,
+    prev_state: ffi::doca_ctx_states,
+    next_state: ffi::doca_ctx_states,
+) {
+    CtxCallbacks::with_user_data(user_data, |callbacks| {
+        if let (Some(cb), Ok(prev), Ok(next)) = (
+            callbacks.state_changed.as_mut(),
+            CtxState::try_from(prev_state),
+            CtxState::try_from(next_state),
+        ) {
+            cb(prev, next);
+        }
+    });
+}
+
+/// Get the current state of a DOCA context
+fn ctx_state(ctx: NonNull<ffi::doca_ctx>) -> DOCAResult<CtxState> {
+    let mut state: ffi::doca_ctx_states = ffi::DOCA_CTX_STATE_IDLE;
+    let ret = unsafe { ffi::doca_ctx_get_state(ctx.as_ptr(), &mut state as *mut _) };
+
+    if ret != DOCAError::DOCA_SUCCESS {
+        return Err(ret);
+    }
+
+    CtxState::try_from(state)
+}
+
+/// A configuration step of the engine, run before the context is started
+pub(crate) type EngineConf<T> = Box<dyn FnOnce(&T) -> DOCAResult<()>>;
+
+/// Collect the callbacks and the task configurations of a context, then start it.
+pub struct PeContextBuilder<T: EngineToContext> {
+    engine: Arc<T>,
+    pe: Arc<ProgressEngine>,
+    pub(crate) callbacks: Box<CtxCallbacks>,
+    pub(crate) confs: Vec<EngineConf<T>>,
+}
+
+impl<T: EngineToContext> PeContextBuilder<T> {
+    /// Set the callback called on each state change of the context,
+    /// with the previous and the next state.
+    pub fn on_state_changed<F>(&mut self, cb: F) -> &mut Self
+    where
+        F: FnMut(CtxState, CtxState) + 'static,
+    {
+        self.callbacks.state_changed = Some(Box::new(cb));
+        self
+    }
+
+    /// Apply the configurations, connect the context to the progress engine and start it.
+    ///
+    /// Some contexts finish starting asynchronously, their state is `Starting` until
+    /// the progress engine moves them to `Running`.
+    pub fn start(&mut self) -> DOCAResult<Arc<PeContext<T>>> {
+        for conf in self.confs.drain(..) {
+            conf(&self.engine)?;
+        }
+
+        let ctx = unsafe { self.engine.to_ctx() };
+        let ctx = NonNull::new(ctx).ok_or(DOCAError::DOCA_ERROR_INVALID_VALUE)?;
+        let callbacks = NonNull::from(Box::leak(std::mem::take(&mut self.callbacks)));
+
+        let mut user_data = ffi::doca_data::default();
+        user_data.ptr = callbacks.as_ptr() as *mut c_void;
+
+        let ret = unsafe { ffi::doca_ctx_set_user_data(ctx.as_ptr(), user_data) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            drop(unsafe { Box::from_raw(callbacks.as_ptr()) });
+            return Err(ret);
+        }
+
+        let ret = unsafe {
+            let ret = ffi::doca_ctx_set_state_changed_cb(ctx.as_ptr(), Some(state_changed_cb));
+            if ret == DOCAError::DOCA_SUCCESS {
+                ffi::doca_pe_connect_ctx(self.pe.inner_ptr(), ctx.as_ptr())
+            } else {
+                ret
+            }
+        };
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(self.abort_start(ctx, callbacks, ret));
+        }
+
+        let ret = self
+            .pe
+            .enter(|| unsafe { ffi::doca_ctx_start(ctx.as_ptr()) });
+        if ret != DOCAError::DOCA_SUCCESS && ret != DOCAError::DOCA_ERROR_IN_PROGRESS {
+            return Err(self.abort_start(ctx, callbacks, ret));
+        }
+
+        Ok(Arc::new(PeContext {
+            inner: ctx,
+            engine: self.engine.clone(),
+            pe: self.pe.clone(),
+            callbacks,
+        }))
+    }
+
+    /// Detach the callbacks from a context which failed to start, then free them.
+    ///
+    /// The context may stay connected to the progress engine, but without tasks or
+    /// user data it is never called back.
+    fn abort_start(
+        &self,
+        ctx: NonNull<ffi::doca_ctx>,
+        callbacks: NonNull<CtxCallbacks>,
+        err: DOCAError,
+    ) -> DOCAError {
+        unsafe {
+            ffi::doca_ctx_set_state_changed_cb(ctx.as_ptr(), None);
+            ffi::doca_ctx_set_user_data(ctx.as_ptr(), ffi::doca_data::default());
+            drop(Box::from_raw(callbacks.as_ptr()));
+        }
+        err
+    }
+}
+
+/// A started DOCA context connected to a progress engine.
+///
+/// It must be dropped on the thread of its progress engine,
+/// since stopping it may need to progress the engine.
+pub struct PeContext<T: EngineToContext> {
+    inner: NonNull<ffi::doca_ctx>,
+
+    // Ensure that the engine & the progress engine are dropped after the context is stopped
+    engine: Arc<T>,
+    pe: Arc<ProgressEngine>,
+    // Referenced by the DOCA CTX, freed once the context is stopped
+    callbacks: NonNull<CtxCallbacks>,
+}
+
+impl<T: EngineToContext> PeContext<T> {
+    /// Start building a context of the engine on the progress engine.
+    pub fn builder(engine: &Arc<T>, pe: &Arc<ProgressEngine>) -> PeContextBuilder<T> {
+        PeContextBuilder {
+            engine: engine.clone(),
+            pe: pe.clone(),
+            callbacks: Box::default(),
+            confs: Vec::new(),
+        }
+    }
+
+    /// Get the current state of the context.
+    pub fn state(&self) -> DOCAResult<CtxState> {
+        ctx_state(self.inner)
+    }
+
+    /// Get the engine of the context
+    #[inline]
+    pub fn engine(&self) -> &Arc<T> {
+        &self.engine
+    }
+
+    /// Get the progress engine the context is connected to
+    #[inline]
+    pub fn pe(&self) -> &Arc<ProgressEngine> {
+        &self.pe
+    }
+
+    /// Get the inner pointer of the DOCA context.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_ctx {
+        self.inner.as_ptr()
+    }
+}
+
+impl<T: EngineToContext> Drop for PeContext<T> {
+    fn drop(&mut self) {
+        let stopping = StoppingCtx {
+            inner: self.inner,
+            pe: self.pe.clone(),
+            callbacks: self.callbacks,
+            _engine: self.engine.clone(),
+        };
+
+        // Stopping progresses the engine and frees the callbacks, which can not be done
+        // from a callback: the context is stopped once the callbacks return.
+        if self.pe.in_callbacks.get() {
+            self.pe.stopping.borrow_mut().push(stopping);
+        } else {
+            drop(stopping);
+        }
+    }
+}
+
+/// A dropped context, stopped when this is dropped
+struct StoppingCtx {
+    inner: NonNull<ffi::doca_ctx>,
+    pe: Arc<ProgressEngine>,
+    callbacks: NonNull<CtxCallbacks>,
+    // Ensure that the engine is dropped after the context is stopped
+    _engine: Arc<dyn Any>,
+}
+
+impl Drop for StoppingCtx {
+    fn drop(&mut self) {
+        let ret = self
+            .pe
+            .enter(|| unsafe { ffi::doca_ctx_stop(self.inner.as_ptr()) });
+        match ret {
+            DOCAError::DOCA_SUCCESS => {}
+            // The remaining tasks are flushed by the progress engine
+            DOCAError::DOCA_ERROR_IN_PROGRESS => loop {
+                match ctx_state(self.inner) {
+                    Ok(CtxState::Idle) => break,
+                    Ok(_) => {
+                        self.pe.progress();
+                    }
+                    Err(e) => panic!("Failed to get the state of the stopping Context: {:?}", e),
+                }
+            },
+            e => panic!("Failed to stop the Context: {:?}", e),
+        }
+
+        drop(unsafe { Box::from_raw(self.callbacks.as_ptr()) });
+
+        trace!(ctx = ?self.inner, "DOCA PE Context is dropped");
+    }
+}
+
+#[cfg(feature = "dma")]
+mod tests {
+    #[test]
+    fn test_pe_context_start() {
+        use crate::pe::{CtxState, PeContext, ProgressEngine};
+        use crate::DMAEngine;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let device = crate::device::devices()
+            .unwrap()
+            .get(0)
+            .unwrap()
+            .open()
+            .unwrap();
+
+        let pe = ProgressEngine::new().unwrap();
+        let dma = DMAEngine::new(&device).unwrap();
+
+        let states = Rc::new(RefCell::new(Vec::new()));
+        let states_cb = states.clone();
+        let ctx = PeContext::builder(&dma, &pe)
+            .on_state_changed(move |_prev, next| states_cb.borrow_mut().push(next))
+            .dma_memcpy(4, |_task, _result| {})
+            .start()
+            .unwrap();
+
+        assert_eq!(ctx.state().unwrap(), CtxState::Running);
+        assert_eq!(states.borrow().last(), Some(&CtxState::Running));
+        assert_eq!(pe.num_inflight_tasks().unwrap(), 0);
+    }
+}
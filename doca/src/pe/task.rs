@@ -0,0 +1,199 @@
+//! The ownership of the DOCA 2.x tasks, shared by all the task types.
+//!
+//! A [`Task`] owns the DOCA task and a boxed state: the context of the task, its buffers
+//! and the mark for user data. The address of the state is the user data of the DOCA task.
+//! Once submitted, the DOCA task and the state are owned by DOCA, then taken back
+//! when the task completes and handed to the completion callback of the context.
+
+use std::ffi::c_void;
+use std::mem::ManuallyDrop;
+use std::ptr::NonNull;
+
+use super::{CtxCallbacks, OnCompletion};
+use crate::DOCAError;
+
+/// A typed DOCA task, e.g. `doca_dma_task_memcpy`
+pub trait RawTask: 'static {
+    /// What a task of this type owns besides the DOCA task
+    type State;
+
+    /// Get the base `doca_task` of a task of this type.
+    ///
+    /// # Safety
+    ///
+    /// `task` must be a valid task of this type.
+    unsafe fn as_task(task: *mut Self) -> *mut ffi::doca_task;
+}
+
+/// The boxed state of a task, its address is the user data of the DOCA task
+struct Owned<S> {
+    state: S,
+    user_data: u64,
+}
+
+/// A DOCA task of type `T`, e.g. [`super::DmaMemcpyTask`].
+///
+/// The task is freed when dropped. Once submitted, the task is owned by DOCA
+/// and handed back to the completion callback of the context.
+pub struct Task<T: RawTask> {
+    inner: NonNull<T>,
+    owned: Box<Owned<T::State>>,
+}
+
+impl<T: RawTask> Drop for Task<T> {
+    fn drop(&mut self) {
+        unsafe { ffi::doca_task_free(self.as_task()) };
+    }
+}
+
+impl<T: RawTask> Task<T> {
+    /// Box the state, then allocate the DOCA task with `alloc`, called with the state
+    /// and the user data of the DOCA task. If the allocation fails, the error and
+    /// the state are returned.
+    ///
+    /// # Safety
+    ///
+    /// If `alloc` returns `DOCA_SUCCESS`, it must have set a task of type `T`
+    /// allocated with the given user data.
+    pub(crate) unsafe fn alloc(
+        state: T::State,
+        user_data: u64,
+        alloc: impl FnOnce(&T::State, ffi::doca_data, *mut *mut T) -> DOCAError,
+    ) -> Result<Self, (DOCAError, T::State)> {
+        let mut owned = Box::new(Owned { state, user_data });
+
+        let mut task_user_data = ffi::doca_data::default();
+        task_user_data.ptr = &mut *owned as *mut Owned<T::State> as *mut c_void;
+
+        let mut task: *mut T = std::ptr::null_mut();
+        let ret = alloc(&owned.state, task_user_data, &mut task as *mut _);
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err((ret, owned.state));
+        }
+
+        Ok(Self {
+            inner: NonNull::new_unchecked(task),
+            owned,
+        })
+    }
+
+    /// Submit the task.
+    ///
+    /// The task is handed back to the completion callback of the context when it
+    /// completes. If the submission fails, the error and the task are returned.
+    pub fn submit(self) -> Result<(), (DOCAError, Self)> {
+        let ret = unsafe { ffi::doca_task_submit(self.as_task()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err((ret, self));
+        }
+
+        // DOCA owns the task until it completes, the state is taken back
+        // by `from_completion` from the user data of the task.
+        let this = ManuallyDrop::new(self);
+        let _ = Box::into_raw(unsafe { std::ptr::read(&this.owned) });
+        Ok(())
+    }
+
+    /// Get the mark for user data
+    #[inline]
+    pub fn user_data(&self) -> u64 {
+        self.owned.user_data
+    }
+
+    /// Set mark for user data
+    #[inline]
+    pub fn set_user_data(&mut self, user_data: u64) -> &mut Self {
+        self.owned.user_data = user_data;
+        self
+    }
+
+    /// Get the inner pointer of the DOCA task.
+    pub unsafe fn inner_ptr(&self) -> *mut T {
+        self.inner.as_ptr()
+    }
+
+    /// Get the state of the task
+    #[inline]
+    pub(crate) fn state(&self) -> &T::State {
+        &self.owned.state
+    }
+
+    /// Get the state of the task
+    #[inline]
+    pub(crate) fn state_mut(&mut self) -> &mut T::State {
+        &mut self.owned.state
+    }
+
+    /// Free the DOCA task and return its state
+    pub(crate) fn into_state(self) -> T::State {
+        let this = ManuallyDrop::new(self);
+        unsafe { ffi::doca_task_free(this.as_task()) };
+
+        let owned = unsafe { std::ptr::read(&this.owned) };
+        owned.state
+    }
+
+    /// Get the base `doca_task` of the task
+    #[inline]
+    unsafe fn as_task(&self) -> *mut ffi::doca_task {
+        T::as_task(self.inner_ptr())
+    }
+
+    /// Take back a completed task from DOCA
+    #[inline]
+    unsafe fn from_completion(task: *mut T, task_user_data: ffi::doca_data) -> Self {
+        Self {
+            inner: NonNull::new_unchecked(task),
+            owned: Box::from_raw(task_user_data.ptr as *mut Owned<T::State>),
+        }
+    }
+}
+
+/// The completion callback of a task type among the callbacks of the context
+pub(crate) trait TaskCompletion: RawTask + Sized {
+    /// The error handed to the completion callback with a failed task
+    type Error;
+
+    /// Get the completion callback of the task type
+    fn callback(
+        callbacks: &mut CtxCallbacks,
+    ) -> &mut OnCompletion<Task<Self>, Result<(), Self::Error>>;
+
+    /// Get the error of a failed task from its status
+    fn error(status: DOCAError) -> Self::Error;
+}
+
+/// The DOCA success callback of the tasks of type `T`
+pub(crate) unsafe extern "C" fn success_cb<T: TaskCompletion>(
+    task: *mut T,
+    task_user_data: ffi::doca_data,
+    ctx_user_data: ffi::doca_data,
+) {
+    completed(task, task_user_data, ctx_user_data, Ok(()));
+}
+
+/// The DOCA error callback of the tasks of type `T`
+pub(crate) unsafe extern "C" fn error_cb<T: TaskCompletion>(
+    task: *mut T,
+    task_user_data: ffi::doca_data,
+    ctx_user_data: ffi::doca_data,
+) {
+    let status = ffi::doca_task_get_status(T::as_task(task));
+    completed(task, task_user_data, ctx_user_data, Err(T::error(status)));
+}
+
+/// Hand a completed task to the completion callback of its type
+unsafe fn completed<T: TaskCompletion>(
+    task: *mut T,
+    task_user_data: ffi::doca_data,
+    ctx_user_data: ffi::doca_data,
+    result: Result<(), T::Error>,
+) {
+    let task = Task::from_completion(task, task_user_data);
+    CtxCallbacks::with_user_data(ctx_user_data, |callbacks| {
+        if let Some(cb) = T::callback(callbacks).as_mut() {
+            cb(task, result);
+        }
+    });
+}
@@ -0,0 +1,752 @@
+//! Wrapper for DOCA RDMA. It provides the ability of sending and receiving messages,
+//! and reading and writing the memory of a remote peer with RDMA, over the same
+//! [`crate::DOCAMmap`] and [`DOCABuffer`] used by the other engines.
+//!
+//! It basically contains the following core structs:
+//! - [`RdmaEngine`]: The RDMA Engine of DOCA, created on a device.
+//! It is connected to the engine of the remote peer once its context is started:
+//! each side exports its connection details with [`PeContext::export_rdma`],
+//! sends them to the other side out of band, e.g. over the comm channel or a socket,
+//! and connects with [`PeContext::connect_rdma`].
+//!
+//! - [`RdmaSendTask`] and [`RdmaReceiveTask`]: Send the data of a local buffer, and
+//! receive a message into a local buffer.
+//!
+//! - [`RdmaReadTask`] and [`RdmaWriteTask`]: Read the data of a remote buffer into a
+//! local buffer, and write the data of a local buffer into a remote buffer.
+//! The remote buffer is allocated from a mmap created with
+//! [`crate::DOCAMmap::new_from_export`] on the descriptor exported by the peer
+//! with [`crate::DOCAMmap::export_rdma`].
+//!
+//! The RDMA library is only available since DOCA 2.0, which has no work queue, so the
+//! engine is built on the progress engine (see [`crate::pe`]) rather than on `DOCAContext`
+//! and `DOCAWorkQueue`, and requires the `rdma` and `doca-2-x` features.
+//!
+//! # Examples
+//!
+//! ``` rust, no_run
+//! use doca::pe::{PeContext, ProgressEngine};
+//! use doca::rdma::{RdmaEngine, RdmaSendTask};
+//!
+//! let device = doca::device::open_device_with_pci("03:00.0").unwrap();
+//! let pe = ProgressEngine::new().unwrap();
+//! let rdma = RdmaEngine::new(&device).unwrap();
+//! rdma.set_permissions(doca::rdma::ACCESS_LOCAL_READ_WRITE).unwrap();
+//!
+//! let ctx = PeContext::builder(&rdma, &pe)
+//!     .rdma_send(16, |task, result| {
+//!         println!("task {} finished: {:?}", task.user_data(), result);
+//!     })
+//!     .start()
+//!     .unwrap();
+//!
+//! // Exchange the connection details with the remote peer
+//! let local = ctx.export_rdma().unwrap();
+//! # fn exchange(_: Vec<u8>) -> Vec<u8> { unimplemented!() }
+//! let remote = exchange(local);
+//! ctx.connect_rdma(&remote).unwrap();
+//!
+//! # fn buffer() -> doca::DOCABuffer { unimplemented!() }
+//! let task = RdmaSendTask::new(&ctx, buffer(), 1).unwrap();
+//! task.submit().map_err(|(e, _task)| e).unwrap();
+//! pe.progress_until_idle().unwrap();
+//! ```
+//!
+
+use std::ffi::c_void;
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+use crate::context::EngineToContext;
+use crate::pe::task::{self, RawTask, TaskCompletion};
+use crate::pe::{CtxCallbacks, OnCompletion, PeContext, PeContextBuilder, Task};
+use crate::{DOCABuffer, DOCAError, DOCAResult, DevContext};
+
+/// Only allow the local access to the memory
+pub const ACCESS_LOCAL_READ_WRITE: u32 = ffi::DOCA_ACCESS_FLAG_LOCAL_READ_WRITE;
+/// Allow the remote peer to read the memory
+pub const ACCESS_RDMA_READ: u32 = ffi::DOCA_ACCESS_FLAG_RDMA_READ;
+/// Allow the remote peer to write the memory
+pub const ACCESS_RDMA_WRITE: u32 = ffi::DOCA_ACCESS_FLAG_RDMA_WRITE;
+
+/// DOCA RDMA engine instance
+pub struct RdmaEngine {
+    inner: NonNull<ffi::doca_rdma>,
+
+    // The engine is created on a device,
+    // which should be closed after the engine is destroyed
+    #[allow(dead_code)]
+    dev: Arc<DevContext>,
+}
+
+unsafe impl Sync for RdmaEngine {}
+unsafe impl Send for RdmaEngine {}
+
+impl Drop for RdmaEngine {
+    fn drop(&mut self) {
+        let ret = unsafe { ffi::doca_rdma_destroy(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            panic!("Failed to destory rdma engine!");
+        }
+
+        trace!(rdma = ?self.inner, "RDMA Engine is dropped");
+    }
+}
+
+/// Implementation `EngineToContext` Trait for RDMA Engine
+impl EngineToContext for RdmaEngine {
+    unsafe fn to_ctx(&self) -> *mut ffi::doca_ctx {
+        ffi::doca_rdma_as_ctx(self.inner_ptr())
+    }
+}
+
+impl RdmaEngine {
+    /// Create a DOCA RDMA instance on the device.
+    pub fn new(dev: &Arc<DevContext>) -> DOCAResult<Arc<Self>> {
+        let mut rdma: *mut ffi::doca_rdma = std::ptr::null_mut();
+        let ret = unsafe { ffi::doca_rdma_create(dev.inner_ptr(), &mut rdma as *mut _) };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(Arc::new(Self {
+            inner: unsafe { NonNull::new_unchecked(rdma) },
+            dev: dev.clone(),
+        }))
+    }
+
+    /// Set the access the remote peer has to the memory of the local tasks,
+    /// a mask of `ACCESS_*`. It should be set before the context is started.
+    pub fn set_permissions(&self, mask: u32) -> DOCAResult<()> {
+        let ret = unsafe { ffi::doca_rdma_set_permissions(self.inner_ptr(), mask) };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+        Ok(())
+    }
+
+    /// Set the size of the send queue. It should be set before the context is started.
+    pub fn set_send_queue_size(&self, size: u32) -> DOCAResult<()> {
+        let ret = unsafe { ffi::doca_rdma_set_send_queue_size(self.inner_ptr(), size) };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+        Ok(())
+    }
+
+    /// Set the size of the receive queue. It should be set before the context is started.
+    pub fn set_recv_queue_size(&self, size: u32) -> DOCAResult<()> {
+        let ret = unsafe { ffi::doca_rdma_set_recv_queue_size(self.inner_ptr(), size) };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+        Ok(())
+    }
+
+    /// Set the GID index of the port, which is required by RoCE.
+    /// It should be set before the context is started.
+    pub fn set_gid_index(&self, gid_index: u32) -> DOCAResult<()> {
+        let ret = unsafe { ffi::doca_rdma_set_gid_index(self.inner_ptr(), gid_index) };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+        Ok(())
+    }
+
+    /// Get the inner pointer of the DOCA RDMA instance.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_rdma {
+        self.inner.as_ptr()
+    }
+}
+
+impl PeContext<RdmaEngine> {
+    /// Export the connection details of the started context.
+    ///
+    /// The details are copied out of the engine, and should be sent to the remote peer,
+    /// which connects to the local engine with them.
+    pub fn export_rdma(&self) -> DOCAResult<Vec<u8>> {
+        let mut len: usize = 0;
+        let mut details: *const c_void = std::ptr::null();
+        let ret = unsafe {
+            ffi::doca_rdma_export(
+                self.engine().inner_ptr(),
+                &mut details as *mut _,
+                &mut len as *mut usize,
+            )
+        };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        if details.is_null() {
+            return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+        }
+
+        // the details are only valid as long as the engine, so take a copy of them
+        Ok(unsafe { std::slice::from_raw_parts(details as *const u8, len) }.to_vec())
+    }
+
+    /// Connect the started context to the remote peer with its exported connection details.
+    pub fn connect_rdma(&self, remote: &[u8]) -> DOCAResult<()> {
+        let ret = unsafe {
+            ffi::doca_rdma_connect(
+                self.engine().inner_ptr(),
+                remote.as_ptr() as *const c_void,
+                remote.len(),
+            )
+        };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+        Ok(())
+    }
+}
+
+/// What a send or receive task owns
+pub struct RdmaMsgState {
+    // Ensure that the context is stopped after all its tasks are freed
+    #[allow(dead_code)]
+    ctx: Arc<PeContext<RdmaEngine>>,
+    buf: DOCABuffer,
+}
+
+/// What a read or write task owns
+pub struct RdmaCopyState {
+    // Ensure that the context is stopped after all its tasks are freed
+    #[allow(dead_code)]
+    ctx: Arc<PeContext<RdmaEngine>>,
+    src_buf: DOCABuffer,
+    dst_buf: DOCABuffer,
+}
+
+impl RawTask for ffi::doca_rdma_task_send {
+    type State = RdmaMsgState;
+
+    unsafe fn as_task(task: *mut Self) -> *mut ffi::doca_task {
+        ffi::doca_rdma_task_send_as_task(task)
+    }
+}
+
+impl TaskCompletion for ffi::doca_rdma_task_send {
+    type Error = DOCAError;
+
+    fn callback(callbacks: &mut CtxCallbacks) -> &mut OnCompletion<RdmaSendTask> {
+        &mut callbacks.rdma_send
+    }
+
+    fn error(status: DOCAError) -> DOCAError {
+        status
+    }
+}
+
+impl RawTask for ffi::doca_rdma_task_receive {
+    type State = RdmaMsgState;
+
+    unsafe fn as_task(task: *mut Self) -> *mut ffi::doca_task {
+        ffi::doca_rdma_task_receive_as_task(task)
+    }
+}
+
+impl TaskCompletion for ffi::doca_rdma_task_receive {
+    type Error = DOCAError;
+
+    fn callback(callbacks: &mut CtxCallbacks) -> &mut OnCompletion<RdmaReceiveTask> {
+        &mut callbacks.rdma_receive
+    }
+
+    fn error(status: DOCAError) -> DOCAError {
+        status
+    }
+}
+
+impl RawTask for ffi::doca_rdma_task_read {
+    type State = RdmaCopyState;
+
+    unsafe fn as_task(task: *mut Self) -> *mut ffi::doca_task {
+        ffi::doca_rdma_task_read_as_task(task)
+    }
+}
+
+impl TaskCompletion for ffi::doca_rdma_task_read {
+    type Error = DOCAError;
+
+    fn callback(callbacks: &mut CtxCallbacks) -> &mut OnCompletion<RdmaReadTask> {
+        &mut callbacks.rdma_read
+    }
+
+    fn error(status: DOCAError) -> DOCAError {
+        status
+    }
+}
+
+impl RawTask for ffi::doca_rdma_task_write {
+    type State = RdmaCopyState;
+
+    unsafe fn as_task(task: *mut Self) -> *mut ffi::doca_task {
+        ffi::doca_rdma_task_write_as_task(task)
+    }
+}
+
+impl TaskCompletion for ffi::doca_rdma_task_write {
+    type Error = DOCAError;
+
+    fn callback(callbacks: &mut CtxCallbacks) -> &mut OnCompletion<RdmaWriteTask> {
+        &mut callbacks.rdma_write
+    }
+
+    fn error(status: DOCAError) -> DOCAError {
+        status
+    }
+}
+
+/// A DOCA RDMA send task, sending the data of the local buffer
+/// to a receive task of the remote peer.
+pub type RdmaSendTask = Task<ffi::doca_rdma_task_send>;
+
+impl RdmaSendTask {
+    /// Allocate a send task on the started context.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NO_MEMORY`: the number of tasks set by
+    ///  [`PeContextBuilder::rdma_send`] are all allocated.
+    ///  - `DOCA_ERROR_BAD_STATE`: the context is not running.
+    ///
+    pub fn new(
+        ctx: &Arc<PeContext<RdmaEngine>>,
+        buf: DOCABuffer,
+        user_data: u64,
+    ) -> DOCAResult<Self> {
+        let state = RdmaMsgState {
+            ctx: ctx.clone(),
+            buf,
+        };
+
+        unsafe {
+            Self::alloc(state, user_data, |state, task_user_data, task| {
+                ffi::doca_rdma_task_send_allocate_init(
+                    ctx.engine().inner_ptr(),
+                    state.buf.inner_ptr(),
+                    task_user_data,
+                    task,
+                )
+            })
+        }
+        .map_err(|(e, _state)| e)
+    }
+
+    /// Free the task and return its buffer.
+    pub fn into_buffer(self) -> DOCABuffer {
+        self.into_state().buf
+    }
+
+    /// Get the local buffer
+    #[inline]
+    pub fn buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().buf
+    }
+}
+
+/// A DOCA RDMA receive task, appending a message sent by the remote peer
+/// to the data of the local buffer.
+pub type RdmaReceiveTask = Task<ffi::doca_rdma_task_receive>;
+
+impl RdmaReceiveTask {
+    /// Allocate a receive task on the started context.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NO_MEMORY`: the number of tasks set by
+    ///  [`PeContextBuilder::rdma_receive`] are all allocated.
+    ///  - `DOCA_ERROR_BAD_STATE`: the context is not running.
+    ///
+    pub fn new(
+        ctx: &Arc<PeContext<RdmaEngine>>,
+        buf: DOCABuffer,
+        user_data: u64,
+    ) -> DOCAResult<Self> {
+        let state = RdmaMsgState {
+            ctx: ctx.clone(),
+            buf,
+        };
+
+        unsafe {
+            Self::alloc(state, user_data, |state, task_user_data, task| {
+                ffi::doca_rdma_task_receive_allocate_init(
+                    ctx.engine().inner_ptr(),
+                    state.buf.inner_ptr(),
+                    task_user_data,
+                    task,
+                )
+            })
+        }
+        .map_err(|(e, _state)| e)
+    }
+
+    /// Free the task and return its buffer.
+    pub fn into_buffer(self) -> DOCABuffer {
+        self.into_state().buf
+    }
+
+    /// Get the local buffer
+    #[inline]
+    pub fn buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().buf
+    }
+}
+
+/// A DOCA RDMA read task, appending the data of the remote source buffer
+/// to the local destination buffer.
+pub type RdmaReadTask = Task<ffi::doca_rdma_task_read>;
+
+impl RdmaReadTask {
+    /// Allocate a read task on the started context.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NO_MEMORY`: the number of tasks set by
+    ///  [`PeContextBuilder::rdma_read`] are all allocated.
+    ///  - `DOCA_ERROR_BAD_STATE`: the context is not running.
+    ///
+    pub fn new(
+        ctx: &Arc<PeContext<RdmaEngine>>,
+        src_buf: DOCABuffer,
+        dst_buf: DOCABuffer,
+        user_data: u64,
+    ) -> DOCAResult<Self> {
+        let state = RdmaCopyState {
+            ctx: ctx.clone(),
+            src_buf,
+            dst_buf,
+        };
+
+        unsafe {
+            Self::alloc(state, user_data, |state, task_user_data, task| {
+                ffi::doca_rdma_task_read_allocate_init(
+                    ctx.engine().inner_ptr(),
+                    state.src_buf.inner_ptr(),
+                    state.dst_buf.inner_ptr(),
+                    task_user_data,
+                    task,
+                )
+            })
+        }
+        .map_err(|(e, _state)| e)
+    }
+
+    /// Free the task and return its source and destination buffers.
+    pub fn into_buffers(self) -> (DOCABuffer, DOCABuffer) {
+        let state = self.into_state();
+        (state.src_buf, state.dst_buf)
+    }
+
+    /// Get the remote source buffer
+    #[inline]
+    pub fn src_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().src_buf
+    }
+
+    /// Get the local destination buffer
+    #[inline]
+    pub fn dst_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().dst_buf
+    }
+}
+
+/// A DOCA RDMA write task, writing the data of the local source buffer
+/// into the remote destination buffer.
+pub type RdmaWriteTask = Task<ffi::doca_rdma_task_write>;
+
+impl RdmaWriteTask {
+    /// Allocate a write task on the started context.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NO_MEMORY`: the number of tasks set by
+    ///  [`PeContextBuilder::rdma_write`] are all allocated.
+    ///  - `DOCA_ERROR_BAD_STATE`: the context is not running.
+    ///
+    pub fn new(
+        ctx: &Arc<PeContext<RdmaEngine>>,
+        src_buf: DOCABuffer,
+        dst_buf: DOCABuffer,
+        user_data: u64,
+    ) -> DOCAResult<Self> {
+        let state = RdmaCopyState {
+            ctx: ctx.clone(),
+            src_buf,
+            dst_buf,
+        };
+
+        unsafe {
+            Self::alloc(state, user_data, |state, task_user_data, task| {
+                ffi::doca_rdma_task_write_allocate_init(
+                    ctx.engine().inner_ptr(),
+                    state.src_buf.inner_ptr(),
+                    state.dst_buf.inner_ptr(),
+                    task_user_data,
+                    task,
+                )
+            })
+        }
+        .map_err(|(e, _state)| e)
+    }
+
+    /// Free the task and return its source and destination buffers.
+    pub fn into_buffers(self) -> (DOCABuffer, DOCABuffer) {
+        let state = self.into_state();
+        (state.src_buf, state.dst_buf)
+    }
+
+    /// Get the local source buffer
+    #[inline]
+    pub fn src_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().src_buf
+    }
+
+    /// Get the remote destination buffer
+    #[inline]
+    pub fn dst_buf(&mut self) -> &mut DOCABuffer {
+        &mut self.state_mut().dst_buf
+    }
+}
+
+impl PeContextBuilder<RdmaEngine> {
+    /// Enable the send tasks on the context.
+    ///
+    /// At most `num_tasks` tasks can be allocated at the same time. `on_completion` is
+    /// called with every completed task and its result.
+    pub fn rdma_send<F>(&mut self, num_tasks: u32, on_completion: F) -> &mut Self
+    where
+        F: FnMut(RdmaSendTask, DOCAResult<()>) + 'static,
+    {
+        self.callbacks.rdma_send = Some(Box::new(on_completion));
+        self.confs.push(Box::new(move |rdma: &RdmaEngine| {
+            let ret = unsafe {
+                ffi::doca_rdma_task_send_set_conf(
+                    rdma.inner_ptr(),
+                    Some(task::success_cb::<ffi::doca_rdma_task_send>),
+                    Some(task::error_cb::<ffi::doca_rdma_task_send>),
+                    num_tasks,
+                )
+            };
+
+            if ret != DOCAError::DOCA_SUCCESS {
+                return Err(ret);
+            }
+            Ok(())
+        }));
+        self
+    }
+
+    /// Enable the receive tasks on the context.
+    ///
+    /// At most `num_tasks` tasks can be allocated at the same time. `on_completion` is
+    /// called with every completed task and its result.
+    /// The receive tasks should be submitted before the remote peer sends the messages.
+    pub fn rdma_receive<F>(&mut self, num_tasks: u32, on_completion: F) -> &mut Self
+    where
+        F: FnMut(RdmaReceiveTask, DOCAResult<()>) + 'static,
+    {
+        self.callbacks.rdma_receive = Some(Box::new(on_completion));
+        self.confs.push(Box::new(move |rdma: &RdmaEngine| {
+            let ret = unsafe {
+                ffi::doca_rdma_task_receive_set_conf(
+                    rdma.inner_ptr(),
+                    Some(task::success_cb::<ffi::doca_rdma_task_receive>),
+                    Some(task::error_cb::<ffi::doca_rdma_task_receive>),
+                    num_tasks,
+                )
+            };
+
+            if ret != DOCAError::DOCA_SUCCESS {
+                return Err(ret);
+            }
+            Ok(())
+        }));
+        self
+    }
+
+    /// Enable the read tasks on the context.
+    ///
+    /// At most `num_tasks` tasks can be allocated at the same time. `on_completion` is
+    /// called with every completed task and its result.
+    pub fn rdma_read<F>(&mut self, num_tasks: u32, on_completion: F) -> &mut Self
+    where
+        F: FnMut(RdmaReadTask, DOCAResult<()>) + 'static,
+    {
+        self.callbacks.rdma_read = Some(Box::new(on_completion));
+        self.confs.push(Box::new(move |rdma: &RdmaEngine| {
+            let ret = unsafe {
+                ffi::doca_rdma_task_read_set_conf(
+                    rdma.inner_ptr(),
+                    Some(task::success_cb::<ffi::doca_rdma_task_read>),
+                    Some(task::error_cb::<ffi::doca_rdma_task_read>),
+                    num_tasks,
+                )
+            };
+
+            if ret != DOCAError::DOCA_SUCCESS {
+                return Err(ret);
+            }
+            Ok(())
+        }));
+        self
+    }
+
+    /// Enable the write tasks on the context.
+    ///
+    /// At most `num_tasks` tasks can be allocated at the same time. `on_completion` is
+    /// called with every completed task and its result.
+    pub fn rdma_write<F>(&mut self, num_tasks: u32, on_completion: F) -> &mut Self
+    where
+        F: FnMut(RdmaWriteTask, DOCAResult<()>) + 'static,
+    {
+        self.callbacks.rdma_write = Some(Box::new(on_completion));
+        self.confs.push(Box::new(move |rdma: &RdmaEngine| {
+            let ret = unsafe {
+                ffi::doca_rdma_task_write_set_conf(
+                    rdma.inner_ptr(),
+                    Some(task::success_cb::<ffi::doca_rdma_task_write>),
+                    Some(task::error_cb::<ffi::doca_rdma_task_write>),
+                    num_tasks,
+                )
+            };
+
+            if ret != DOCAError::DOCA_SUCCESS {
+                return Err(ret);
+            }
+            Ok(())
+        }));
+        self
+    }
+}
+
+mod tests {
+    // Connect two engines of the same device, then exchange a message
+    // and write and read back the memory of the peer
+    #[test]
+    #[ignore = "needs a DOCA device supporting RDMA"]
+    fn test_rdma_loopback() {
+        use crate::device::DeviceCapability;
+        use crate::pe::ProgressEngine;
+        use crate::rdma::*;
+        use crate::*;
+        use std::cell::RefCell;
+        use std::ptr::NonNull;
+        use std::rc::Rc;
+
+        let dev = devices().unwrap().get(0).unwrap();
+        assert!(dev.supports(DeviceCapability::RdmaSend).unwrap());
+        let device = dev.open().unwrap();
+        let pe = ProgressEngine::new().unwrap();
+
+        let done = Rc::new(RefCell::new(Vec::new()));
+        let start = |permissions| {
+            let rdma = RdmaEngine::new(&device).unwrap();
+            rdma.set_permissions(permissions).unwrap();
+
+            let (send_done, recv_done) = (done.clone(), done.clone());
+            let (read_done, write_done) = (done.clone(), done.clone());
+            PeContext::builder(&rdma, &pe)
+                .rdma_send(1, move |task, result| {
+                    assert_eq!(result, Ok(()));
+                    send_done.borrow_mut().push(task.into_buffer());
+                })
+                .rdma_receive(1, move |task, result| {
+                    assert_eq!(result, Ok(()));
+                    recv_done.borrow_mut().push(task.into_buffer());
+                })
+                .rdma_read(1, move |task, result| {
+                    assert_eq!(result, Ok(()));
+                    read_done.borrow_mut().push(task.into_buffers().1);
+                })
+                .rdma_write(1, move |task, result| {
+                    assert_eq!(result, Ok(()));
+                    write_done.borrow_mut().push(task.into_buffers().0);
+                })
+                .start()
+                .unwrap()
+        };
+        let client = start(ACCESS_LOCAL_READ_WRITE);
+        let server = start(ACCESS_LOCAL_READ_WRITE | ACCESS_RDMA_READ | ACCESS_RDMA_WRITE);
+
+        let client_details = client.export_rdma().unwrap();
+        let server_details = server.export_rdma().unwrap();
+        client.connect_rdma(&server_details).unwrap();
+        server.connect_rdma(&client_details).unwrap();
+
+        let test_len = 64;
+        let mut message = vec![1u8; test_len].into_boxed_slice();
+        let mut received = vec![0u8; test_len].into_boxed_slice();
+        let mut remote = vec![0u8; test_len].into_boxed_slice();
+        let mut read_back = vec![0u8; test_len].into_boxed_slice();
+        let inv = BufferInventory::new(8).unwrap();
+
+        let to_buffer =
+            |buffer: &mut [u8], len: usize| crate::memory::test_buffer(&device, &inv, buffer, len);
+
+        // the message is received by the server
+        RdmaReceiveTask::new(&server, to_buffer(&mut received, 0), 0)
+            .unwrap()
+            .submit()
+            .map_err(|(e, _task)| e)
+            .unwrap();
+        RdmaSendTask::new(&client, to_buffer(&mut message, test_len), 1)
+            .unwrap()
+            .submit()
+            .map_err(|(e, _task)| e)
+            .unwrap();
+        pe.progress_until_idle().unwrap();
+        assert_eq!(received, message);
+
+        // the server exports its memory, which the client imports as a remote mmap
+        let mut server_mmap = DOCAMmap::new().unwrap();
+        let dev_idx = server_mmap.add_device(&device).unwrap();
+        let raw = RawPointer {
+            inner: NonNull::new(remote.as_mut_ptr() as _).unwrap(),
+            payload: test_len,
+        };
+        server_mmap.set_memrange(raw).unwrap();
+        server_mmap
+            .set_permission(ACCESS_LOCAL_READ_WRITE | ACCESS_RDMA_READ | ACCESS_RDMA_WRITE)
+            .unwrap();
+        server_mmap.start().unwrap();
+        let desc = server_mmap.export_rdma(dev_idx).unwrap();
+
+        let client_mmap = Arc::new(DOCAMmap::new_from_export(desc, &device).unwrap());
+        let remote_buf = || {
+            DOCARegisteredMemory::new_from_remote(&client_mmap, raw)
+                .unwrap()
+                .to_buffer(&inv)
+                .unwrap()
+        };
+
+        done.borrow_mut().clear();
+        let src_buf = to_buffer(&mut message, test_len);
+        RdmaWriteTask::new(&client, src_buf, remote_buf(), 2)
+            .unwrap()
+            .submit()
+            .map_err(|(e, _task)| e)
+            .unwrap();
+        pe.progress_until_idle().unwrap();
+        assert_eq!(remote, message);
+
+        let mut src_buf = remote_buf();
+        unsafe { src_buf.set_data(0, test_len).unwrap() };
+        RdmaReadTask::new(&client, src_buf, to_buffer(&mut read_back, 0), 3)
+            .unwrap()
+            .submit()
+            .map_err(|(e, _task)| e)
+            .unwrap();
+        pe.progress_until_idle().unwrap();
+        assert_eq!(read_back, message);
+    }
+}
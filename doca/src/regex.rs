@@ -0,0 +1,466 @@
+//! Wrapper for DOCA RegEx. It provides the ability of
+//! searching data with a set of compiled regular expression rules
+//! using hardware acceleration.
+//!
+//! It basically contains two core structs:
+//! - [`RegexSearchJob`]: A search request over the data of a buffer. It implements
+//! the trait [`ToBaseJob`], which makes it capable for being submitted to the work queue.
+//! Once completed, [`RegexSearchJob::matches`] returns the [`RegexMatch`]es found.
+//!
+//! - [`RegexEngine`]: The RegEx Engine of DOCA. It is created by [`RegexEngineBuilder`],
+//! which loads the rules compiled into a `.rof2.binary` file by the `rxpc` compiler
+//! (see [`compile_rules`]), so they can not change once a [`DOCAContext`] of the engine exists.
+//!
+//! The matches are allocated from a per work queue pool, whose size is
+//! set by [`RegexEngineBuilder::matches_pool_size`]. They are given back to the pool
+//! when read by [`RegexSearchJob::matches`], which borrows the work queue.
+//!
+//! The engine is built on the DOCA 1.5 work queue, and requires the `regex` feature.
+//!
+//! # Examples
+//!
+//! ``` rust, no_run
+//! use doca::regex::RegexEngine;
+//! use doca::context::DOCAContext;
+//! use doca::DOCAWorkQueue;
+//!
+//! let device = doca::device::open_device_with_pci("03:00.0").unwrap();
+//! let regex = RegexEngine::builder()
+//!     .rules_file("/tmp/rules.rof2.binary")
+//!     .matches_pool_size(1024)
+//!     .build()
+//!     .unwrap();
+//!
+//! let ctx = DOCAContext::new(&regex, vec![device]).unwrap();
+//! let mut workq = DOCAWorkQueue::new(1, &ctx).unwrap();
+//!
+//! # let buf: doca::DOCABuffer = unimplemented!();
+//! let mut job = workq.create_regex_search_job(buf);
+//! workq.submit(&job).unwrap();
+//!
+//! let event = loop {
+//!     if let Ok(event) = workq.poll_completion() {
+//!         break event;
+//!     }
+//! };
+//! for m in job.matches(&workq, &event).unwrap() {
+//!     println!("rule {} matched {} bytes at {}", m.rule_id, m.len, m.offset);
+//! }
+//! ```
+//!
+
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+use crate::context::work_queue::ToBaseJob;
+use crate::context::EngineToContext;
+use crate::{DOCABuffer, DOCAError, DOCAResult};
+
+pub use crate::context::work_queue::{DOCAEvent, DOCAWorkQueue};
+pub use crate::context::DOCAContext;
+
+/// DOCA RegEx engine instance
+pub struct RegexEngine {
+    inner: NonNull<ffi::doca_regex>,
+}
+
+unsafe impl Sync for RegexEngine {}
+unsafe impl Send for RegexEngine {}
+
+impl Drop for RegexEngine {
+    fn drop(&mut self) {
+        let ret = unsafe { ffi::doca_regex_destroy(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            panic!("Failed to destory regex engine!");
+        }
+
+        trace!(regex = ?self.inner, "RegEx Engine is dropped");
+    }
+}
+
+/// Implementation `EngineToContext` Trait for RegEx Engine
+impl EngineToContext for RegexEngine {
+    unsafe fn to_ctx(&self) -> *mut ffi::doca_ctx {
+        ffi::doca_regex_as_ctx(self.inner_ptr())
+    }
+}
+
+impl RegexEngine {
+    /// Create a DOCA RegEx instance without rules, see [`RegexEngine::builder`].
+    pub fn new() -> DOCAResult<Arc<Self>> {
+        Self::builder().build()
+    }
+
+    /// Start configuring a DOCA RegEx instance.
+    pub fn builder() -> RegexEngineBuilder {
+        RegexEngineBuilder::default()
+    }
+
+    /// Load the rules compiled for the hardware, i.e. the content of a `.rof2.binary` file.
+    fn load_rules(&self, rules: &[u8]) -> DOCAResult<()> {
+        let ret = unsafe {
+            ffi::doca_regex_set_hardware_compiled_rules(
+                self.inner_ptr(),
+                rules.as_ptr() as *const c_void,
+                rules.len(),
+            )
+        };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+        Ok(())
+    }
+
+    /// Set the number of matches that can be allocated by the jobs of each work queue.
+    fn set_matches_pool_size(&self, nb_matches: u32) -> DOCAResult<()> {
+        let ret = unsafe {
+            ffi::doca_regex_set_workq_matches_memory_pool_size(self.inner_ptr(), nb_matches)
+        };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+        Ok(())
+    }
+
+    /// Get the inner pointer of the DOCA RegEx instance.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_regex {
+        self.inner.as_ptr()
+    }
+}
+
+/// The rules of a RegEx engine, loaded when the engine is built
+enum RegexRules {
+    Compiled(Vec<u8>),
+    File(PathBuf),
+}
+
+/// Collect the configuration of a RegEx engine, which can only be set
+/// before a context of the engine is created, then create the engine.
+#[derive(Default)]
+pub struct RegexEngineBuilder {
+    rules: Option<RegexRules>,
+    matches_pool_size: Option<u32>,
+}
+
+impl RegexEngineBuilder {
+    /// Set the rules compiled for the hardware, i.e. the content of a `.rof2.binary` file.
+    pub fn rules(&mut self, rules: &[u8]) -> &mut Self {
+        self.rules = Some(RegexRules::Compiled(rules.to_vec()));
+        self
+    }
+
+    /// Set the rules from a compiled `.rof2.binary` file, read when the engine is built.
+    pub fn rules_file<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.rules = Some(RegexRules::File(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Set the number of matches that can be allocated by the jobs of each work queue.
+    ///
+    /// A job whose matches do not fit in the pool reports fewer matches than detected.
+    pub fn matches_pool_size(&mut self, nb_matches: u32) -> &mut Self {
+        self.matches_pool_size = Some(nb_matches);
+        self
+    }
+
+    /// Create the DOCA RegEx instance and apply the configuration.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_IO_FAILED`: the rules file can not be read.
+    ///  - Any error returned by `doca_regex_create`, `doca_regex_set_hardware_compiled_rules`
+    ///  or `doca_regex_set_workq_matches_memory_pool_size`.
+    ///
+    pub fn build(&mut self) -> DOCAResult<Arc<RegexEngine>> {
+        let mut regex: *mut ffi::doca_regex = std::ptr::null_mut();
+        let ret = unsafe { ffi::doca_regex_create(&mut regex as *mut _) };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        let engine = RegexEngine {
+            inner: unsafe { NonNull::new_unchecked(regex) },
+        };
+
+        match &self.rules {
+            Some(RegexRules::Compiled(rules)) => engine.load_rules(rules)?,
+            Some(RegexRules::File(path)) => {
+                let rules = std::fs::read(path).map_err(|_| DOCAError::DOCA_ERROR_IO_FAILED)?;
+                engine.load_rules(&rules)?;
+            }
+            None => {}
+        }
+
+        if let Some(nb_matches) = self.matches_pool_size {
+            engine.set_matches_pool_size(nb_matches)?;
+        }
+
+        Ok(Arc::new(engine))
+    }
+}
+
+/// Compile a rules file with the `rxpc` compiler shipped with DOCA.
+///
+/// The compiled rules are written to `<out_prefix>.rof2.binary`,
+/// whose path is returned on success.
+pub fn compile_rules<P: AsRef<Path>, Q: AsRef<Path>>(
+    rules: P,
+    out_prefix: Q,
+) -> std::io::Result<PathBuf> {
+    let out_prefix = out_prefix.as_ref();
+    let status = Command::new("rxpc")
+        .arg("-f")
+        .arg(rules.as_ref())
+        .arg("-o")
+        .arg(out_prefix)
+        .status()?;
+
+    if !status.success() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("rxpc failed with {}", status),
+        ));
+    }
+
+    let mut path = out_prefix.as_os_str().to_owned();
+    path.push(".rof2.binary");
+    Ok(PathBuf::from(path))
+}
+
+/// A match found by a search job
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RegexMatch {
+    /// The ID of the matching rule
+    pub rule_id: u32,
+    /// The offset of the match from the start of the searched data
+    pub offset: usize,
+    /// The length of the match
+    pub len: usize,
+}
+
+/// A DOCA RegEx search request
+pub struct RegexSearchJob {
+    pub(crate) inner: ffi::doca_regex_job_search,
+
+    #[allow(dead_code)]
+    ctx: Arc<DOCAContext<RegexEngine>>,
+
+    buff: Option<DOCABuffer>,
+
+    // DOCA writes the result here once the job is done,
+    // so it must not move while the job is in flight
+    result: Box<ffi::doca_regex_search_result>,
+}
+
+// SAFETY: the raw pointers in the DOCA job point to the `doca_buf` of `buff`
+// and to the boxed `result`, which the job owns, and the context is shared
+// through an `Arc`. The matches of the result belong to the pool of the work queue,
+// they are only read and given back by `matches`, which borrows the work queue.
+unsafe impl Send for RegexSearchJob {}
+
+/// Implementation of `ToBaseJob` Trait
+impl ToBaseJob for RegexSearchJob {
+    fn to_base(&self) -> &ffi::doca_job {
+        &self.inner.base
+    }
+}
+
+impl RegexSearchJob {
+    /// Create a search job on the given context
+    pub(crate) fn new(ctx: &Arc<DOCAContext<RegexEngine>>, buf: DOCABuffer) -> Self {
+        let mut res = Self {
+            inner: Default::default(),
+            ctx: ctx.clone(),
+            buff: None,
+            result: Box::default(),
+        };
+
+        res.inner.base.ctx = unsafe { ctx.inner_ptr() };
+        res.inner.base.flags = ffi::DOCA_JOB_FLAGS_NONE as i32;
+        res.inner.base.type_ = ffi::DOCA_REGEX_JOB_SEARCH as i32;
+        res.inner.buffer = unsafe { buf.inner_ptr() };
+        res.buff = Some(buf);
+        res.inner.result = &mut *res.result as *mut _;
+        res
+    }
+
+    /// Set the rule groups to search with, 0 is an unused slot.
+    /// By default all the rules are used.
+    #[inline]
+    pub fn set_rule_groups(&mut self, groups: [u16; 4]) -> &mut Self {
+        self.inner.rule_group_ids = groups;
+        self
+    }
+
+    /// Allow the job to be batched with the following ones
+    #[inline]
+    pub fn set_allow_batching(&mut self, allow: bool) -> &mut Self {
+        self.inner.allow_batching = allow as u8;
+        self
+    }
+
+    /// Set the data pointer of the searched buffer
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the data is out of the buffer.
+    ///
+    #[inline]
+    pub fn set_data(&mut self, offset: usize, payload: usize) -> DOCAResult<()> {
+        match self.buff.as_mut() {
+            Some(f) => unsafe { f.set_data(offset, payload) },
+            None => Ok(()),
+        }
+    }
+
+    /// Set mark for user data
+    #[inline]
+    pub fn set_user_data(&mut self, user_data: u64) -> &mut Self {
+        self.inner.base.user_data.u64_ = user_data;
+        self
+    }
+
+    /// Get the mark for user data
+    #[inline]
+    pub fn user_data(&self) -> u64 {
+        unsafe { self.inner.base.user_data.u64_ }
+    }
+
+    /// Get the matches from the completion event of the job,
+    /// polled from `workq`, the work queue the job was submitted to.
+    ///
+    /// The matches are handed back to the pool of the work queue, so the result
+    /// can be read once per submission. The matches of a result which is not read
+    /// stay out of the pool until the work queue is dropped.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the event does not belong to this job.
+    ///  - The error of the job reported by the event.
+    ///
+    pub fn matches(
+        &mut self,
+        workq: &DOCAWorkQueue<RegexEngine>,
+        event: &DOCAEvent,
+    ) -> DOCAResult<Vec<RegexMatch>> {
+        if event.user_mark() != self.user_data() {
+            return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+        }
+
+        let ret = event.result();
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        let mut res = Vec::with_capacity(self.result.num_matches as usize);
+        let mut m = self.result.matches;
+        while let Some(matched) = unsafe { m.as_ref() } {
+            res.push(RegexMatch {
+                rule_id: matched.rule_id,
+                offset: matched.match_start as usize,
+                len: matched.length as usize,
+            });
+            m = matched.next;
+        }
+        self.release_matches(workq);
+
+        Ok(res)
+    }
+
+    /// Get the number of matches detected by the last search, which is larger than
+    /// the number of returned matches if the pool is exhausted
+    #[inline]
+    pub fn detected_matches(&self) -> u32 {
+        self.result.detected_matches
+    }
+
+    /// Return the searched buffer
+    pub fn into_buffer(mut self) -> DOCABuffer {
+        self.buff.take().unwrap()
+    }
+
+    /// Give the matches of the result back to the pool of the work queue,
+    /// which is borrowed so that the pool outlives the call
+    fn release_matches(&mut self, _workq: &DOCAWorkQueue<RegexEngine>) {
+        let mut m = self.result.matches;
+        while !m.is_null() {
+            let next = unsafe { (*m).next };
+            unsafe {
+                ffi::doca_regex_mempool_put_obj(self.result.matches_mempool, m as *mut c_void)
+            };
+            m = next;
+        }
+        self.result.matches = std::ptr::null_mut();
+        self.result.num_matches = 0;
+    }
+}
+
+impl DOCAWorkQueue<RegexEngine> {
+    /// Create a search job over the data of the buffer
+    pub fn create_regex_search_job(&self, buf: DOCABuffer) -> RegexSearchJob {
+        RegexSearchJob::new(&self.ctx, buf)
+    }
+}
+
+mod tests {
+    #[test]
+    #[ignore = "needs a DOCA device supporting RegEx and DOCA_REGEX_TEST_RULES"]
+    fn test_regex_search() {
+        use crate::device::DeviceCapability;
+        use crate::regex::*;
+        use crate::*;
+
+        // the rules are compiled from a file containing the single rule `1, hello`
+        let rules = std::env::var("DOCA_REGEX_TEST_RULES")
+            .expect("DOCA_REGEX_TEST_RULES is the path of the compiled rules");
+
+        let dev = devices().unwrap().get(0).unwrap();
+        assert!(dev.supports(DeviceCapability::RegexSearch).unwrap());
+        let device = dev.open().unwrap();
+
+        let regex = RegexEngine::builder()
+            .rules_file(rules)
+            .matches_pool_size(16)
+            .build()
+            .unwrap();
+        let ctx = DOCAContext::new(&regex, vec![device.clone()]).unwrap();
+        let mut workq = DOCAWorkQueue::new(1, &ctx).unwrap();
+
+        let mut data = b"say hello to hello".to_vec().into_boxed_slice();
+        let inv = BufferInventory::new(1).unwrap();
+        let data_len = data.len();
+        let buf = crate::memory::test_buffer(&device, &inv, &mut data, data_len);
+
+        let mut job = workq.create_regex_search_job(buf);
+        workq.submit(&job).unwrap();
+        let event = loop {
+            if let Ok(event) = workq.poll_completion() {
+                break event;
+            }
+        };
+
+        let mut matches = job.matches(&workq, &event).unwrap();
+        matches.sort_by_key(|m| m.offset);
+        assert_eq!(
+            matches,
+            vec![
+                RegexMatch {
+                    rule_id: 1,
+                    offset: 4,
+                    len: 5
+                },
+                RegexMatch {
+                    rule_id: 1,
+                    offset: 13,
+                    len: 5
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! Check whether DOCA can be used on this machine.
+//!
+//! With the `dlopen` feature the DOCA libraries are not linked into the
+//! binary but loaded at runtime, so the same binary can start on hosts
+//! without DOCA installed and take a non-DPU code path there.
+//! Without the feature DOCA is linked and always available.
+//!
+//! Any DOCA API called while [`is_available`] returns `false` panics,
+//! so the applications should check it (or discover the devices with
+//! [`crate::devices`]) before using DOCA.
+//!
+//! ``` rust, no_run
+//! use doca::runtime::RuntimeError;
+//!
+//! match doca::devices() {
+//!     Ok(devices) => println!("{} DOCA devices", devices.num_devices()),
+//!     Err(RuntimeError::LibraryNotFound(e)) => println!("DOCA is not installed: {}", e),
+//!     Err(RuntimeError::Doca(e)) => panic!("failed to list the devices: {:?}", e),
+//! }
+//! ```
+//!
+
+use std::fmt;
+
+use crate::DOCAError;
+
+/// Get the list of all available local devices, see [`crate::device::devices`].
+pub use crate::device::devices;
+
+/// Errors of the runtime loading of DOCA
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuntimeError {
+    /// The DOCA libraries are not found on this machine,
+    /// the message tells which one and why
+    LibraryNotFound(String),
+    /// The DOCA libraries are loaded, but the call failed
+    Doca(DOCAError),
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::LibraryNotFound(e) => write!(f, "DOCA library not found: {}", e),
+            RuntimeError::Doca(e) => write!(f, "DOCA error: {:?}", e),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+impl From<DOCAError> for RuntimeError {
+    fn from(e: DOCAError) -> Self {
+        RuntimeError::Doca(e)
+    }
+}
+
+/// A missing library becomes `DOCA_ERROR_NOT_FOUND`
+impl From<RuntimeError> for DOCAError {
+    fn from(e: RuntimeError) -> Self {
+        match e {
+            RuntimeError::LibraryNotFound(_) => DOCAError::DOCA_ERROR_NOT_FOUND,
+            RuntimeError::Doca(e) => e,
+        }
+    }
+}
+
+/// Load the DOCA libraries if needed.
+///
+/// # Errors
+///
+///  - `LibraryNotFound`: the DOCA libraries can not be loaded.
+///
+pub fn load() -> Result<(), RuntimeError> {
+    #[cfg(feature = "dlopen")]
+    ffi::dlopen::load().map_err(|e| RuntimeError::LibraryNotFound(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Check whether the DOCA libraries are available on this machine.
+pub fn is_available() -> bool {
+    load().is_ok()
+}
+
+mod tests {
+    #[test]
+    fn test_runtime_devices() {
+        use crate::runtime::{self, RuntimeError};
+
+        match runtime::devices() {
+            Ok(devices) => assert!(runtime::is_available() && !devices.is_empty()),
+            Err(RuntimeError::LibraryNotFound(e)) => {
+                assert!(!runtime::is_available());
+                println!("DOCA is not available: {}", e);
+            }
+            Err(RuntimeError::Doca(e)) => panic!("failed to list the devices: {:?}", e),
+        }
+    }
+}
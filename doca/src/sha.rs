@@ -0,0 +1,575 @@
+//! Wrapper for DOCA SHA. It provides the ability of
+//! hashing data with SHA-1, SHA-256 and SHA-512 using hardware acceleration.
+//!
+//! It basically contains three core structs:
+//! - [`ShaJob`]: A SHA request hashing the source buffer into the response buffer.
+//! It implements the trait [`ToBaseJob`], which makes it capable for being submitted
+//! to the work queue. The algorithm is a type parameter, see [`ShaAlgorithm`],
+//! so that [`ShaJob::digest`] returns a fixed size array.
+//!
+//! - [`ShaPartialSession`]: A partial (streaming) SHA over several submissions.
+//! It borrows the work queue, and submits its [`ShaPartialJob`]s. Every job of the
+//! session but the final one hashes a multiple of [`ShaAlgorithm::BLOCK_SIZE`] bytes,
+//! and the digest is written by the final job.
+//!
+//! - [`ShaEngine`]: The SHA Engine of DOCA. Users should create an instance of
+//! the engine and execute the jobs on a [`DOCAContext`] of the engine.
+//!
+//! The engine is built on the DOCA 1.5 work queue, and requires the `sha` feature.
+//!
+//! # Examples
+//!
+//! ``` rust, no_run
+//! use doca::sha::{Sha256, ShaEngine};
+//! use doca::context::DOCAContext;
+//! use doca::DOCAWorkQueue;
+//!
+//! let device = doca::device::open_device_with_pci("17:00.0").unwrap();
+//! let sha = ShaEngine::new().unwrap();
+//! let ctx = DOCAContext::new(&sha, vec![device]).unwrap();
+//! let mut workq = DOCAWorkQueue::new(1, &ctx).unwrap();
+//!
+//! # let (src_buf, dst_buf): (doca::DOCABuffer, doca::DOCABuffer) = unimplemented!();
+//! let job = workq.create_sha_job::<Sha256>(src_buf, dst_buf).unwrap();
+//! workq.submit(&job).unwrap();
+//!
+//! let event = loop {
+//!     if let Ok(event) = workq.poll_completion() {
+//!         break event;
+//!     }
+//! };
+//! let digest: [u8; 32] = job.digest(&event).unwrap();
+//! ```
+//!
+
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+use crate::context::work_queue::ToBaseJob;
+use crate::context::EngineToContext;
+use crate::{DOCABuffer, DOCAError, DOCAResult};
+
+pub use crate::context::work_queue::{DOCAEvent, DOCAWorkQueue};
+pub use crate::context::DOCAContext;
+
+/// DOCA SHA engine instance
+pub struct ShaEngine {
+    inner: NonNull<ffi::doca_sha>,
+}
+
+unsafe impl Sync for ShaEngine {}
+unsafe impl Send for ShaEngine {}
+
+impl Drop for ShaEngine {
+    fn drop(&mut self) {
+        let ret = unsafe { ffi::doca_sha_destroy(self.inner_ptr()) };
+        if ret != DOCAError::DOCA_SUCCESS {
+            panic!("Failed to destory sha engine!");
+        }
+
+        trace!(sha = ?self.inner, "SHA Engine is dropped");
+    }
+}
+
+/// Implementation `EngineToContext` Trait for SHA Engine
+impl EngineToContext for ShaEngine {
+    unsafe fn to_ctx(&self) -> *mut ffi::doca_ctx {
+        ffi::doca_sha_as_ctx(self.inner_ptr())
+    }
+}
+
+impl ShaEngine {
+    /// Create a DOCA SHA instance.
+    pub fn new() -> DOCAResult<Arc<Self>> {
+        let mut sha: *mut ffi::doca_sha = std::ptr::null_mut();
+        let ret = unsafe { ffi::doca_sha_create(&mut sha as *mut _) };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(Arc::new(Self {
+            inner: unsafe { NonNull::new_unchecked(sha) },
+        }))
+    }
+
+    /// Get the inner pointer of the DOCA SHA instance.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_sha {
+        self.inner.as_ptr()
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// A SHA algorithm supported by the engine: [`Sha1`], [`Sha256`] or [`Sha512`]
+pub trait ShaAlgorithm: private::Sealed {
+    /// The digest, a byte array of `DIGEST_LEN`
+    type Digest: for<'a> TryFrom<&'a [u8]> + Copy + std::fmt::Debug + Eq;
+
+    /// The length of the digest in bytes
+    const DIGEST_LEN: usize;
+
+    /// The block size of the algorithm, the length of the data of
+    /// every non-final partial job must be a multiple of it
+    const BLOCK_SIZE: usize;
+
+    /// Get the DOCA job type of the algorithm
+    #[doc(hidden)]
+    fn job_type(partial: bool) -> ffi::doca_sha_job_types;
+}
+
+/// SHA-1
+#[derive(Clone, Copy, Debug)]
+pub struct Sha1;
+
+/// SHA-256
+#[derive(Clone, Copy, Debug)]
+pub struct Sha256;
+
+/// SHA-512
+#[derive(Clone, Copy, Debug)]
+pub struct Sha512;
+
+impl private::Sealed for Sha1 {}
+impl private::Sealed for Sha256 {}
+impl private::Sealed for Sha512 {}
+
+impl ShaAlgorithm for Sha1 {
+    type Digest = [u8; 20];
+    const DIGEST_LEN: usize = 20;
+    const BLOCK_SIZE: usize = 64;
+
+    fn job_type(partial: bool) -> ffi::doca_sha_job_types {
+        if partial {
+            ffi::DOCA_SHA_JOB_SHA1_PARTIAL
+        } else {
+            ffi::DOCA_SHA_JOB_SHA1
+        }
+    }
+}
+
+impl ShaAlgorithm for Sha256 {
+    type Digest = [u8; 32];
+    const DIGEST_LEN: usize = 32;
+    const BLOCK_SIZE: usize = 64;
+
+    fn job_type(partial: bool) -> ffi::doca_sha_job_types {
+        if partial {
+            ffi::DOCA_SHA_JOB_SHA256_PARTIAL
+        } else {
+            ffi::DOCA_SHA_JOB_SHA256
+        }
+    }
+}
+
+impl ShaAlgorithm for Sha512 {
+    type Digest = [u8; 64];
+    const DIGEST_LEN: usize = 64;
+    const BLOCK_SIZE: usize = 128;
+
+    fn job_type(partial: bool) -> ffi::doca_sha_job_types {
+        if partial {
+            ffi::DOCA_SHA_JOB_SHA512_PARTIAL
+        } else {
+            ffi::DOCA_SHA_JOB_SHA512
+        }
+    }
+}
+
+/// A partial SHA session, hashing a stream of data over several jobs.
+///
+/// The session borrows the work queue it is created on until it is dropped,
+/// so its jobs are submitted and polled through the session.
+pub struct ShaPartialSession<'a, A: ShaAlgorithm> {
+    inner: NonNull<ffi::doca_sha_partial_session>,
+    workq: &'a mut DOCAWorkQueue<ShaEngine>,
+    algorithm: PhantomData<A>,
+}
+
+impl<A: ShaAlgorithm> Drop for ShaPartialSession<'_, A> {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::doca_sha_partial_session_destroy(
+                self.workq.ctx.engine.inner_ptr(),
+                self.workq.inner_ptr(),
+                self.inner_ptr(),
+            )
+        };
+
+        trace!(session = ?self.inner, "SHA Partial Session is dropped");
+    }
+}
+
+impl<'a, A: ShaAlgorithm> ShaPartialSession<'a, A> {
+    /// Create a partial session on the work queue
+    fn new(workq: &'a mut DOCAWorkQueue<ShaEngine>) -> DOCAResult<Self> {
+        let mut session: *mut ffi::doca_sha_partial_session = std::ptr::null_mut();
+        let ret = unsafe {
+            ffi::doca_sha_partial_session_create(
+                workq.ctx.engine.inner_ptr(),
+                workq.inner_ptr(),
+                &mut session as *mut _,
+            )
+        };
+
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        Ok(Self {
+            inner: unsafe { NonNull::new_unchecked(session) },
+            workq,
+            algorithm: PhantomData,
+        })
+    }
+
+    /// Create a job of the session, which is not the final one.
+    ///
+    /// The digest is written into the response buffer of the job marked
+    /// with [`ShaPartialJob::set_final`].
+    pub fn create_job(
+        &self,
+        src_buf: DOCABuffer,
+        dst_buf: DOCABuffer,
+    ) -> DOCAResult<ShaPartialJob<A>> {
+        let mut job = ShaJob::new(&self.workq.ctx, src_buf, dst_buf, true)?;
+        job.inner.session = self.inner.as_ptr();
+        Ok(ShaPartialJob { job })
+    }
+
+    /// Add a job of the session into the work queue
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the job is created by another session.
+    ///  - Any error returned by `doca_workq_submit`.
+    ///
+    pub fn submit(&mut self, job: &ShaPartialJob<A>) -> DOCAResult<()> {
+        if job.job.inner.session != self.inner.as_ptr() {
+            return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+        }
+        self.workq.submit(&job.job)
+    }
+
+    /// Check whether there's a job finished in the work queue
+    #[inline]
+    pub fn poll_completion(&mut self) -> DOCAResult<DOCAEvent> {
+        self.workq.poll_completion()
+    }
+
+    /// Get the inner pointer of the DOCA SHA partial session.
+    pub unsafe fn inner_ptr(&self) -> *mut ffi::doca_sha_partial_session {
+        self.inner.as_ptr()
+    }
+}
+
+/// A DOCA SHA request
+///
+/// The digest is appended to the data of the response (destination) buffer.
+pub struct ShaJob<A: ShaAlgorithm> {
+    pub(crate) inner: ffi::doca_sha_partial_job,
+
+    #[allow(dead_code)]
+    ctx: Arc<DOCAContext<ShaEngine>>,
+
+    src_buff: DOCABuffer,
+    dst_buff: DOCABuffer,
+    // The length of the response data before the job
+    dst_len: usize,
+    partial: bool,
+    algorithm: PhantomData<A>,
+}
+
+// SAFETY: the buffer pointers in the DOCA job point to the `doca_buf`s of `src_buff`
+// and `dst_buff`, which the job owns, and the context is shared through an `Arc`.
+// The session pointer of a partial job is never dereferenced by the job: DOCA only
+// reads it when the job is submitted through `ShaPartialSession::submit`,
+// which borrows the session.
+unsafe impl<A: ShaAlgorithm> Send for ShaJob<A> {}
+
+/// Implementation of `ToBaseJob` Trait
+impl<A: ShaAlgorithm> ToBaseJob for ShaJob<A> {
+    fn to_base(&self) -> &ffi::doca_job {
+        &self.inner.sha_job.base
+    }
+}
+
+impl<A: ShaAlgorithm> ShaJob<A> {
+    /// Create a SHA job on the given context
+    fn new(
+        ctx: &Arc<DOCAContext<ShaEngine>>,
+        src_buf: DOCABuffer,
+        dst_buf: DOCABuffer,
+        partial: bool,
+    ) -> DOCAResult<Self> {
+        let mut res = Self {
+            inner: Default::default(),
+            ctx: ctx.clone(),
+            dst_len: dst_buf.data_len()?,
+            src_buff: src_buf,
+            dst_buff: dst_buf,
+            partial,
+            algorithm: PhantomData,
+        };
+
+        let job = &mut res.inner.sha_job;
+        job.base.ctx = unsafe { ctx.inner_ptr() };
+        job.base.flags = ffi::DOCA_JOB_FLAGS_NONE as i32;
+        job.base.type_ = A::job_type(partial) as i32;
+        job.flags = ffi::DOCA_SHA_JOB_FLAGS_NONE as u64;
+        unsafe {
+            job.req_buf = res.src_buff.inner_ptr();
+            job.resp_buf = res.dst_buff.inner_ptr();
+        }
+        Ok(res)
+    }
+
+    /// Return `true` if the job writes the digest, i.e. it is not a partial job
+    /// or it is the final job of its session
+    #[inline]
+    fn is_final(&self) -> bool {
+        !self.partial
+            || self.inner.sha_job.flags & ffi::DOCA_SHA_JOB_FLAGS_SHA_PARTIAL_FINAL as u64 != 0
+    }
+
+    /// Set the data pointer of the src buffer
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the data is out of the buffer.
+    ///
+    #[inline]
+    pub fn set_src_data(&mut self, offset: usize, payload: usize) -> DOCAResult<()> {
+        unsafe { self.src_buff.set_data(offset, payload) }
+    }
+
+    /// Set the data pointer of the dst buffer, the digest is appended after it
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the data is out of the buffer.
+    ///
+    #[inline]
+    pub fn set_dst_data(&mut self, offset: usize, payload: usize) -> DOCAResult<()> {
+        unsafe { self.dst_buff.set_data(offset, payload)? };
+        self.dst_len = payload;
+        Ok(())
+    }
+
+    /// Set mark for user data
+    #[inline]
+    pub fn set_user_data(&mut self, user_data: u64) -> &mut Self {
+        self.inner.sha_job.base.user_data.u64_ = user_data;
+        self
+    }
+
+    /// Get the mark for user data
+    #[inline]
+    pub fn user_data(&self) -> u64 {
+        unsafe { self.inner.sha_job.base.user_data.u64_ }
+    }
+
+    /// Get the digest from the completion event of the job.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the event does not belong to this job.
+    ///  - `DOCA_ERROR_BAD_STATE`: the job is a non-final partial job, which has no digest.
+    ///  - The error of the job reported by the event.
+    ///
+    pub fn digest(&self, event: &DOCAEvent) -> DOCAResult<A::Digest> {
+        if event.user_mark() != self.user_data() {
+            return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+        }
+
+        let ret = event.result();
+        if ret != DOCAError::DOCA_SUCCESS {
+            return Err(ret);
+        }
+
+        if !self.is_final() {
+            return Err(DOCAError::DOCA_ERROR_BAD_STATE);
+        }
+
+        if self.dst_buff.data_len()? < self.dst_len + A::DIGEST_LEN {
+            return Err(DOCAError::DOCA_ERROR_UNEXPECTED);
+        }
+
+        let digest = unsafe {
+            let data = self.dst_buff.get_data()? as *const u8;
+            std::slice::from_raw_parts(data.add(self.dst_len), A::DIGEST_LEN)
+        };
+        A::Digest::try_from(digest).map_err(|_| DOCAError::DOCA_ERROR_UNEXPECTED)
+    }
+
+    /// Return the source and destination buffers of the job
+    pub fn into_buffers(self) -> (DOCABuffer, DOCABuffer) {
+        (self.src_buff, self.dst_buff)
+    }
+}
+
+/// A job of a [`ShaPartialSession`], which can only be submitted through its session.
+///
+/// Every job but the final one hashes a multiple of [`ShaAlgorithm::BLOCK_SIZE`] bytes.
+pub struct ShaPartialJob<A: ShaAlgorithm> {
+    job: ShaJob<A>,
+}
+
+impl<A: ShaAlgorithm> ShaPartialJob<A> {
+    /// Mark the job as the final one of its partial session,
+    /// the digest is written by the final job only.
+    #[inline]
+    pub fn set_final(&mut self, is_final: bool) -> &mut Self {
+        self.job.inner.sha_job.flags = if is_final {
+            ffi::DOCA_SHA_JOB_FLAGS_SHA_PARTIAL_FINAL as u64
+        } else {
+            ffi::DOCA_SHA_JOB_FLAGS_NONE as u64
+        };
+        self
+    }
+
+    /// Return `true` if the job is the final one of its session
+    #[inline]
+    pub fn is_final(&self) -> bool {
+        self.job.is_final()
+    }
+
+    /// Set the data pointer of the src buffer, see [`ShaJob::set_src_data`]
+    #[inline]
+    pub fn set_src_data(&mut self, offset: usize, payload: usize) -> DOCAResult<()> {
+        self.job.set_src_data(offset, payload)
+    }
+
+    /// Set the data pointer of the dst buffer, see [`ShaJob::set_dst_data`]
+    #[inline]
+    pub fn set_dst_data(&mut self, offset: usize, payload: usize) -> DOCAResult<()> {
+        self.job.set_dst_data(offset, payload)
+    }
+
+    /// Set mark for user data
+    #[inline]
+    pub fn set_user_data(&mut self, user_data: u64) -> &mut Self {
+        self.job.set_user_data(user_data);
+        self
+    }
+
+    /// Get the mark for user data
+    #[inline]
+    pub fn user_data(&self) -> u64 {
+        self.job.user_data()
+    }
+
+    /// Get the digest from the completion event of the job, see [`ShaJob::digest`]
+    #[inline]
+    pub fn digest(&self, event: &DOCAEvent) -> DOCAResult<A::Digest> {
+        self.job.digest(event)
+    }
+
+    /// Return the source and destination buffers of the job
+    pub fn into_buffers(self) -> (DOCABuffer, DOCABuffer) {
+        self.job.into_buffers()
+    }
+}
+
+impl DOCAWorkQueue<ShaEngine> {
+    /// Create a SHA job hashing the whole data of the source buffer
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: the data length of the destination buffer can not be read.
+    ///
+    pub fn create_sha_job<A: ShaAlgorithm>(
+        &self,
+        src_buf: DOCABuffer,
+        dst_buf: DOCABuffer,
+    ) -> DOCAResult<ShaJob<A>> {
+        ShaJob::new(&self.ctx, src_buf, dst_buf, false)
+    }
+
+    /// Create a partial SHA session on the work queue,
+    /// which borrows the work queue until it is dropped
+    pub fn create_sha_partial_session<A: ShaAlgorithm>(
+        &mut self,
+    ) -> DOCAResult<ShaPartialSession<'_, A>> {
+        ShaPartialSession::new(self)
+    }
+}
+
+mod tests {
+    #[test]
+    #[ignore = "needs a DOCA device supporting SHA-256"]
+    fn test_sha256_one_shot_and_partial() {
+        use crate::device::DeviceCapability;
+        use crate::sha::*;
+        use crate::*;
+
+        let dev = devices().unwrap().get(0).unwrap();
+        assert!(dev.supports(DeviceCapability::Sha256).unwrap());
+        let device = dev.open().unwrap();
+
+        let sha = ShaEngine::new().unwrap();
+        let ctx = DOCAContext::new(&sha, vec![device.clone()]).unwrap();
+        let mut workq = DOCAWorkQueue::new(1, &ctx).unwrap();
+
+        let mut src_buffer = vec![b'a'; 2 * Sha256::BLOCK_SIZE].into_boxed_slice();
+        let mut dst_buffer = vec![0u8; 256].into_boxed_slice();
+        let inv = BufferInventory::new(16).unwrap();
+
+        let to_buffer =
+            |buffer: &mut [u8], len: usize| crate::memory::test_buffer(&device, &inv, buffer, len);
+        let src_buf = to_buffer(&mut src_buffer, 2 * Sha256::BLOCK_SIZE);
+        let dst_buf = to_buffer(&mut dst_buffer, 0);
+
+        let wait = |workq: &mut DOCAWorkQueue<ShaEngine>| loop {
+            if let Ok(event) = workq.poll_completion() {
+                break event;
+            }
+        };
+
+        // hash the whole source at once
+        let job = workq.create_sha_job::<Sha256>(src_buf, dst_buf).unwrap();
+        workq.submit(&job).unwrap();
+        let one_shot = job.digest(&wait(&mut workq)).unwrap();
+
+        // hash it again block by block
+        let (src_buf, mut dst_buf) = job.into_buffers();
+        unsafe { dst_buf.set_data(0, 0).unwrap() };
+        let mut session = workq.create_sha_partial_session::<Sha256>().unwrap();
+        let wait = |session: &mut ShaPartialSession<Sha256>| loop {
+            if let Ok(event) = session.poll_completion() {
+                break event;
+            }
+        };
+
+        let mut job = session.create_job(src_buf, dst_buf).unwrap();
+        job.set_src_data(0, Sha256::BLOCK_SIZE).unwrap();
+        session.submit(&job).unwrap();
+        assert_eq!(
+            job.digest(&wait(&mut session)),
+            Err(DOCAError::DOCA_ERROR_BAD_STATE)
+        );
+
+        job.set_src_data(Sha256::BLOCK_SIZE, Sha256::BLOCK_SIZE)
+            .unwrap();
+        job.set_dst_data(0, 0).unwrap();
+        job.set_final(true);
+        session.submit(&job).unwrap();
+        let partial = job.digest(&wait(&mut session)).unwrap();
+
+        assert_eq!(one_shot, partial);
+        // SHA-256 of 128 'a'
+        assert_eq!(
+            one_shot,
+            [
+                0x68, 0x36, 0xcf, 0x13, 0xba, 0xc4, 0x00, 0xe9, 0x10, 0x50, 0x71, 0xcd, 0x6a, 0xf4,
+                0x70, 0x84, 0xdf, 0xac, 0xad, 0x4e, 0x5e, 0x30, 0x2c, 0x94, 0xbf, 0xed, 0x24, 0xe0,
+                0x13, 0xaf, 0xb7, 0x3e
+            ]
+        );
+        drop(job);
+        drop(session);
+    }
+}
@@ -0,0 +1,764 @@
+//! Device and port counters of an opened DOCA device.
+//!
+//! The port counters are the ones the mlx5 driver exposes for the device under
+//! `/sys/class/infiniband/<ibdev>/ports/<port>/`, which are also the source
+//! DOCA Telemetry exports from. They are read directly, so they need
+//! neither the DOCA Telemetry service nor an extra DOCA library.
+//!
+//! The PCIe and DMA counters are sampled by the device for DOCA Telemetry
+//! Diagnostics, which needs the `telemetry-diag` feature and DOCA 2.9 or newer.
+//! The device names its counters by data ids, listed by the DOCA Telemetry
+//! Diagnostics documentation of the device, so `DiagConfig` takes the data ids
+//! of the counters to read, and `Telemetry::with_diag` adds them to the snapshots.
+//!
+//! A [`Telemetry`] reads a typed [`TelemetrySnapshot`] on demand, and
+//! [`Telemetry::sample_every`] spawns a [`Sampler`] that reads one snapshot
+//! per period on a background thread.
+//!
+//! # Examples
+//!
+//! ```rust, no_run
+//! use std::time::Duration;
+//! use doca::telemetry::Telemetry;
+//!
+//! let device = doca::device::devices().unwrap().get(0).unwrap().open().unwrap();
+//! let telemetry = Telemetry::new(device).unwrap();
+//!
+//! let sampler = telemetry.sample_every(Duration::from_secs(1));
+//! let mut prev = sampler.recv().unwrap().unwrap();
+//! for snapshot in sampler.iter().flatten() {
+//!     let rates = snapshot.rates_since(&prev);
+//!     println!("rx {:.0} B/s, tx {:.0} B/s", rates.rx_bytes_per_sec, rates.tx_bytes_per_sec);
+//!     prev = snapshot;
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+#[cfg(all(feature = "telemetry-diag", doca_2_x))]
+use std::ptr::NonNull;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+#[cfg(all(feature = "telemetry-diag", doca_2_x))]
+use std::sync::{Mutex, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::device::DevContext;
+use crate::{DOCAError, DOCAResult};
+
+const SYSFS_INFINIBAND: &str = "/sys/class/infiniband";
+
+/// The data counters of the port count in units of 4 bytes.
+const DATA_WORD_SIZE: u64 = 4;
+
+/// How often the device samples the diagnostics counters by default.
+#[cfg(all(feature = "telemetry-diag", doca_2_x))]
+const DEFAULT_DIAG_SAMPLE_PERIOD: Duration = Duration::from_millis(100);
+
+/// The device keeps up to `1 << DIAG_LOG_MAX_SAMPLES` samples between two queries.
+#[cfg(all(feature = "telemetry-diag", doca_2_x))]
+const DIAG_LOG_MAX_SAMPLES: u8 = 2;
+
+/// The counters of the physical port.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PortCounters {
+    /// Bytes received by the port.
+    pub rx_bytes: u64,
+    /// Bytes transmitted by the port.
+    pub tx_bytes: u64,
+    /// Packets received by the port.
+    pub rx_packets: u64,
+    /// Packets transmitted by the port.
+    pub tx_packets: u64,
+    /// Received packets that contained an error.
+    pub rx_errors: u64,
+    /// Outbound packets that were discarded.
+    pub tx_discards: u64,
+}
+
+/// The PCIe traffic of the device, read by DOCA Telemetry Diagnostics.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PcieCounters {
+    /// Bytes the device received from PCIe.
+    pub inbound_bytes: u64,
+    /// Bytes the device sent to PCIe.
+    pub outbound_bytes: u64,
+}
+
+/// The DMA traffic of the device, read by DOCA Telemetry Diagnostics.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DmaCounters {
+    /// Bytes read by the DMA of the device.
+    pub read_bytes: u64,
+    /// Bytes written by the DMA of the device.
+    pub write_bytes: u64,
+}
+
+/// The counters of the device read at one point in time.
+#[derive(Debug, Clone)]
+pub struct TelemetrySnapshot {
+    /// When the snapshot was read, used to compute rates.
+    pub taken_at: Instant,
+    /// Wall clock time of the snapshot, for the dashboards.
+    pub timestamp: SystemTime,
+    /// The counters of the physical port.
+    pub port: PortCounters,
+    /// The driver specific counters of the port (`hw_counters`), by name.
+    pub hw_counters: BTreeMap<String, u64>,
+    /// The PCIe counters, once sampled when their data ids are read by `Telemetry::with_diag`.
+    pub pcie: Option<PcieCounters>,
+    /// The DMA counters, once sampled when their data ids are read by `Telemetry::with_diag`.
+    pub dma: Option<DmaCounters>,
+    /// The other counters read by `Telemetry::with_diag`, by data id.
+    pub diag_counters: BTreeMap<u64, u64>,
+}
+
+/// The throughput of the device between two snapshots.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TelemetryRates {
+    /// Time between the two snapshots.
+    pub interval: Duration,
+    /// Received bytes per second.
+    pub rx_bytes_per_sec: f64,
+    /// Transmitted bytes per second.
+    pub tx_bytes_per_sec: f64,
+    /// Received packets per second.
+    pub rx_packets_per_sec: f64,
+    /// Transmitted packets per second.
+    pub tx_packets_per_sec: f64,
+    /// Bytes per second the device received from PCIe, if both snapshots have the PCIe counters.
+    pub pcie_inbound_bytes_per_sec: Option<f64>,
+    /// Bytes per second the device sent to PCIe, if both snapshots have the PCIe counters.
+    pub pcie_outbound_bytes_per_sec: Option<f64>,
+    /// Bytes per second read by the DMA, if both snapshots have the DMA counters.
+    pub dma_read_bytes_per_sec: Option<f64>,
+    /// Bytes per second written by the DMA, if both snapshots have the DMA counters.
+    pub dma_write_bytes_per_sec: Option<f64>,
+}
+
+impl TelemetrySnapshot {
+    /// Compute the throughput of the device since the `prev` snapshot.
+    ///
+    /// Counters that went backwards, e.g. after a driver reset, count as zero.
+    pub fn rates_since(&self, prev: &TelemetrySnapshot) -> TelemetryRates {
+        let interval = self.taken_at.saturating_duration_since(prev.taken_at);
+        let secs = interval.as_secs_f64();
+        if secs == 0.0 {
+            return TelemetryRates {
+                interval,
+                ..Default::default()
+            };
+        }
+
+        let rate = |now: u64, before: u64| now.saturating_sub(before) as f64 / secs;
+        let pcie = self.pcie.zip(prev.pcie);
+        let dma = self.dma.zip(prev.dma);
+        TelemetryRates {
+            interval,
+            rx_bytes_per_sec: rate(self.port.rx_bytes, prev.port.rx_bytes),
+            tx_bytes_per_sec: rate(self.port.tx_bytes, prev.port.tx_bytes),
+            rx_packets_per_sec: rate(self.port.rx_packets, prev.port.rx_packets),
+            tx_packets_per_sec: rate(self.port.tx_packets, prev.port.tx_packets),
+            pcie_inbound_bytes_per_sec: pcie.map(|(n, b)| rate(n.inbound_bytes, b.inbound_bytes)),
+            pcie_outbound_bytes_per_sec: pcie
+                .map(|(n, b)| rate(n.outbound_bytes, b.outbound_bytes)),
+            dma_read_bytes_per_sec: dma.map(|(n, b)| rate(n.read_bytes, b.read_bytes)),
+            dma_write_bytes_per_sec: dma.map(|(n, b)| rate(n.write_bytes, b.write_bytes)),
+        }
+    }
+}
+
+/// Reads the counters of one port of an opened device.
+pub struct Telemetry {
+    port_dir: PathBuf,
+
+    // Declared before `dev`, so that it is destroyed before the device is closed.
+    #[cfg(all(feature = "telemetry-diag", doca_2_x))]
+    diag: Option<Diag>,
+
+    // Keep the device open while the counters are read.
+    #[allow(dead_code)]
+    dev: Arc<DevContext>,
+}
+
+impl Telemetry {
+    /// Read the counters of the first port of the device.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NOT_SUPPORTED`: the device exposes no counters.
+    ///
+    pub fn new(dev: Arc<DevContext>) -> DOCAResult<Self> {
+        Self::with_port(dev, 1)
+    }
+
+    /// Read the counters of the given port of the device, ports start from 1.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_NOT_SUPPORTED`: the port exposes no counters.
+    ///
+    pub fn with_port(dev: Arc<DevContext>, port: u32) -> DOCAResult<Self> {
+        let port_dir = Path::new(SYSFS_INFINIBAND)
+            .join(dev.ibdev_name()?)
+            .join("ports")
+            .join(port.to_string());
+
+        if !port_dir.join("counters").is_dir() {
+            return Err(DOCAError::DOCA_ERROR_NOT_SUPPORTED);
+        }
+
+        Ok(Self {
+            port_dir,
+            #[cfg(all(feature = "telemetry-diag", doca_2_x))]
+            diag: None,
+            dev,
+        })
+    }
+
+    /// Also read the DOCA Telemetry Diagnostics counters of `config` in the snapshots.
+    ///
+    /// The device samples them every [`DiagConfig::sample_period`], a snapshot
+    /// has the values of the last sample and none before the first one.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_INVALID_VALUE`: `config` has no data id.
+    ///  - `DOCA_ERROR_NOT_SUPPORTED`: the device does not support DOCA Telemetry Diagnostics.
+    ///  - Any other error returned while configuring and starting the diagnostics,
+    ///    e.g. for a data id that the device does not know.
+    ///
+    #[cfg(all(feature = "telemetry-diag", doca_2_x))]
+    pub fn with_diag(mut self, config: &DiagConfig) -> DOCAResult<Self> {
+        self.diag = Some(Diag::start(&self.dev, config)?);
+        Ok(self)
+    }
+
+    /// Read the current counters of the device.
+    ///
+    /// # Errors
+    ///
+    ///  - `DOCA_ERROR_IO_FAILED`: a counter could not be read or parsed.
+    ///  - Any error returned while querying the DOCA Telemetry Diagnostics counters.
+    ///
+    pub fn snapshot(&self) -> DOCAResult<TelemetrySnapshot> {
+        #[allow(unused_mut)]
+        let mut snapshot = read_snapshot(&self.port_dir)?;
+
+        #[cfg(all(feature = "telemetry-diag", doca_2_x))]
+        if let Some(diag) = &self.diag {
+            diag.read_into(&mut snapshot)?;
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Read a snapshot every `period` on a background thread.
+    pub fn sample_every(self, period: Duration) -> Sampler {
+        let (tx, rx) = mpsc::channel();
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+
+        let handle = std::thread::spawn(move || loop {
+            if tx.send(self.snapshot()).is_err() {
+                return;
+            }
+            match stop_rx.recv_timeout(period) {
+                Err(RecvTimeoutError::Timeout) => {}
+                _ => return,
+            }
+        });
+
+        Sampler {
+            rx,
+            stop: Some(stop_tx),
+            handle: Some(handle),
+        }
+    }
+}
+
+/// The snapshots read periodically by [`Telemetry::sample_every`].
+///
+/// The first snapshot is read immediately. The thread stops when the sampler
+/// is dropped.
+pub struct Sampler {
+    rx: Receiver<DOCAResult<TelemetrySnapshot>>,
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Sampler {
+    /// Wait for the next snapshot.
+    ///
+    /// Return `None` if the sampling thread has exited.
+    pub fn recv(&self) -> Option<DOCAResult<TelemetrySnapshot>> {
+        self.rx.recv().ok()
+    }
+
+    /// Return the next snapshot if one is ready, without blocking.
+    pub fn try_recv(&self) -> Option<DOCAResult<TelemetrySnapshot>> {
+        self.rx.try_recv().ok()
+    }
+
+    /// Drain the ready snapshots and return the most recent one, if any.
+    pub fn latest(&self) -> Option<DOCAResult<TelemetrySnapshot>> {
+        self.rx.try_iter().last()
+    }
+
+    /// Iterate over the snapshots as they are read, blocking between them.
+    pub fn iter(&self) -> impl Iterator<Item = DOCAResult<TelemetrySnapshot>> + '_ {
+        self.rx.iter()
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the thread up from its wait.
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+
+        trace!("Telemetry Sampler is dropped");
+    }
+}
+
+/// The DOCA Telemetry Diagnostics counters read by [`Telemetry::with_diag`].
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use doca::telemetry::{DiagConfig, Telemetry};
+///
+/// // The data ids of the counters, from the documentation of the device
+/// const PCIE_INBOUND_BYTES: u64 = 0x1;
+/// const PCIE_OUTBOUND_BYTES: u64 = 0x2;
+///
+/// let device = doca::device::devices().unwrap().get(0).unwrap().open().unwrap();
+/// let telemetry = Telemetry::new(device)
+///     .unwrap()
+///     .with_diag(DiagConfig::new().pcie_bytes(PCIE_INBOUND_BYTES, PCIE_OUTBOUND_BYTES))
+///     .unwrap();
+/// println!("{:?}", telemetry.snapshot().unwrap().pcie);
+/// ```
+#[cfg(all(feature = "telemetry-diag", doca_2_x))]
+#[derive(Debug, Clone)]
+pub struct DiagConfig {
+    pcie: Option<(u64, u64)>,
+    dma: Option<(u64, u64)>,
+    others: Vec<u64>,
+    sample_period: Duration,
+}
+
+#[cfg(all(feature = "telemetry-diag", doca_2_x))]
+impl Default for DiagConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(feature = "telemetry-diag", doca_2_x))]
+impl DiagConfig {
+    /// Create a config without counters, sampled every 100 ms.
+    pub fn new() -> Self {
+        Self {
+            pcie: None,
+            dma: None,
+            others: Vec::new(),
+            sample_period: DEFAULT_DIAG_SAMPLE_PERIOD,
+        }
+    }
+
+    /// Read [`TelemetrySnapshot::pcie`] from the counters of the inbound and outbound bytes.
+    pub fn pcie_bytes(&mut self, inbound_id: u64, outbound_id: u64) -> &mut Self {
+        self.pcie = Some((inbound_id, outbound_id));
+        self
+    }
+
+    /// Read [`TelemetrySnapshot::dma`] from the counters of the read and written bytes.
+    pub fn dma_bytes(&mut self, read_id: u64, write_id: u64) -> &mut Self {
+        self.dma = Some((read_id, write_id));
+        self
+    }
+
+    /// Also read the counter into [`TelemetrySnapshot::diag_counters`].
+    pub fn counter(&mut self, data_id: u64) -> &mut Self {
+        self.others.push(data_id);
+        self
+    }
+
+    /// Set how often the device samples the counters.
+    pub fn sample_period(&mut self, period: Duration) -> &mut Self {
+        self.sample_period = period;
+        self
+    }
+
+    /// The data ids in the order of the values of a sample.
+    fn data_ids(&self) -> Vec<u64> {
+        let pairs = self.pcie.iter().chain(self.dma.iter());
+        pairs
+            .flat_map(|&(first, second)| [first, second])
+            .chain(self.others.iter().copied())
+            .collect()
+    }
+
+    /// Set the counters of the snapshot from the values of a sample.
+    fn fill(&self, values: &[u64], snapshot: &mut TelemetrySnapshot) {
+        let mut values = values.iter().copied();
+        let mut next = || values.next().unwrap_or_default();
+
+        if self.pcie.is_some() {
+            snapshot.pcie = Some(PcieCounters {
+                inbound_bytes: next(),
+                outbound_bytes: next(),
+            });
+        }
+        if self.dma.is_some() {
+            snapshot.dma = Some(DmaCounters {
+                read_bytes: next(),
+                write_bytes: next(),
+            });
+        }
+        for &data_id in &self.others {
+            snapshot.diag_counters.insert(data_id, next());
+        }
+    }
+}
+
+/// The DOCA Telemetry Diagnostics counters sampled by the device.
+#[cfg(all(feature = "telemetry-diag", doca_2_x))]
+struct Diag {
+    inner: NonNull<ffi::doca_telemetry_diag>,
+    config: DiagConfig,
+    num_data_ids: usize,
+    sample_size: usize,
+    started: bool,
+    state: Mutex<DiagState>,
+}
+
+/// The samples queried from the device, and the values of the last one,
+/// kept for the snapshots read before the device takes the next sample.
+#[cfg(all(feature = "telemetry-diag", doca_2_x))]
+struct DiagState {
+    buf: Vec<u8>,
+    values: Option<Vec<u64>>,
+}
+
+// SAFETY: the diagnostics are only queried with the lock of `state` held, and
+// only configured and started before being shared, and stopped when dropped.
+#[cfg(all(feature = "telemetry-diag", doca_2_x))]
+unsafe impl Send for Diag {}
+#[cfg(all(feature = "telemetry-diag", doca_2_x))]
+unsafe impl Sync for Diag {}
+
+#[cfg(all(feature = "telemetry-diag", doca_2_x))]
+impl Drop for Diag {
+    fn drop(&mut self) {
+        if self.started {
+            let _ret = unsafe { ffi::doca_telemetry_diag_stop(self.inner.as_ptr()) };
+            trace!(error = ?_ret, "DOCA Telemetry Diagnostics is stopped");
+        }
+        unsafe { ffi::doca_telemetry_diag_destroy(self.inner.as_ptr()) };
+
+        trace!("DOCA Telemetry Diagnostics is destroyed");
+    }
+}
+
+#[cfg(all(feature = "telemetry-diag", doca_2_x))]
+impl Diag {
+    /// Configure the device to sample the counters of `config`, and start sampling.
+    fn start(dev: &DevContext, config: &DiagConfig) -> DOCAResult<Self> {
+        let data_ids = config.data_ids();
+        if data_ids.is_empty() {
+            return Err(DOCAError::DOCA_ERROR_INVALID_VALUE);
+        }
+
+        let mut inner: *mut ffi::doca_telemetry_diag = std::ptr::null_mut();
+        // do not take over the diagnostics already configured by another process
+        check(unsafe {
+            ffi::doca_telemetry_diag_create(dev.inner_ptr(), 0, &mut inner as *mut _)
+        })?;
+
+        // destroyed by the drop of `diag` if the configuration fails
+        let mut diag = Diag {
+            inner: unsafe { NonNull::new_unchecked(inner) },
+            config: config.clone(),
+            num_data_ids: data_ids.len(),
+            sample_size: 0,
+            started: false,
+            state: Mutex::new(DiagState {
+                buf: Vec::new(),
+                values: None,
+            }),
+        };
+        let raw = diag.inner.as_ptr();
+
+        let period_ns = u64::try_from(config.sample_period.as_nanos()).unwrap_or(u64::MAX);
+        check(unsafe {
+            ffi::doca_telemetry_diag_set_output_format(
+                raw,
+                ffi::DOCA_TELEMETRY_DIAG_OUTPUT_FORMAT_1,
+            )
+        })?;
+        check(unsafe {
+            ffi::doca_telemetry_diag_set_sample_mode(
+                raw,
+                ffi::DOCA_TELEMETRY_DIAG_SAMPLE_MODE_REPETITIVE,
+            )
+        })?;
+        check(unsafe { ffi::doca_telemetry_diag_set_sample_period(raw, period_ns) })?;
+        check(unsafe {
+            ffi::doca_telemetry_diag_set_log_max_num_samples(raw, DIAG_LOG_MAX_SAMPLES)
+        })?;
+        check(unsafe {
+            ffi::doca_telemetry_diag_set_max_num_data_ids(raw, data_ids.len() as u32)
+        })?;
+        // the counters keep growing, the rates are computed between the snapshots
+        check(unsafe { ffi::doca_telemetry_diag_set_data_clear(raw, 0) })?;
+        check(unsafe { ffi::doca_telemetry_diag_apply_config(raw) })?;
+
+        let mut results = vec![0_i32; data_ids.len()];
+        check(unsafe {
+            ffi::doca_telemetry_diag_apply_counters_list_by_id(
+                raw,
+                data_ids.as_ptr(),
+                data_ids.len() as u32,
+                results.as_mut_ptr(),
+            )
+        })?;
+
+        let mut sample_size = 0_u32;
+        check(unsafe {
+            ffi::doca_telemetry_diag_get_sample_size(raw, &mut sample_size as *mut _)
+        })?;
+        diag.sample_size = sample_size as usize;
+        // the values end every sample, after its id and timestamps
+        if diag.sample_size < diag.num_data_ids * std::mem::size_of::<u64>() {
+            return Err(DOCAError::DOCA_ERROR_UNEXPECTED);
+        }
+        diag.state = Mutex::new(DiagState {
+            buf: vec![0_u8; diag.sample_size << DIAG_LOG_MAX_SAMPLES],
+            values: None,
+        });
+
+        check(unsafe { ffi::doca_telemetry_diag_start(raw) })?;
+        diag.started = true;
+
+        Ok(diag)
+    }
+
+    /// Set the counters of the snapshot from the last sample of the device.
+    fn read_into(&self, snapshot: &mut TelemetrySnapshot) -> DOCAResult<()> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let state = &mut *state;
+
+        let mut num_samples = 0_u32;
+        check(unsafe {
+            ffi::doca_telemetry_diag_query_counters(
+                self.inner.as_ptr(),
+                state.buf.as_mut_ptr().cast(),
+                1 << DIAG_LOG_MAX_SAMPLES,
+                &mut num_samples as *mut _,
+            )
+        })?;
+
+        // no new sample since the last query keeps the values of the last one
+        if num_samples > 0 {
+            let end = num_samples as usize * self.sample_size;
+            let sample = &state.buf[end - self.sample_size..end];
+            state.values = Some(sample_values(sample, self.num_data_ids));
+        }
+
+        if let Some(values) = &state.values {
+            self.config.fill(values, snapshot);
+        }
+        Ok(())
+    }
+}
+
+/// Get the values of a sample of output format 1, which end the sample
+/// in the order of the data ids.
+#[cfg(all(feature = "telemetry-diag", doca_2_x))]
+fn sample_values(sample: &[u8], num_data_ids: usize) -> Vec<u64> {
+    let values = &sample[sample.len() - num_data_ids * std::mem::size_of::<u64>()..];
+    values
+        .chunks_exact(std::mem::size_of::<u64>())
+        .map(|value| u64::from_ne_bytes(value.try_into().unwrap()))
+        .collect()
+}
+
+#[cfg(all(feature = "telemetry-diag", doca_2_x))]
+fn check(ret: DOCAError) -> DOCAResult<()> {
+    if ret != DOCAError::DOCA_SUCCESS {
+        return Err(ret);
+    }
+    Ok(())
+}
+
+fn read_snapshot(port_dir: &Path) -> DOCAResult<TelemetrySnapshot> {
+    let taken_at = Instant::now();
+    let timestamp = SystemTime::now();
+
+    let counters = port_dir.join("counters");
+    let port = PortCounters {
+        rx_bytes: read_counter(&counters.join("port_rcv_data"))? * DATA_WORD_SIZE,
+        tx_bytes: read_counter(&counters.join("port_xmit_data"))? * DATA_WORD_SIZE,
+        rx_packets: read_counter(&counters.join("port_rcv_packets"))?,
+        tx_packets: read_counter(&counters.join("port_xmit_packets"))?,
+        rx_errors: read_counter(&counters.join("port_rcv_errors"))?,
+        tx_discards: read_counter(&counters.join("port_xmit_discards"))?,
+    };
+
+    let mut hw_counters = BTreeMap::new();
+    match fs::read_dir(port_dir.join("hw_counters")) {
+        Ok(entries) => {
+            for entry in entries {
+                let entry = entry.map_err(io_error)?;
+                // Some entries, e.g. `lifespan`, are settings rather than counters.
+                if let Ok(value) = read_counter(&entry.path()) {
+                    hw_counters.insert(entry.file_name().to_string_lossy().into_owned(), value);
+                }
+            }
+        }
+        // Not every port has driver specific counters.
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(io_error(e)),
+    }
+
+    Ok(TelemetrySnapshot {
+        taken_at,
+        timestamp,
+        port,
+        hw_counters,
+        pcie: None,
+        dma: None,
+        diag_counters: BTreeMap::new(),
+    })
+}
+
+fn read_counter(path: &Path) -> DOCAResult<u64> {
+    fs::read_to_string(path)
+        .map_err(io_error)?
+        .trim()
+        .parse()
+        .map_err(|_| DOCAError::DOCA_ERROR_IO_FAILED)
+}
+
+fn io_error(_: io::Error) -> DOCAError {
+    DOCAError::DOCA_ERROR_IO_FAILED
+}
+
+mod tests {
+    #[test]
+    fn test_read_snapshot_from_dir() {
+        use super::*;
+
+        let port_dir = std::env::temp_dir().join(format!("doca-telemetry-{}", std::process::id()));
+        let counters = port_dir.join("counters");
+        let hw_counters = port_dir.join("hw_counters");
+        fs::create_dir_all(&counters).unwrap();
+        fs::create_dir_all(&hw_counters).unwrap();
+
+        for (name, value) in [
+            ("port_rcv_data", "100\n"),
+            ("port_xmit_data", "200\n"),
+            ("port_rcv_packets", "3\n"),
+            ("port_xmit_packets", "4\n"),
+            ("port_rcv_errors", "0\n"),
+            ("port_xmit_discards", "1\n"),
+        ] {
+            fs::write(counters.join(name), value).unwrap();
+        }
+        fs::write(hw_counters.join("out_of_buffer"), "7\n").unwrap();
+        fs::write(hw_counters.join("lifespan"), "ten\n").unwrap();
+
+        let snapshot = read_snapshot(&port_dir).unwrap();
+        fs::remove_dir_all(&port_dir).unwrap();
+
+        assert_eq!(snapshot.port.rx_bytes, 400);
+        assert_eq!(snapshot.port.tx_bytes, 800);
+        assert_eq!(snapshot.port.tx_discards, 1);
+        assert_eq!(snapshot.hw_counters.get("out_of_buffer"), Some(&7));
+        assert!(!snapshot.hw_counters.contains_key("lifespan"));
+
+        let mut later = snapshot.clone();
+        later.taken_at += Duration::from_secs(2);
+        later.port.rx_bytes += 1000;
+        let rates = later.rates_since(&snapshot);
+        assert_eq!(rates.rx_bytes_per_sec, 500.0);
+        assert_eq!(rates.tx_bytes_per_sec, 0.0);
+        assert_eq!(rates.pcie_inbound_bytes_per_sec, None);
+
+        let mut with_pcie = later.clone();
+        with_pcie.taken_at += Duration::from_secs(1);
+        later.pcie = Some(PcieCounters::default());
+        with_pcie.pcie = Some(PcieCounters {
+            inbound_bytes: 300,
+            outbound_bytes: 0,
+        });
+        let rates = with_pcie.rates_since(&later);
+        assert_eq!(rates.pcie_inbound_bytes_per_sec, Some(300.0));
+        assert_eq!(rates.pcie_outbound_bytes_per_sec, Some(0.0));
+        assert_eq!(rates.dma_read_bytes_per_sec, None);
+    }
+
+    #[test]
+    #[cfg(all(feature = "telemetry-diag", doca_2_x))]
+    fn test_fill_snapshot_from_diag_sample() {
+        use super::*;
+
+        let mut config = DiagConfig::new();
+        config.dma_bytes(10, 11).counter(20);
+        assert_eq!(config.data_ids(), vec![10, 11, 20]);
+
+        // a sample of output format 1 starts with its id and timestamps
+        let mut sample = vec![0xff_u8; 24];
+        for value in [1000_u64, 2000, 7] {
+            sample.extend_from_slice(&value.to_ne_bytes());
+        }
+        let values = sample_values(&sample, 3);
+        assert_eq!(values, vec![1000, 2000, 7]);
+
+        let mut snapshot = TelemetrySnapshot {
+            taken_at: Instant::now(),
+            timestamp: SystemTime::now(),
+            port: PortCounters::default(),
+            hw_counters: BTreeMap::new(),
+            pcie: None,
+            dma: None,
+            diag_counters: BTreeMap::new(),
+        };
+        config.fill(&values, &mut snapshot);
+        assert_eq!(snapshot.pcie, None);
+        assert_eq!(
+            snapshot.dma,
+            Some(DmaCounters {
+                read_bytes: 1000,
+                write_bytes: 2000
+            })
+        );
+        assert_eq!(snapshot.diag_counters.get(&20), Some(&7));
+    }
+
+    #[test]
+    #[ignore = "needs a DOCA device exposing its port counters"]
+    fn test_sample_device_counters() {
+        use super::*;
+
+        let device = crate::device::devices()
+            .unwrap()
+            .get(0)
+            .unwrap()
+            .open()
+            .unwrap();
+        let telemetry = Telemetry::new(device).unwrap();
+
+        let sampler = telemetry.sample_every(Duration::from_millis(100));
+        let first = sampler.recv().unwrap().unwrap();
+        let second = sampler.recv().unwrap().unwrap();
+        assert!(second.port.rx_packets >= first.port.rx_packets);
+        assert!(second.rates_since(&first).interval >= Duration::from_millis(100));
+    }
+}